@@ -21,6 +21,7 @@ setup(
     rust_extensions=[RustExtension(
         'woods',
         'Cargo.toml',
+        features=['python'],
     )],
     install_requires=install_requires,
     setup_requires=setup_requires,
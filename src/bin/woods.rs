@@ -0,0 +1,456 @@
+//! Command-line tool for training, cross-validation and batch prediction of Woods models.
+//!
+//! ```text
+//! woods train --data train.csv --target y --model gbm --depth 5 --output model.bin.zst
+//! woods cv --data train.csv --target y --config gbm.toml --folds 5 --split shuffle --seed 0 --metric mae
+//! woods predict --model model.bin.zst --data test.csv --target y --output predictions.csv
+//! ```
+//!
+//! Data is read by [`woods::data`] loaders from CSV files with header, LibSVM files (`.svm`, `.libsvm`, `.svmlight`)
+//! or Parquet files (`parquet` feature). All columns except the target are features, labels of LibSVM files are
+//! the target. Missing values, e.g. empty values, `NA` and `NaN`, are read as `NaN`, which is supported only by prediction.
+//!
+//! Model parameters are read from TOML `--config` file, command-line flags override them:
+//! ```toml
+//! model = "gbm"        # "tree", "gbm" or "deep"
+//! dtype = "float32"    # "float32" or "float64" (default)
+//! depth = 5
+//! min_samples_split = 2
+//! n_estimators = 100
+//! learning_rate = 0.1
+//! ```
+//! Deep gradient boosting uses `n_estimators`, `layer_width` and `learning_rate`,
+//! parameters of its layers are selected by cross-validation.
+
+use std::error::Error;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use ndarray::{Array1, Array2, Axis};
+use serde::Deserialize;
+use structopt::StructOpt;
+use woods::ensemble::AverageEnsemble;
+use woods::ensemble::boosting::{GradientBoostingImpl, GradientBoostingParameters, TreeGBM};
+use woods::ensemble::deep_boosting::{DeepBoostingImpl, DeepBoostingParameters};
+use woods::data::{self, Column, ColumnRoles, CsvOptions, Dataset, LibSvmOptions};
+use woods::estimator::{ConstructibleWithArg, ConstructibleWithCopyArg, Estimator, WithInputFeatures};
+use woods::metrics::metric_by_name;
+use woods::model_selection::{cross_val_score, GroupKFold, KFold, ShuffleKFold, Splitter,
+                              TimeSeriesSplit};
+use woods::tree::{DecisionTreeImpl, TreeParameters};
+use woods::tree::rule::RandomSplitRule;
+use woods::utils::numerics::{D, Float, FLOAT_TYPES};
+use woods::utils::serialization::{self, Model, ModelHeader};
+
+type CliResult<T> = Result<T, Box<dyn Error>>;
+
+/// Available estimators.
+const MODELS: [&str; 3] = ["tree", "gbm", "deep"];
+
+/// Available cross-validation splitters.
+const SPLITTERS: [&str; 4] = ["kfold", "shuffle", "group", "time"];
+
+#[derive(Debug, Clone, Copy)]
+enum ModelKind {
+    Tree,
+    Gbm,
+    Deep,
+}
+
+impl FromStr for ModelKind {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "tree" => Ok(ModelKind::Tree),
+            "gbm" => Ok(ModelKind::Gbm),
+            "deep" => Ok(ModelKind::Deep),
+            _ => Err(format!("Incorrect model: `{}`. Please, use one of: {:?}", name, MODELS)),
+        }
+    }
+}
+
+impl ModelKind {
+    fn name(self) -> &'static str {
+        match self {
+            ModelKind::Tree => "tree",
+            ModelKind::Gbm => "gbm",
+            ModelKind::Deep => "deep",
+        }
+    }
+
+    /// Estimator of saved model kind, see [`Model::KIND`].
+    fn of_header(header: &ModelHeader) -> CliResult<Self> {
+        let kind = header.model_kind.split('[').next().unwrap_or_default();
+        if kind == <DecisionTreeImpl<RandomSplitRule>>::KIND {
+            Ok(ModelKind::Tree)
+        } else if kind == <TreeGBM>::KIND {
+            Ok(ModelKind::Gbm)
+        } else if kind == <DeepBoostingImpl<AverageEnsemble<TreeGBM>>>::KIND {
+            Ok(ModelKind::Deep)
+        } else {
+            Err(format!("Unsupported model kind: `{}`", header.model_kind).into())
+        }
+    }
+}
+
+// Model parameters, set by TOML config file or command-line flags
+// (not a doc comment: it would replace descriptions of subcommands with flattened parameters).
+#[derive(Debug, Default, Deserialize, StructOpt)]
+#[serde(default, deny_unknown_fields)]
+struct ModelConfig {
+    /// Estimator: `tree`, `gbm` or `deep` [default: gbm]
+    #[structopt(long)]
+    model: Option<String>,
+    /// Floating point type of the model: `float32` or `float64` [default: float64]
+    #[structopt(long)]
+    dtype: Option<String>,
+    /// Depth of trees
+    #[structopt(long)]
+    depth: Option<u8>,
+    /// Minimum number of samples to split tree node
+    #[structopt(long)]
+    min_samples_split: Option<usize>,
+    /// Number of candidate features of each tree split [default: 1]
+    #[structopt(long)]
+    max_features: Option<usize>,
+    /// Number of boosting iterations or deep boosting layers
+    #[structopt(long)]
+    n_estimators: Option<u32>,
+    /// Learning rate of boosting
+    #[structopt(long)]
+    learning_rate: Option<D>,
+    /// Number of models in deep boosting layer
+    #[structopt(long)]
+    layer_width: Option<u32>,
+}
+
+impl ModelConfig {
+    /// Read config from TOML file `path` and override it by set values of `self`.
+    fn with_file(self, path: Option<&Path>) -> CliResult<Self> {
+        let file: ModelConfig = match path {
+            Some(path) => toml::from_str(&std::fs::read_to_string(path)?)?,
+            None => ModelConfig::default(),
+        };
+        Ok(ModelConfig {
+            model: self.model.or(file.model),
+            dtype: self.dtype.or(file.dtype),
+            depth: self.depth.or(file.depth),
+            min_samples_split: self.min_samples_split.or(file.min_samples_split),
+            max_features: self.max_features.or(file.max_features),
+            n_estimators: self.n_estimators.or(file.n_estimators),
+            learning_rate: self.learning_rate.or(file.learning_rate),
+            layer_width: self.layer_width.or(file.layer_width),
+        })
+    }
+
+    fn kind(&self) -> CliResult<ModelKind> {
+        Ok(self.model.as_deref().unwrap_or("gbm").parse()?)
+    }
+
+    /// Check that floating point type is `float32`.
+    fn is_f32(&self) -> CliResult<bool> {
+        match self.dtype.as_deref().unwrap_or(f64::NAME) {
+            dtype if FLOAT_TYPES.contains(&dtype) => Ok(dtype == f32::NAME),
+            dtype => Err(format!("Incorrect dtype: `{}`. Please, use one of: {:?}", dtype, FLOAT_TYPES).into()),
+        }
+    }
+
+    fn tree_params(&self) -> TreeParameters {
+        let mut params = TreeParameters::new(self.depth, self.min_samples_split);
+        if let Some(max_features) = self.max_features {
+            params.max_features = max_features;
+        }
+        params
+    }
+
+    fn gbm_params(&self) -> GradientBoostingParameters<TreeParameters> {
+        GradientBoostingParameters::new(self.tree_params(), self.n_estimators, self.learning_rate)
+    }
+
+    fn deep_params(&self) -> DeepBoostingParameters {
+        let mut params = DeepBoostingParameters::new(self.n_estimators, self.layer_width, self.learning_rate);
+        if let Some(max_features) = self.max_features {
+            params.layer_search.max_features = vec![max_features];
+        }
+        params
+    }
+}
+
+/// Evaluate `$body` with `$est` bound to a new estimator of `$config` kind, type and parameters.
+macro_rules! with_estimator {
+    ($config:expr, $est:ident => $body:expr) => {
+        match ($config.kind()?, $config.is_f32()?) {
+            (ModelKind::Tree, true) => {
+                let $est = DecisionTreeImpl::<RandomSplitRule<f32>>::new($config.tree_params());
+                $body
+            },
+            (ModelKind::Tree, false) => {
+                let $est = DecisionTreeImpl::<RandomSplitRule<f64>>::new($config.tree_params());
+                $body
+            },
+            (ModelKind::Gbm, true) => {
+                let $est: TreeGBM<f32> = GradientBoostingImpl::new($config.gbm_params());
+                $body
+            },
+            (ModelKind::Gbm, false) => {
+                let $est: TreeGBM<f64> = GradientBoostingImpl::new($config.gbm_params());
+                $body
+            },
+            (ModelKind::Deep, true) => {
+                let $est = DeepBoostingImpl::<AverageEnsemble<TreeGBM<f32>>>::new($config.deep_params());
+                $body
+            },
+            (ModelKind::Deep, false) => {
+                let $est = DeepBoostingImpl::<AverageEnsemble<TreeGBM<f64>>>::new($config.deep_params());
+                $body
+            },
+        }
+    };
+}
+
+#[derive(Debug, StructOpt)]
+struct DataArgs {
+    /// Input CSV file with header, Parquet file (`.parquet`) or LibSVM file (`.svm`, `.libsvm`, `.svmlight`)
+    #[structopt(long)]
+    data: String,
+    /// CSV delimiter
+    #[structopt(long, default_value = ",")]
+    delimiter: char,
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "woods", about = "Train, evaluate and apply Woods decision tree ensembles")]
+enum Command {
+    /// Train model and save it
+    Train {
+        #[structopt(flatten)]
+        data: DataArgs,
+        /// Target column name, required for CSV and Parquet files
+        #[structopt(long)]
+        target: Option<String>,
+        /// TOML file with model parameters
+        #[structopt(long, parse(from_os_str))]
+        config: Option<PathBuf>,
+        #[structopt(flatten)]
+        params: ModelConfig,
+        /// Output model file
+        #[structopt(long, short)]
+        output: String,
+        /// Serialization format, inferred from the output file extension by default
+        #[structopt(long)]
+        format: Option<String>,
+    },
+    /// Evaluate model parameters with cross-validation
+    Cv {
+        #[structopt(flatten)]
+        data: DataArgs,
+        /// Target column name, required for CSV and Parquet files
+        #[structopt(long)]
+        target: Option<String>,
+        /// TOML file with model parameters
+        #[structopt(long, parse(from_os_str))]
+        config: Option<PathBuf>,
+        #[structopt(flatten)]
+        params: ModelConfig,
+        /// Number of folds
+        #[structopt(long, default_value = "5")]
+        folds: usize,
+        /// Splitter: `kfold`, `shuffle` (k-fold of shuffled samples), `group` (k-fold of groups)
+        /// or `time` (time series split)
+        #[structopt(long, default_value = "kfold")]
+        split: String,
+        /// Group column name for `group` splitter, groups of LibSVM files are set by `qid`
+        #[structopt(long)]
+        group: Option<String>,
+        /// Random seed of `shuffle` splitter
+        #[structopt(long)]
+        seed: Option<u64>,
+        /// Metric, e.g. `mse`, `mae`, `r2`, `quantile@0.9` or `ndcg@10` (averaged over groups)
+        #[structopt(long, default_value = "mse")]
+        metric: String,
+    },
+    /// Predict target values with saved model and write them to CSV
+    Predict {
+        #[structopt(flatten)]
+        data: DataArgs,
+        /// Model file
+        #[structopt(long)]
+        model: String,
+        /// Target column name, it is excluded from features
+        #[structopt(long)]
+        target: Option<String>,
+        /// Output CSV file, predictions are printed to stdout by default
+        #[structopt(long, short, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+}
+
+/// Feature columns of `dataset` converted to `F`.
+fn features<F: Float>(dataset: &Dataset) -> Array2<F> {
+    dataset.columns.mapv(F::from_d)
+}
+
+/// Target values of `dataset` converted to `F`.
+fn target<F: Float>(dataset: &Dataset) -> CliResult<Array1<F>> {
+    let target = dataset.target.as_ref().ok_or("Target column is not set, please use `--target`")?;
+    Ok(target.mapv(F::from_d))
+}
+
+#[cfg(feature = "parquet")]
+fn read_parquet(path: &str, roles: &ColumnRoles) -> CliResult<Dataset> {
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use parquet::record::Field;
+
+    let reader = SerializedFileReader::new(File::open(path)?)?;
+    let names: Vec<String> = reader.metadata().file_metadata().schema().get_fields().iter()
+        .map(|field| field.name().to_string())
+        .collect();
+    let mut values = vec![];
+    for row in reader.get_row_iter(None)? {
+        for (name, field) in row?.get_column_iter() {
+            values.push(match field {
+                Field::Null => D::NAN,
+                Field::Bool(v) => *v as u8 as D,
+                Field::Byte(v) => *v as D,
+                Field::Short(v) => *v as D,
+                Field::Int(v) => *v as D,
+                Field::Long(v) => *v as D,
+                Field::UByte(v) => *v as D,
+                Field::UShort(v) => *v as D,
+                Field::UInt(v) => *v as D,
+                Field::ULong(v) => *v as D,
+                Field::Float(v) => *v as D,
+                Field::Double(v) => *v,
+                _ => return Err(format!("Column `{}` is not numeric", name).into()),
+            });
+        }
+    }
+    let rows = Array2::from_shape_vec((values.len() / names.len().max(1), names.len()), values)?;
+    Ok(Dataset::from_rows(rows.view(), Some(names), roles)?)
+}
+
+#[cfg(not(feature = "parquet"))]
+fn read_parquet(_path: &str, _roles: &ColumnRoles) -> CliResult<Dataset> {
+    Err("Parquet input requires `parquet` feature".into())
+}
+
+/// Read dataset by file extension with optional `target` and `group` columns,
+/// labels of LibSVM files are always target.
+fn read_dataset(args: &DataArgs, target: Option<&str>, group: Option<&str>) -> CliResult<Dataset> {
+    let roles = ColumnRoles { target: target.map(Column::from), group: group.map(Column::from), ..ColumnRoles::default() };
+    match Path::new(&args.data).extension().and_then(|ext| ext.to_str()) {
+        Some("parquet") => read_parquet(&args.data, &roles),
+        Some("svm") | Some("libsvm") | Some("svmlight") => Ok(data::load_libsvm(&args.data, &LibSvmOptions::default())?),
+        _ => {
+            let options = CsvOptions { delimiter: args.delimiter as u8, roles, ..CsvOptions::default() };
+            Ok(data::load_csv(&args.data, &options)?)
+        },
+    }
+}
+
+/// Fit `est` on `dataset` and save it to `output`.
+fn train<E: Estimator + Model + WithInputFeatures>(mut est: E, dataset: &Dataset,
+                                                    output: &str, format: Option<&str>) -> CliResult<()> {
+    let (columns, target) = (features::<E::F>(dataset), target::<E::F>(dataset)?);
+    est.fit(&columns.view(), &target.view())?;
+    if let Some(names) = &dataset.feature_names {
+        est.input_features_mut().set_names(names.clone())?;
+    }
+    serialization::save(&est, output, format)?;
+    eprintln!("Model is trained on {} samples with {} features and saved to `{}`", target.dim(), columns.dim().0, output);
+    Ok(())
+}
+
+/// Make cross-validation splitter by name.
+fn make_splitter(split: &str, folds: usize, seed: Option<u64>) -> CliResult<Box<dyn Splitter>> {
+    match split {
+        "kfold" => Ok(Box::new(KFold::new(folds))),
+        "shuffle" => Ok(Box::new(ShuffleKFold::new(folds, seed))),
+        "group" => Ok(Box::new(GroupKFold::new(folds))),
+        "time" => Ok(Box::new(TimeSeriesSplit::new(folds))),
+        _ => Err(format!("Incorrect splitter: `{}`. Please, use one of: {:?}", split, SPLITTERS).into()),
+    }
+}
+
+/// Evaluate `est` on `dataset` by cross-validation with `splitter` and return `metric` of each fold.
+fn cross_validate<E: Estimator>(mut est: E, dataset: &Dataset, splitter: &dyn Splitter, metric: &str) -> CliResult<Array1<D>> {
+    let (columns, target) = (features::<E::F>(dataset), target::<E::F>(dataset)?);
+    let groups = dataset.groups.as_ref().map(|groups| groups.to_vec());
+    let metric = metric_by_name::<E::F>(metric)?;
+    Ok(cross_val_score(&mut est, &columns.view(), &target.view(), splitter, groups.as_deref(), metric.as_ref())?)
+}
+
+/// Load `est` from `model` file, predict target values of `dataset` samples and write them to `output` CSV.
+///
+/// Features are selected by names, stored in the model, if the dataset has column names,
+/// otherwise all columns are features in the order of training.
+fn predict<E: Estimator + Model + WithInputFeatures>(mut est: E, model: &str, dataset: &Dataset,
+                                                      output: Option<&Path>) -> CliResult<()> {
+    serialization::load(&mut est, model, None)?;
+    let columns = match (&est.input_features().names, &dataset.feature_names) {
+        (Some(names), Some(columns)) => {
+            let missing: Vec<&str> = names.iter()
+                .filter(|name| !columns.contains(name))
+                .map(String::as_str)
+                .collect();
+            if !missing.is_empty() {
+                return Err(format!("Model features are missing in data: {}", missing.join(", ")).into());
+            }
+            let indices: Vec<usize> = names.iter().filter_map(|name| columns.iter().position(|c| c == name)).collect();
+            dataset.columns.select(Axis(0), &indices).mapv(E::F::from_d)
+        },
+        _ => features::<E::F>(dataset),
+    };
+    let preds = est.predict(&columns.view())?;
+    let mut writer = match output {
+        Some(path) => csv::Writer::from_writer(Box::new(File::create(path)?) as Box<dyn std::io::Write>),
+        None => csv::Writer::from_writer(Box::new(std::io::stdout()) as Box<dyn std::io::Write>),
+    };
+    writer.write_record(["prediction"])?;
+    for pred in preds.iter() {
+        // `Debug` prints the shortest representation that is parsed to the same value
+        writer.write_record(&[format!("{:?}", pred)])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn run(command: Command) -> CliResult<()> {
+    match command {
+        Command::Train { data, target, config, params, output, format } => {
+            let config = params.with_file(config.as_deref())?;
+            let dataset = read_dataset(&data, target.as_deref(), None)?;
+            with_estimator!(config, est => train(est, &dataset, &output, format.as_deref()))
+        },
+        Command::Cv { data, target, config, params, folds, split, group, seed, metric } => {
+            let config = params.with_file(config.as_deref())?;
+            let splitter = make_splitter(&split, folds, seed)?;
+            let name = metric_by_name::<D>(&metric)?.name();
+            let dataset = read_dataset(&data, target.as_deref(), group.as_deref())?;
+            let scores = with_estimator!(config, est => cross_validate(est, &dataset, splitter.as_ref(), &metric))?;
+            for (i, score) in scores.iter().enumerate() {
+                println!("Fold {} {}: {}", i + 1, name, score);
+            }
+            println!("Cross-validation {} ({} folds): {} +- {}", name, scores.dim(), scores.mean().unwrap_or_default(), scores.std_axis(Axis(0), 0.0).into_scalar());
+            Ok(())
+        },
+        Command::Predict { data, model, target, output } => {
+            let header = serialization::read_header(&model, None)?;
+            let config = ModelConfig {
+                model: Some(ModelKind::of_header(&header)?.name().into()),
+                dtype: Some(header.dtype().into()),
+                ..ModelConfig::default()
+            };
+            let dataset = read_dataset(&data, target.as_deref(), None)?;
+            with_estimator!(config, est => predict(est, &model, &dataset, output.as_deref()))
+        },
+    }
+}
+
+fn main() {
+    if let Err(err) = run(Command::from_args()) {
+        eprintln!("Error: {}", err);
+        std::process::exit(1);
+    }
+}
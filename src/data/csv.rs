@@ -0,0 +1,106 @@
+//! Delimited text (CSV, TSV, ...) loader.
+//!
+//! Values are parsed as `f64`, [missing values](CsvOptions::missing_values) are `NaN`.
+//! Values of the group column are arbitrary strings.
+
+use std::fs::File;
+use std::io::Read;
+use ndarray::Array2;
+use crate::utils::numerics::D;
+use crate::error::{Result, WoodsError};
+use super::{group_ids, ColumnRoles, Dataset};
+
+/// Default values, which are parsed as `NaN`.
+pub const DEFAULT_MISSING_VALUES: [&str; 6] = ["", "NA", "N/A", "NaN", "nan", "null"];
+
+/// Options of CSV parsing.
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    /// The first line contains column names.
+    pub has_header: bool,
+    /// Field delimiter, e.g. `b'\t'` for TSV.
+    pub delimiter: u8,
+    /// Values which are parsed as `NaN`, leading and trailing whitespaces of values are ignored.
+    pub missing_values: Vec<String>,
+    /// Columns of target, sample weight and group.
+    pub roles: ColumnRoles,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            has_header: true,
+            delimiter: b',',
+            missing_values: DEFAULT_MISSING_VALUES.iter().map(|v| v.to_string()).collect(),
+            roles: ColumnRoles::default(),
+        }
+    }
+}
+
+impl From<::csv::Error> for WoodsError {
+    fn from(err: ::csv::Error) -> Self {
+        match err.into_kind() {
+            ::csv::ErrorKind::Io(err) => WoodsError::Io(err),
+            kind => WoodsError::InvalidData(format!("{:?}", kind)),
+        }
+    }
+}
+
+/// Read dataset from CSV `reader`.
+pub fn read_csv<R: Read>(reader: R, options: &CsvOptions) -> Result<Dataset> {
+    let mut reader = ::csv::ReaderBuilder::new()
+        .has_headers(options.has_header)
+        .delimiter(options.delimiter)
+        .trim(::csv::Trim::All)
+        .from_reader(reader);
+    let names: Option<Vec<String>> = if options.has_header {
+        Some(reader.headers()?.iter().map(String::from).collect())
+    } else {
+        None
+    };
+    // group labels are strings, they are replaced by group numbers
+    let group = match &options.roles.group {
+        Some(column) => {
+            let n_columns = match &names {
+                Some(names) => names.len(),
+                None => reader.headers()?.len(),
+            };
+            Some(column.index(names.as_deref(), n_columns)?)
+        },
+        None => None,
+    };
+    let mut values = vec![];
+    let mut labels = vec![];
+    let mut n_columns = 0;
+    for (line, record) in reader.records().enumerate() {
+        let record = record?;
+        n_columns = record.len();
+        for (i, value) in record.iter().enumerate() {
+            if Some(i) == group {
+                labels.push(value.to_string());
+                values.push(0.0);
+            } else if options.missing_values.iter().any(|m| m == value) {
+                values.push(D::NAN);
+            } else {
+                values.push(value.parse().map_err(|_| WoodsError::InvalidData(
+                    format!("incorrect value `{}` in record {}, column {}", value, line + 1, i)
+                ))?);
+            }
+        }
+    }
+    if let Some(names) = &names {
+        n_columns = names.len();
+    }
+    let mut rows = Array2::from_shape_vec((values.len() / n_columns.max(1), n_columns), values)
+        .map_err(|_| WoodsError::InvalidData("records have different lengths".into()))?;
+    if let Some(group) = group {
+        let ids = group_ids(labels.into_iter());
+        rows.column_mut(group).assign(&ids.mapv(|id| id as D));
+    }
+    Dataset::from_rows(rows.view(), names, &options.roles)
+}
+
+/// Load dataset from CSV file `filename`.
+pub fn load_csv(filename: &str, options: &CsvOptions) -> Result<Dataset> {
+    read_csv(File::open(filename)?, options)
+}
@@ -0,0 +1,105 @@
+//! LibSVM / svmlight sparse format loader.
+//!
+//! Each line is a sample `<label>[:<weight>] [qid:<group>] <index>:<value> ... [# comment]`,
+//! missing features are zeros. Groups must be set for all samples or for none of them.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use ndarray::{Array1, Array2};
+use crate::utils::numerics::D;
+use crate::error::{Result, WoodsError};
+use super::{group_ids, Dataset};
+
+/// Options of LibSVM parsing.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LibSvmOptions {
+    /// Number of features, by default the maximum feature index in file.
+    pub n_features: Option<usize>,
+    /// Feature indices start from `0`, by default they are zero-based if index `0` is in file.
+    pub zero_based: Option<bool>,
+}
+
+/// Parsed line of LibSVM file.
+struct Sample {
+    label: D,
+    weight: Option<D>,
+    group: Option<String>,
+    features: Vec<(usize, D)>,
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str, line: usize) -> Result<T> {
+    value.parse().map_err(|_| WoodsError::InvalidData(format!("incorrect value `{}` in line {}", value, line)))
+}
+
+fn parse_line(text: &str, line: usize) -> Result<Option<Sample>> {
+    let text = text.split('#').next().unwrap_or("");
+    let mut tokens = text.split_whitespace();
+    let (label, weight) = match tokens.next() {
+        Some(token) => match token.find(':') {
+            Some(colon) => (parse_number(&token[..colon], line)?, Some(parse_number(&token[colon + 1..], line)?)),
+            None => (parse_number(token, line)?, None),
+        },
+        None => return Ok(None),
+    };
+    let mut sample = Sample { label, weight, group: None, features: vec![] };
+    for token in tokens {
+        let colon = token.find(':')
+            .ok_or_else(|| WoodsError::InvalidData(format!("`{}` is not `index:value` pair in line {}", token, line)))?;
+        let (key, value) = (&token[..colon], &token[colon + 1..]);
+        if key == "qid" {
+            sample.group = Some(value.to_string());
+        } else {
+            sample.features.push((parse_number(key, line)?, parse_number(value, line)?));
+        }
+    }
+    Ok(Some(sample))
+}
+
+/// Read dataset from LibSVM `reader`, labels are target values.
+pub fn read_libsvm<R: BufRead>(reader: R, options: &LibSvmOptions) -> Result<Dataset> {
+    let mut samples = vec![];
+    for (line, text) in reader.lines().enumerate() {
+        if let Some(sample) = parse_line(&text?, line + 1)? {
+            samples.push(sample);
+        }
+    }
+    let indices = || samples.iter().flat_map(|s| s.features.iter().map(|f| f.0));
+    let zero_based = options.zero_based.unwrap_or_else(|| indices().any(|i| i == 0));
+    let offset = if zero_based { 0 } else { 1 };
+    if !zero_based && indices().any(|i| i == 0) {
+        return Err(WoodsError::InvalidData("feature index 0 in one-based file".into()));
+    }
+    let n_features = match options.n_features {
+        Some(n_features) => n_features,
+        None => indices().max().map_or(0, |i| i + 1 - offset),
+    };
+    let mut columns = Array2::zeros((n_features, samples.len()));
+    for (j, sample) in samples.iter().enumerate() {
+        for &(index, value) in &sample.features {
+            if index - offset >= n_features {
+                return Err(WoodsError::InvalidData(format!("feature index {} is out of range", index)));
+            }
+            columns[[index - offset, j]] = value;
+        }
+    }
+    let n_grouped = samples.iter().filter(|s| s.group.is_some()).count();
+    if n_grouped != 0 && n_grouped != samples.len() {
+        return Err(WoodsError::InvalidData("`qid` is set only for some samples".into()));
+    }
+    let n_weighted = samples.iter().filter(|s| s.weight.is_some()).count();
+    if n_weighted != 0 && n_weighted != samples.len() {
+        return Err(WoodsError::InvalidData("weight is set only for some samples".into()));
+    }
+    Ok(Dataset {
+        columns,
+        target: Some(samples.iter().map(|s| s.label).collect()),
+        weights: if n_weighted > 0 { Some(samples.iter().map(|s| s.weight.unwrap()).collect::<Array1<D>>()) } else { None },
+        groups: if n_grouped > 0 { Some(group_ids(samples.iter().map(|s| s.group.as_ref().unwrap()))) } else { None },
+        feature_names: None,
+    })
+}
+
+/// Load dataset from LibSVM file `filename`.
+pub fn load_libsvm(filename: &str, options: &LibSvmOptions) -> Result<Dataset> {
+    read_libsvm(BufReader::new(File::open(filename)?), options)
+}
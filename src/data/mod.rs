@@ -0,0 +1,137 @@
+//! Loading of tabular data into feature-major arrays, expected by [`Estimator::fit`](crate::estimator::Estimator::fit).
+//!
+//! Supported formats:
+//! 1. [`csv`]: delimited text with optional header and missing values.
+//! 2. [`libsvm`]: LibSVM / svmlight sparse format `<label>[:<weight>] [qid:<group>] <index>:<value> ...`.
+//!
+//! Loaders return [`Dataset`] with features of shape `(n_features, n_samples)` and optional
+//! target, sample weights and groups. Columns of CSV files are assigned to them by [`ColumnRoles`].
+//!
+//! # Example
+//! ```no_run
+//! # use woods::data::{load_csv, Column, ColumnRoles, CsvOptions};
+//! # fn main() -> woods::error::Result<()> {
+//! let options = CsvOptions {
+//!     roles: ColumnRoles { target: Some(Column::from("price")), ..ColumnRoles::default() },
+//!     ..CsvOptions::default()
+//! };
+//! let dataset = load_csv("train.csv", &options)?;
+//! println!("{} samples, {} features", dataset.n_samples(), dataset.n_features());
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use ndarray::{ArrayView2, Array1, Array2, Axis};
+use crate::utils::numerics::D;
+use crate::error::{Result, WoodsError};
+
+pub mod csv;
+pub mod libsvm;
+
+pub use self::csv::{load_csv, read_csv, CsvOptions, DEFAULT_MISSING_VALUES};
+pub use self::libsvm::{load_libsvm, read_libsvm, LibSvmOptions};
+
+/// Features with optional target, sample weights and groups.
+#[derive(Debug, Clone)]
+pub struct Dataset {
+    /// Features in standard layout with shape `(n_features, n_samples)`.
+    pub columns: Array2<D>,
+    /// Target values, `None` if target is not selected.
+    pub target: Option<Array1<D>>,
+    /// Sample weights.
+    pub weights: Option<Array1<D>>,
+    /// Group of each sample, groups are numbered from `0` in order of first appearance.
+    pub groups: Option<Array1<usize>>,
+    /// Names of features, e.g. CSV header.
+    pub feature_names: Option<Vec<String>>,
+}
+
+impl Dataset {
+    pub fn n_samples(&self) -> usize {
+        self.columns.dim().1
+    }
+
+    pub fn n_features(&self) -> usize {
+        self.columns.dim().0
+    }
+
+    /// Make dataset from `rows` with shape `(n_samples, n_columns)` and optional column `names`.
+    ///
+    /// Columns are assigned by `roles`, all other columns are features.
+    /// Groups are numbered by distinct values of group column.
+    pub fn from_rows(rows: ArrayView2<'_, D>, names: Option<Vec<String>>, roles: &ColumnRoles) -> Result<Self> {
+        let n_columns = rows.dim().1;
+        let names = names.as_deref();
+        if let Some(names) = names {
+            if names.len() != n_columns {
+                return Err(WoodsError::ShapeMismatch { what: "column names", expected: n_columns, found: names.len() });
+            }
+        }
+        let resolve = |column: &Option<Column>| column.as_ref().map(|c| c.index(names, n_columns)).transpose();
+        let (target, weight, group) = (resolve(&roles.target)?, resolve(&roles.weight)?, resolve(&roles.group)?);
+        let ignored = roles.ignore.iter().map(|c| c.index(names, n_columns)).collect::<Result<Vec<_>>>()?;
+        let features: Vec<usize> = (0..n_columns)
+            .filter(|i| ![target, weight, group].contains(&Some(*i)) && !ignored.contains(i))
+            .collect();
+        let column = |i: usize| rows.column(i).to_owned();
+        Ok(Dataset {
+            columns: rows.select(Axis(1), &features).reversed_axes().as_standard_layout().into_owned(),
+            target: target.map(column),
+            weights: weight.map(column),
+            groups: group.map(|i| group_ids(rows.column(i).iter().map(|v| v.to_bits()))),
+            feature_names: names.map(|names| features.iter().map(|i| names[*i].clone()).collect()),
+        })
+    }
+}
+
+/// Number groups by distinct `labels` in order of first appearance.
+fn group_ids<L: std::hash::Hash + Eq, I: Iterator<Item = L>>(labels: I) -> Array1<usize> {
+    let mut ids = HashMap::new();
+    labels.map(|label| {
+        let next_id = ids.len();
+        *ids.entry(label).or_insert(next_id)
+    }).collect()
+}
+
+/// Column of a table, selected by name or by index.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Column {
+    Name(String),
+    Index(usize),
+}
+
+impl From<&str> for Column {
+    fn from(name: &str) -> Self {
+        Column::Name(name.into())
+    }
+}
+
+impl From<usize> for Column {
+    fn from(index: usize) -> Self {
+        Column::Index(index)
+    }
+}
+
+impl Column {
+    /// Index of the column in table with `n_columns` columns and optional `names`.
+    fn index(&self, names: Option<&[String]>, n_columns: usize) -> Result<usize> {
+        match self {
+            Column::Index(index) if *index < n_columns => Ok(*index),
+            Column::Index(index) => Err(WoodsError::InvalidData(format!("column index {} is out of range", index))),
+            Column::Name(name) => names.and_then(|names| names.iter().position(|n| n == name))
+                .ok_or_else(|| WoodsError::InvalidData(format!("column `{}` is not found", name))),
+        }
+    }
+}
+
+/// Roles of table columns: target, sample weight and group, ignored columns.
+///
+/// All other columns are features.
+#[derive(Debug, Clone, Default)]
+pub struct ColumnRoles {
+    pub target: Option<Column>,
+    pub weight: Option<Column>,
+    pub group: Option<Column>,
+    pub ignore: Vec<Column>,
+}
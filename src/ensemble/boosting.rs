@@ -1,11 +1,20 @@
-use ndarray::{ArrayView2, ArrayView1, Array1, Array2, Axis};
+use ndarray::{ArrayView2, ArrayView1, Array1, Array2};
 use average::Mean;
-use crate::estimator::{Estimator, ConstructibleWithCopyArg};
+use crate::estimator::{check_fit_input, Estimator, ConstructibleWithCopyArg, FeatureImportance, ImportanceType, PredictContributions,
+                       InputFeatures, SparseEstimator, WithInputFeatures, WithParameters};
 use crate::tree::rule::RandomSplitRule;
-use crate::utils::numerics::D;
+use crate::inspection::PartialDependenceRecursion;
+use crate::metrics::Metric;
+use crate::export::{AsWeightedTrees, WeightedTrees};
+use crate::export::codegen::{AsTreeSum, TreeSum};
+use crate::utils::numerics::{D, Float};
+use crate::error::{Result, WoodsError};
 use crate::tree::{TreeParameters, DecisionTreeImpl};
+use crate::utils::serialization::Model;
+use crate::utils::sparse::SparseColumns;
 use serde::{Serialize, Deserialize};
-use super::{Ensemble, EstimatorsCollection};
+use serde::de::DeserializeOwned;
+use super::{stack_predictions, Ensemble, EstimatorsCollection};
 
 #[derive(Serialize, Deserialize, Clone, Copy)]
 pub struct GradientBoostingParameters<EstParams: Copy> {
@@ -27,46 +36,268 @@ impl<E: Copy> GradientBoostingParameters<E> {
     }
 }
 
+impl<E: Copy + Default> Default for GradientBoostingParameters<E> {
+    fn default() -> Self {
+        GradientBoostingParameters::new(E::default(), None, None)
+    }
+}
+
+/// Gradient boosting of base estimators, floating point type is the type of base estimators ([`Estimator::F`]).
 #[derive(Serialize, Deserialize)]
-pub struct GradientBoostingImpl<Est, EstParams: Copy> {
+#[serde(bound(serialize = "Est: Estimator + Serialize, EstParams: Serialize",
+              deserialize = "Est: Estimator + Deserialize<'de>, EstParams: Deserialize<'de>"))]
+pub struct GradientBoostingImpl<Est: Estimator, EstParams: Copy> {
     params: GradientBoostingParameters<EstParams>,
     estimators: Vec<Est>,
-    mean: D,
+    mean: Est::F,
+    #[serde(default)]
+    features: InputFeatures,
 }
 
-impl<T, P: Copy> ConstructibleWithCopyArg for GradientBoostingImpl<T, P> {
+impl<T: Estimator, P: Copy> ConstructibleWithCopyArg for GradientBoostingImpl<T, P> {
     type Arg = GradientBoostingParameters<P>;
     fn new(params: Self::Arg) -> Self {
         GradientBoostingImpl {
             params: params,
             estimators: vec![],
-            mean: D::default(),
+            mean: T::F::default(),
+            features: InputFeatures::default(),
         }
     }
 }
 
+impl<E: Estimator, P: Copy> GradientBoostingImpl<E, P> {
+    /// Fitted base estimator `i`, `None` if there is no such estimator.
+    pub fn estimator(&self, i: usize) -> Option<&E> {
+        self.estimators.get(i)
+    }
+
+    /// Learning rate, converted to the floating point type of the model.
+    fn learning_rate(&self) -> E::F {
+        E::F::from_d(self.params.learning_rate)
+    }
+}
+
 impl<E, P: Copy> Estimator for GradientBoostingImpl<E, P>
     where E: Estimator + ConstructibleWithCopyArg<Arg=P> {
-    fn fit(&mut self, columns: &ArrayView2<'_, D>, target: &ArrayView1<'_, D>) {
+    type F = E::F;
+
+    fn fit_unchecked(&mut self, columns: &ArrayView2<'_, E::F>, target: &ArrayView1<'_, E::F>) -> Result<()> {
+        self.fit_with(target, |est, cur_target| {
+            est.fit_unchecked(columns, cur_target)?;
+            est.predict(columns)
+        })?;
+        self.features = InputFeatures::of(columns);
+        Ok(())
+    }
+
+    fn predict(&self, columns: &ArrayView2<'_, E::F>) -> Result<Array1<E::F>> {
+        self.features.check(columns)?;
+        self.predict_ensemble(columns)
+    }
+}
+
+impl<E, P: Copy> GradientBoostingImpl<E, P>
+    where E: Estimator + ConstructibleWithCopyArg<Arg=P> {
+    /// Fit base estimators to residuals of `target` by `fit_predict`, which fits estimator and
+    /// returns its predictions on training data.
+    fn fit_with<T>(&mut self, target: &ArrayView1<'_, E::F>, fit_predict: T) -> Result<()>
+        where T: Fn(&mut E, &ArrayView1<'_, E::F>) -> Result<Array1<E::F>> {
         self.estimators.clear();
 
-        let average: Mean = target.iter().collect();
-        self.mean = average.mean();
-        let mut cur_target: Array1<D> = target.iter().map(|t| t - self.mean).collect();
+        let average: Mean = target.iter().map(|t| t.to_d()).collect();
+        self.mean = E::F::from_d(average.mean());
+        let mut cur_target: Array1<E::F> = target.iter().map(|t| *t - self.mean).collect();
         
         for it in 0..self.params.n_estimators {
             let mut est = E::new(self.params.est_params);
-            est.fit(columns, &cur_target.view());
-            let preds = est.predict(columns);
+            let preds = fit_predict(&mut est, &cur_target.view())?;
             if it != self.params.n_estimators - 1 {
-                cur_target = cur_target - preds * self.params.learning_rate;
+                cur_target = cur_target - preds * self.learning_rate();
             }
             self.estimators.push(est);
         }
+        Ok(())
     }
 
-    fn predict(&self, columns: &ArrayView2<'_, D>) -> Array1<D> {
-        self.predict_ensemble(columns)
+    /// Fit with early stopping on validation data: base estimators are added until validation loss of `metric`
+    /// (see [`Metric::loss`]) doesn't improve for `patience` iterations, estimators after the best iteration are removed.
+    ///
+    /// At most `n_estimators` estimators are fitted, returns validation `metric` after each iteration.
+    pub fn fit_early_stopping<M: Metric<E::F> + ?Sized>(
+            &mut self,
+            columns: &ArrayView2<'_, E::F>,
+            target: &ArrayView1<'_, E::F>,
+            val_columns: &ArrayView2<'_, E::F>,
+            val_target: &ArrayView1<'_, E::F>,
+            metric: &M,
+            patience: u32
+        ) -> Result<Array1<D>> {
+        check_fit_input(columns, target)?;
+        check_fit_input(val_columns, val_target)?;
+        if val_columns.dim().0 != columns.dim().0 {
+            return Err(WoodsError::ShapeMismatch { what: "features", expected: columns.dim().0, found: val_columns.dim().0 });
+        }
+        if patience == 0 {
+            return Err(WoodsError::InvalidParameter("early stopping patience must be positive".into()));
+        }
+        self.estimators.clear();
+
+        let average: Mean = target.iter().map(|t| t.to_d()).collect();
+        self.mean = E::F::from_d(average.mean());
+        let mut cur_target: Array1<E::F> = target.iter().map(|t| *t - self.mean).collect();
+        let mut val_preds = Array1::from_elem(val_target.dim(), self.mean);
+        let mut scores = vec![];
+        // iteration and loss of the best validation predictions
+        let (mut best_it, mut best_loss) = (0, D::INFINITY);
+
+        for it in 0..self.params.n_estimators as usize {
+            let mut est = E::new(self.params.est_params);
+            est.fit_unchecked(columns, &cur_target.view())?;
+            cur_target = cur_target - est.predict(columns)? * self.learning_rate();
+            val_preds = val_preds + est.predict(val_columns)? * self.learning_rate();
+            self.estimators.push(est);
+
+            let score = metric.evaluate(&val_preds.view(), val_target, None);
+            let loss = if metric.greater_is_better() { -score } else { score };
+            scores.push(score);
+            if loss < best_loss {
+                best_it = it;
+                best_loss = loss;
+            } else if it - best_it >= patience as usize {
+                break;
+            }
+        }
+        self.estimators.truncate(best_it + 1);
+        self.features = InputFeatures::of(columns);
+        Ok(Array1::from(scores))
+    }
+}
+
+impl<E, P: Copy> SparseEstimator for GradientBoostingImpl<E, P>
+    where E: SparseEstimator + ConstructibleWithCopyArg<Arg=P> {
+    fn fit_sparse_unchecked(&mut self, columns: &SparseColumns<E::F>, target: &ArrayView1<'_, E::F>) -> Result<()> {
+        self.fit_with(target, |est, cur_target| {
+            est.fit_sparse_unchecked(columns, cur_target)?;
+            est.predict_sparse(columns)
+        })?;
+        self.features = InputFeatures::with_n_features(columns.n_features());
+        Ok(())
+    }
+
+    fn predict_sparse(&self, columns: &SparseColumns<E::F>) -> Result<Array1<E::F>> {
+        self.features.check_n_features(columns.n_features())?;
+        let preds: Vec<Array1<E::F>> = self.estimators.iter()
+            .map(|est| est.predict_sparse(columns))
+            .collect::<Result<_>>()?;
+        Ok(self.predict_by_all(&stack_predictions(&preds)?.view()))
+    }
+}
+
+impl<E: Estimator, P: Copy> WithInputFeatures for GradientBoostingImpl<E, P> {
+    fn input_features(&self) -> &InputFeatures {
+        &self.features
+    }
+
+    fn input_features_mut(&mut self) -> &mut InputFeatures {
+        &mut self.features
+    }
+}
+
+impl<E: Estimator, P: Copy> WithParameters for GradientBoostingImpl<E, P> {
+    type Params = GradientBoostingParameters<P>;
+
+    fn params(&self) -> &GradientBoostingParameters<P> {
+        &self.params
+    }
+
+    fn params_mut(&mut self) -> &mut GradientBoostingParameters<P> {
+        &mut self.params
+    }
+}
+
+impl<E: Estimator, P: Copy> Model for GradientBoostingImpl<E, P> where Self: Serialize + DeserializeOwned {
+    const KIND: &'static str = "GradientBoosting";
+    const DTYPE: &'static str = E::F::NAME;
+
+    fn feature_names(&self) -> Option<Vec<String>> {
+        self.features.names.clone()
+    }
+}
+
+impl<E: Estimator + FeatureImportance, P: Copy> FeatureImportance for GradientBoostingImpl<E, P> {
+    fn feature_importances(&self, n_features: usize, importance_type: ImportanceType) -> Result<Array1<D>> {
+        self.features.check_n_features(n_features)?;
+        self.estimators.iter().try_fold(Array1::zeros(n_features), |acc, est| {
+            Ok(acc + est.feature_importances(n_features, importance_type)?)
+        })
+    }
+}
+
+impl<E, P: Copy> PredictContributions for GradientBoostingImpl<E, P>
+    where E: PredictContributions + ConstructibleWithCopyArg<Arg=P> {
+    fn predict_contributions(&self, columns: &ArrayView2<'_, E::F>) -> Result<Array2<D>> {
+        self.features.check(columns)?;
+        let (n_features, n_samples) = columns.dim();
+        let mut contributions = Array2::zeros((n_samples, n_features + 1));
+        for est in &self.estimators {
+            contributions = contributions + est.predict_contributions(columns)?;
+        }
+        contributions *= self.params.learning_rate;
+        contributions.column_mut(n_features).map_inplace(|bias| *bias += self.mean.to_d());
+        Ok(contributions)
+    }
+}
+
+impl<E, P: Copy> PartialDependenceRecursion for GradientBoostingImpl<E, P>
+    where E: PartialDependenceRecursion + ConstructibleWithCopyArg<Arg=P> {
+    fn partial_dependence_recursion(&self, features: &[usize], grid: &ArrayView2<'_, E::F>) -> Array1<E::F> {
+        self.estimators.iter().fold(Array1::zeros(grid.dim().0), |acc, est| {
+            acc + est.partial_dependence_recursion(features, grid)
+        }) * self.learning_rate() + self.mean
+    }
+}
+
+impl<E: Estimator + AsWeightedTrees, P: Copy> AsWeightedTrees for GradientBoostingImpl<E, P> {
+    /// Learning rate is folded into tree weights and mean target value into base value.
+    fn weighted_trees(&self) -> Result<WeightedTrees> {
+        if self.estimators.is_empty() {
+            return Err(WoodsError::NotFitted);
+        }
+        let learning_rate = self.params.learning_rate;
+        let mut ensemble = WeightedTrees {
+            features: self.features.clone(),
+            base_value: self.mean.to_d(),
+            trees: vec![],
+        };
+        for est in &self.estimators {
+            let est_trees = est.weighted_trees()?;
+            ensemble.base_value += learning_rate * est_trees.base_value;
+            ensemble.trees.extend(est_trees.trees.into_iter().map(|(weight, nodes)| (learning_rate * weight, nodes)));
+        }
+        Ok(ensemble)
+    }
+}
+
+impl<E: Estimator + AsTreeSum, P: Copy> AsTreeSum for GradientBoostingImpl<E, P> {
+    /// Base estimators must be single trees.
+    fn tree_sum(&self) -> Result<TreeSum> {
+        if self.estimators.is_empty() {
+            return Err(WoodsError::NotFitted);
+        }
+        let mut trees = Vec::with_capacity(self.estimators.len());
+        for est in &self.estimators {
+            let est_sum = est.tree_sum()?;
+            if est_sum.boosting.is_some() {
+                return Err(WoodsError::UnsupportedModel("boosting of tree ensembles".into()));
+            }
+            trees.extend(est_sum.trees);
+        }
+        Ok(TreeSum {
+            features: self.features.clone(),
+            trees,
+            boosting: Some((self.params.learning_rate, self.mean.to_d())),
+        })
     }
 }
 
@@ -79,13 +310,17 @@ impl<E, P: Copy> Ensemble for GradientBoostingImpl<E, P>
         )
     }
 
-    fn predict_all(&self, columns: &ArrayView2<'_, D>) -> Array2<D> {
+    fn predict_all(&self, columns: &ArrayView2<'_, E::F>) -> Result<Array2<E::F>> {
         self.estimators.make_predictions(columns)
     }
 
-    fn predict_by_all(&self, preds: &ArrayView2<'_, D>) -> Array1<D> {
-        preds.sum_axis(Axis(0)) * self.params.learning_rate + self.mean
+    /// Tree predictions are summed sequentially, not pairwise like `sum_axis`,
+    /// so generated code reproduces them exactly, see [`codegen`](crate::export::codegen).
+    fn predict_by_all(&self, preds: &ArrayView2<'_, E::F>) -> Array1<E::F> {
+        let sum = preds.outer_iter().fold(Array1::zeros(preds.dim().1), |sum, p| sum + p);
+        sum * self.learning_rate() + self.mean
     }
 }
 
-pub type TreeGBM = GradientBoostingImpl<DecisionTreeImpl<RandomSplitRule>, TreeParameters>;
+/// Gradient boosting of decision trees with values of floating point type `F`.
+pub type TreeGBM<F = D> = GradientBoostingImpl<DecisionTreeImpl<RandomSplitRule<F>>, TreeParameters>;
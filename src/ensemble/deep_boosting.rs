@@ -1,19 +1,30 @@
-use ndarray::{ArrayView2, ArrayView1, Array1, Axis, Array2, stack};
-use crate::utils::numerics::{D, NonNan};
+use ndarray::{ArrayView2, ArrayView1, Array1, Axis, Array2, stack, s};
+use crate::utils::numerics::{D, Float, NonNan};
+use crate::error::{Result, WoodsError};
 use crate::tree::TreeParameters;
 use super::boosting::{GradientBoostingParameters, TreeGBM};
+use crate::utils::serialization::Model;
 use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
 use itertools::iproduct;
 use rayon::prelude::*;
 use rayon::iter::ParallelBridge;
 use crate::estimator::*;
+use crate::metrics::metric_by_name;
+use crate::model_selection::{cross_val_score, KFold};
 use crate::ensemble::*;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct DeepBoostingParameters {
     pub n_estimators: u32,
     pub layer_width: u32,
     pub learning_rate: D,
+    /// Search space of gradient boosting parameters of each layer.
+    #[serde(default)]
+    pub layer_search: LayerSearch,
+    /// Fixed gradient boosting parameters of all layers, the search is skipped if they are set.
+    #[serde(default)]
+    pub layer_params: Option<TreeGBMParams>,
 }
 
 const DEFAULT_DGBM_N_ESTIMATORS: u32 = 5u32;
@@ -26,48 +37,107 @@ impl DeepBoostingParameters {
             n_estimators:   n_estimators.unwrap_or(DEFAULT_DGBM_N_ESTIMATORS),
             layer_width:     layer_width.unwrap_or(DEFAULT_DGBM_LAYER_WIDTH),
             learning_rate: learning_rate.unwrap_or(DEFAULT_DGBM_LEARNING_RATE),
+            layer_search: LayerSearch::default(),
+            layer_params: None,
         }
     }
 }
 
+impl Default for DeepBoostingParameters {
+    fn default() -> Self {
+        DeepBoostingParameters::new(None, None, None)
+    }
+}
+
 type TreeGBMParams = GradientBoostingParameters<TreeParameters>;
 
-pub trait WithBestParameters {
+/// Grid search space of gradient boosting parameters of each deep boosting layer.
+///
+/// Each combination of `depth`, `max_features`, `n_estimators` and `learning_rate` is evaluated by `n_folds`-fold
+/// cross-validation ([`KFold`]) on the layer input, the combination with the best mean `metric` is used.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LayerSearch {
+    /// Max depths of trees
+    pub depth: Vec<u8>,
+    /// Numbers of candidate features of tree splits, see [`TreeParameters::max_features`]
+    #[serde(default = "default_search_max_features")]
+    pub max_features: Vec<usize>,
+    /// Numbers of boosting iterations
+    pub n_estimators: Vec<u32>,
+    /// Learning rates of boosting
+    pub learning_rate: Vec<D>,
+    /// Number of cross-validation folds
+    pub n_folds: usize,
+    /// Metric name, see [`metric_by_name`].
+    pub metric: String,
+}
+
+fn default_search_max_features() -> Vec<usize> {
+    vec![TreeParameters::default().max_features]
+}
+
+impl Default for LayerSearch {
+    fn default() -> Self {
+        LayerSearch {
+            depth: vec![2, 3, 5],
+            max_features: default_search_max_features(),
+            n_estimators: vec![100, 1000],
+            learning_rate: vec![0.1, 0.01],
+            n_folds: 5,
+            metric: "mse".into(),
+        }
+    }
+}
+
+pub trait WithBestParameters: Estimator {
     type Params;
-    fn cv_best_params(columns: &ArrayView2<'_, D>, target: &ArrayView1<'_, D>) -> Self::Params;
+    type Search;
+    fn cv_best_params(columns: &ArrayView2<'_, Self::F>, target: &ArrayView1<'_, Self::F>,
+                      search: &Self::Search) -> Result<Self::Params>;
 }
 
-impl WithBestParameters for TreeGBM {
+impl<F: Float> WithBestParameters for TreeGBM<F> {
     type Params = TreeGBMParams;
-    fn cv_best_params(columns: &ArrayView2<'_, D>, target: &ArrayView1<'_, D>) -> TreeGBMParams {
-        let depth = vec![2, 3, 5];
-        let n_epochs = vec![100, 1000];
-        let learning_rate = vec![0.1, 0.01];
-        let best = iproduct!(depth.iter(), n_epochs.iter(), learning_rate.iter())
+    type Search = LayerSearch;
+    fn cv_best_params(columns: &ArrayView2<'_, F>, target: &ArrayView1<'_, F>,
+                      search: &LayerSearch) -> Result<TreeGBMParams> {
+        let metric = metric_by_name::<F>(&search.metric)?;
+        let best = iproduct!(search.depth.iter(), search.max_features.iter(),
+                             search.n_estimators.iter(), search.learning_rate.iter())
             .par_bridge() // compute in parallel
             .map(|p| {
-                let (d, n, lr) = p;
-                let tree_params = TreeParameters::new(Some(*d), None);
+                let (d, m, n, lr) = p;
+                let mut tree_params = TreeParameters::new(Some(*d), None);
+                tree_params.max_features = *m;
                 let params = GradientBoostingParameters::new(tree_params, Some(*n), Some(*lr));
-                let mut est = TreeGBM::new(params);
-                let score = eval_est_cv(&mut est, 5, columns, target);
-                (p, NonNan::from(score))
+                let mut est = TreeGBM::<F>::new(params);
+                let scores = cross_val_score(&mut est, columns, target, &KFold::new(search.n_folds), None, metric.as_ref())?;
+                let score = scores.mean().unwrap_or_default();
+                let loss = if metric.greater_is_better() { -score } else { score };
+                // undefined metric, e.g. AUC of one class, is the worst
+                Ok((p, NonNan::from(if loss.is_nan() { D::INFINITY } else { loss })))
              })
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
             .min_by_key(|a| {
                 a.1.clone()
              })
             .map(|a| a.0)
-            .unwrap();
-        let tree_params = TreeParameters::new(Some(*best.0), None);
-        let params = GradientBoostingParameters::new(tree_params, Some(*best.1), Some(*best.2));
-        params
+            .ok_or_else(|| WoodsError::InvalidParameter("layer parameters search space is empty".into()))?;
+        let mut tree_params = TreeParameters::new(Some(*best.0), None);
+        tree_params.max_features = *best.1;
+        let params = GradientBoostingParameters::new(tree_params, Some(*best.2), Some(*best.3));
+        Ok(params)
     }
 }
 
+/// Deep gradient boosting, floating point type is the type of ensembles ([`Estimator::F`]).
 #[derive(Serialize, Deserialize)]
 pub struct DeepBoostingImpl<EnsembleEst> {
     pub params: DeepBoostingParameters,
     estimators: Vec<EnsembleEst>,
+    #[serde(default)]
+    features: InputFeatures,
 }
 
 // impl DeepBoostingImpl<AverageEnsemble<TreeGBM>> {
@@ -78,6 +148,7 @@ impl<E: Ensemble> ConstructibleWithArg for DeepBoostingImpl<E> {
         DeepBoostingImpl {
             params: params,
             estimators: vec![],
+            features: InputFeatures::default(),
         }
     }
 }
@@ -90,26 +161,31 @@ impl<E: Ensemble> ConstructibleWithArg for DeepBoostingImpl<E> {
 // impl<T, E> Estimator for DeepBoostingImpl<E>
 //     where T: Estimator + WithBestParameters + ConstructibleWithRcArg,
 //           E: Ensemble {
-impl Estimator for DeepBoostingImpl<AverageEnsemble<TreeGBM>> {
-    fn fit(&mut self, columns: &ArrayView2<'_, D>, target: &ArrayView1<'_, D>) {
+impl<F: Float> Estimator for DeepBoostingImpl<AverageEnsemble<TreeGBM<F>>> {
+    type F = F;
+
+    fn fit_unchecked(&mut self, columns: &ArrayView2<'_, F>, target: &ArrayView1<'_, F>) -> Result<()> {
         self.estimators.clear();
 
-        let mut acc_columns: Array2<D> = columns.to_owned(); // accumulated columns
+        let mut acc_columns: Array2<F> = columns.to_owned(); // accumulated columns
         // copy target
-        let mut cur_target: Array1<D> = target.to_owned();
+        let mut cur_target: Array1<F> = target.to_owned();
         
         for it in 0..self.params.n_estimators {
             // find locally optimal GBM parameters
             // let opt_params = Rc::new(T::cv_best_params(&acc_columns.view(), &cur_target.view()));
-            let opt_params = TreeGBM::cv_best_params(&acc_columns.view(), &cur_target.view());
+            let opt_params = match self.params.layer_params {
+                Some(params) => params,
+                None => TreeGBM::<F>::cv_best_params(&acc_columns.view(), &cur_target.view(), &self.params.layer_search)?,
+            };
             let mut ensemble = AverageEnsemble::make(self.params.layer_width, opt_params);
             // let mut ensemble = E::new(self.params.layer_width, opt_params);
 
             // fit ensemble on generated features
-            ensemble.fit(&acc_columns.view(), &cur_target.view());
+            ensemble.fit_unchecked(&acc_columns.view(), &cur_target.view())?;
             // predict with ensemble
             // let preds = ensemble.predict(&acc_columns.view());
-            let all_preds = ensemble.predict_all(&acc_columns.view());
+            let all_preds = ensemble.predict_all(&acc_columns.view())?;
             let preds = ensemble.predict_by_all(&all_preds.view());
             
             // append new features
@@ -119,35 +195,161 @@ impl Estimator for DeepBoostingImpl<AverageEnsemble<TreeGBM>> {
             // update target
             if it != self.params.n_estimators - 1 {
                 cur_target = cur_target - preds * if it == 0 {
-                    1.0 as D
+                    F::one()
                 } else {
-                    self.params.learning_rate
+                    F::from_d(self.params.learning_rate)
                 };
             }
             self.estimators.push(ensemble);
         }
+        self.features = InputFeatures::of(columns);
+        Ok(())
     }
 
-    fn predict(&self, columns: &ArrayView2<'_, D>) -> Array1<D> {
+    fn predict(&self, columns: &ArrayView2<'_, F>) -> Result<Array1<F>> {
+        self.features.check(columns)?;
         // let mut predictions: Array1<D> = self.estimators.first().unwrap().predict(columns);
         // let mut acc_columns: Array2<D> = stack(Axis(0), &[columns.to_owned().view(),
         //                                     predictions.broadcast((1, predictions.dim())).unwrap()]).unwrap();
-        let first_est = self.estimators.first().unwrap();
+        let first_est = self.estimators.first().ok_or(WoodsError::NotFitted)?;
         // let input_view: ArrayView2<'_, D> = columns.clone();
-        let all_preds: Array2<D> = first_est.predict_all(&columns);
-        let mut acc_columns: Array2<D> = stack(Axis(0), &[columns.to_owned().view(), all_preds.view()]).unwrap();
-        let mut predictions: Array1<D> = first_est.predict_by_all(&all_preds.view());
+        let all_preds: Array2<F> = first_est.predict_all(&columns)?;
+        let mut acc_columns: Array2<F> = stack(Axis(0), &[columns.to_owned().view(), all_preds.view()]).unwrap();
+        let mut predictions: Array1<F> = first_est.predict_by_all(&all_preds.view());
 
         for est in self.estimators.iter().skip(1) {
             // let cur_preds = est.predict(&acc_columns.view());
-            let cur_all_preds = est.predict_all(&acc_columns.view());
+            let cur_all_preds = est.predict_all(&acc_columns.view())?;
             let cur_preds = est.predict_by_all(&cur_all_preds.view());
             // it not needed for the last iteration
             // acc_columns = stack(Axis(0), &[acc_columns.view(), cur_preds.broadcast((1, cur_preds.dim())).unwrap()]).unwrap();
             acc_columns = stack(Axis(0), &[acc_columns.view(), cur_all_preds.view()]).unwrap();
             // predictions = predictions + cur_preds.mapv(|v| self.params.learning_rate * v);
-            predictions = predictions + cur_preds * self.params.learning_rate;
+            predictions = predictions + cur_preds * F::from_d(self.params.learning_rate);
+        }
+        Ok(predictions)
+    }
+}
+
+impl<E> WithInputFeatures for DeepBoostingImpl<E> {
+    fn input_features(&self) -> &InputFeatures {
+        &self.features
+    }
+
+    fn input_features_mut(&mut self) -> &mut InputFeatures {
+        &mut self.features
+    }
+}
+
+impl<E> WithParameters for DeepBoostingImpl<E> {
+    type Params = DeepBoostingParameters;
+
+    fn params(&self) -> &DeepBoostingParameters {
+        &self.params
+    }
+
+    fn params_mut(&mut self) -> &mut DeepBoostingParameters {
+        &mut self.params
+    }
+}
+
+impl<E: Estimator> Model for DeepBoostingImpl<E> where Self: Serialize + DeserializeOwned {
+    const KIND: &'static str = "DeepGradientBoosting";
+    const DTYPE: &'static str = E::F::NAME;
+
+    fn feature_names(&self) -> Option<Vec<String>> {
+        self.features.names.clone()
+    }
+}
+
+impl<F: Float> FeatureImportance for DeepBoostingImpl<AverageEnsemble<TreeGBM<F>>> {
+    /// Calculate importances of original input features.
+    /// 
+    /// Importance of each generated (stacked) feature is attributed back to the original features
+    /// proportionally to importances of the estimator that generated it.
+    fn feature_importances(&self, n_features: usize, importance_type: ImportanceType) -> Result<Array1<D>> {
+        self.features.check_n_features(n_features)?;
+        let mut importances: Array1<D> = Array1::zeros(n_features);
+        // contributions of original features to each accumulated column
+        let mut attribution: Array2<D> = Array2::eye(n_features);
+
+        for ensemble in &self.estimators {
+            let n_columns = attribution.dim().0;
+            let all_importances = ensemble.all_feature_importances(n_columns, importance_type)?;
+            let mut original_importances = all_importances.dot(&attribution);
+            importances = importances + original_importances.sum_axis(Axis(0));
+
+            // each base estimator generates one column
+            for mut row in original_importances.outer_iter_mut() {
+                let total = row.sum();
+                if total > 0.0 {
+                    row /= total;
+                }
+            }
+            attribution = stack(Axis(0), &[attribution.view(), original_importances.view()]).unwrap();
+        }
+        Ok(importances)
+    }
+}
+
+/// Attribute `stacked` contributions of accumulated columns back to the original features and the bias.
+/// 
+/// `generated` are contributions of the original features and the bias to each generated column,
+/// contribution of a generated column is split proportionally to their absolute values,
+/// so each row sum is preserved.
+fn attribute_contributions(stacked: &Array2<D>, generated: &[Array2<D>], n_features: usize) -> Array2<D> {
+    let n_samples = stacked.dim().0;
+    let mut original = Array2::zeros((n_samples, n_features + 1));
+    original.slice_mut(s![.., ..n_features]).assign(&stacked.slice(s![.., ..n_features]));
+    original.column_mut(n_features).assign(&stacked.column(stacked.dim().1 - 1));
+    for (k, column) in generated.iter().enumerate() {
+        for (i, (mut row, parts)) in original.outer_iter_mut().zip(column.outer_iter()).enumerate() {
+            let contribution = stacked[[i, n_features + k]];
+            let total: D = parts.iter().map(|p| p.abs()).sum();
+            if total > 0.0 {
+                row.zip_mut_with(&parts, |c, p| *c += contribution * p.abs() / total);
+            } else {
+                row[n_features] += contribution;
+            }
+        }
+    }
+    original
+}
+
+impl<F: Float> PredictContributions for DeepBoostingImpl<AverageEnsemble<TreeGBM<F>>> {
+    /// Predict contributions of original input features.
+    /// 
+    /// Each base estimator is explained by TreeSHAP on its accumulated columns, contribution of each
+    /// generated column is attributed back to the original features and the bias proportionally to
+    /// absolute contributions to the column, so rows still sum up to predictions.
+    fn predict_contributions(&self, columns: &ArrayView2<'_, F>) -> Result<Array2<D>> {
+        self.features.check(columns)?;
+        let (n_features, n_samples) = columns.dim();
+        if self.estimators.is_empty() {
+            return Err(WoodsError::NotFitted);
+        }
+        let mut contributions = Array2::zeros((n_samples, n_features + 1));
+        // contributions of original features to each generated column
+        let mut generated: Vec<Array2<D>> = vec![];
+        let mut acc_columns: Array2<F> = columns.to_owned();
+
+        for (it, ensemble) in self.estimators.iter().enumerate() {
+            let rate = if it == 0 { 1.0 } else { self.params.learning_rate };
+            let layer: Vec<Array2<D>> = ensemble.all_predict_contributions(&acc_columns.view())?
+                .iter()
+                .map(|stacked| attribute_contributions(stacked, &generated, n_features))
+                .collect();
+            // layer prediction is the mean of generated columns
+            let weight = rate / layer.len() as D;
+            for column in &layer {
+                contributions.scaled_add(weight, column);
+            }
+            if it + 1 < self.estimators.len() {
+                let all_preds = ensemble.predict_all(&acc_columns.view())?;
+                acc_columns = stack(Axis(0), &[acc_columns.view(), all_preds.view()]).unwrap();
+            }
+            generated.extend(layer);
         }
-        predictions
+        Ok(contributions)
     }
 }
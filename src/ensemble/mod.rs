@@ -1,7 +1,11 @@
 use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
 use ndarray::{ArrayView2, ArrayView1, Array1, Array2, stack, Axis};
-use crate::utils::numerics::D;
+use crate::utils::numerics::{D, Float};
+use crate::error::{Result, WoodsError};
 use crate::estimator::*;
+use crate::export::{AsWeightedTrees, WeightedTrees};
+use crate::utils::serialization::Model;
 use rayon::prelude::*;
 
 pub mod boosting;
@@ -15,13 +19,13 @@ pub trait Ensemble: Estimator {
     type Arg;
     fn make(width: u32, params: Self::Arg) -> Self;
     /// Predict with all base estimators and concatenate rows of predictions along 0 axis.
-    fn predict_all(&self, columns: &ArrayView2<'_, D>) -> Array2<D>;
+    fn predict_all(&self, columns: &ArrayView2<'_, Self::F>) -> Result<Array2<Self::F>>;
     /// Make ensemble prediction from base estimators predictions.
-    fn predict_by_all(&self, preds: &ArrayView2<'_, D>) -> Array1<D>;
+    fn predict_by_all(&self, preds: &ArrayView2<'_, Self::F>) -> Array1<Self::F>;
 
-    fn predict_ensemble(&self, columns: &ArrayView2<'_, D>) -> Array1<D> {
-        let all_preds = self.predict_all(columns);
-        self.predict_by_all(&all_preds.view())
+    fn predict_ensemble(&self, columns: &ArrayView2<'_, Self::F>) -> Result<Array1<Self::F>> {
+        let all_preds = self.predict_all(columns)?;
+        Ok(self.predict_by_all(&all_preds.view()))
     }
 }
 
@@ -32,11 +36,16 @@ pub trait EstimatorsIter {
 }
 
 /// Stack slice of predictions into 2D array.
-fn stack_predictions(preds: &[Array1<D>]) -> Array2<D> {
-    let views: Vec<ArrayView2<'_, D>> = preds.iter().map(|p|
+/// 
+/// Empty slice means that there are no fitted estimators.
+fn stack_predictions<F: Float>(preds: &[Array1<F>]) -> Result<Array2<F>> {
+    if preds.is_empty() {
+        return Err(WoodsError::NotFitted);
+    }
+    let views: Vec<ArrayView2<'_, F>> = preds.iter().map(|p|
         p.broadcast((1, p.dim())).unwrap()
     ).collect();
-    stack(Axis(0), &views[..]).unwrap()
+    Ok(stack(Axis(0), &views[..]).unwrap())
 }
 
 // impl<'a, T, E: 'a> EstimatorsIter for T
@@ -52,32 +61,40 @@ fn stack_predictions(preds: &[Array1<D>]) -> Array2<D> {
 
 /// Collection of estimators that can be used to make predictions.
 pub trait EstimatorsCollection {
+    /// Floating point type of data and predictions.
+    type F: Float;
     /// Make predictions with collection of estimators.
-    fn make_predictions(&self, columns: &ArrayView2<'_, D>) -> Array2<D>;
+    fn make_predictions(&self, columns: &ArrayView2<'_, Self::F>) -> Result<Array2<Self::F>>;
 }
 
 /// Collection of `Send + Sync` estimators that can be used to make predictions.
 pub trait ParEstimatorsCollection {
+    /// Floating point type of data and predictions.
+    type F: Float;
     /// Make predictions with collection of estimators **in parallel**.
-    fn par_make_predictions(&self, columns: &ArrayView2<'_, D>) -> Array2<D>;
+    fn par_make_predictions(&self, columns: &ArrayView2<'_, Self::F>) -> Result<Array2<Self::F>>;
 }
 
 impl<E: Estimator> EstimatorsCollection for Vec<E> {
-    fn make_predictions(&self, columns: &ArrayView2<'_, D>) -> Array2<D> {
-        let preds: Vec<Array1<D>> = self
+    type F = E::F;
+
+    fn make_predictions(&self, columns: &ArrayView2<'_, E::F>) -> Result<Array2<E::F>> {
+        let preds: Vec<Array1<E::F>> = self
                         .iter()
                         .map(|est| est.predict(columns))
-                        .collect();
+                        .collect::<Result<_>>()?;
         stack_predictions(&preds)
     }
 }
 
 impl<E: Estimator + Send + Sync> ParEstimatorsCollection for Vec<E> {
-    fn par_make_predictions(&self, columns: &ArrayView2<'_, D>) -> Array2<D> {
-        let preds: Vec<Array1<D>> = self
+    type F = E::F;
+
+    fn par_make_predictions(&self, columns: &ArrayView2<'_, E::F>) -> Result<Array2<E::F>> {
+        let preds: Vec<Array1<E::F>> = self
                         .par_iter()
                         .map(|est| est.predict(columns))
-                        .collect();
+                        .collect::<Result<_>>()?;
         stack_predictions(&preds)
     }
 }
@@ -85,7 +102,9 @@ impl<E: Estimator + Send + Sync> ParEstimatorsCollection for Vec<E> {
 /// Ensemble that averages all base estimators predictions.
 #[derive(Serialize, Deserialize)]
 pub struct AverageEnsemble<E> {
-    estimators: Vec<E>
+    estimators: Vec<E>,
+    #[serde(default)]
+    features: InputFeatures,
 }
 
 // impl Ensemble<GradientBoostingParameters<TreeParameters>> for AverageEnsemble<TreeGBM> {
@@ -97,10 +116,11 @@ impl<P: Copy, T: Estimator + ConstructibleWithCopyArg<Arg=P> + Send + Sync> Ense
         }).collect();
         AverageEnsemble {
             estimators: estimators,
+            features: InputFeatures::default(),
         }
     }
 
-    fn predict_all(&self, columns: &ArrayView2<'_, D>) -> Array2<D> {
+    fn predict_all(&self, columns: &ArrayView2<'_, T::F>) -> Result<Array2<T::F>> {
         // let preds: Vec<Array1<D>> = self.estimators
         //                 .par_iter()
         //                 .map(|est| est.predict(columns))
@@ -110,21 +130,90 @@ impl<P: Copy, T: Estimator + ConstructibleWithCopyArg<Arg=P> + Send + Sync> Ense
         self.estimators.par_make_predictions(columns)
     }
 
-    fn predict_by_all(&self, preds: &ArrayView2<'_, D>) -> Array1<D> {
+    fn predict_by_all(&self, preds: &ArrayView2<'_, T::F>) -> Array1<T::F> {
         preds.mean_axis(Axis(0)).unwrap()
     }
 }
 
-impl<T: Estimator + Send + Sync> Estimator for AverageEnsemble<T>
-    where AverageEnsemble<T>: Ensemble {
-    fn fit(&mut self, columns: &ArrayView2<'_, D>, target: &ArrayView1<'_, D>) {
+impl<E> WithInputFeatures for AverageEnsemble<E> {
+    fn input_features(&self) -> &InputFeatures {
+        &self.features
+    }
+
+    fn input_features_mut(&mut self) -> &mut InputFeatures {
+        &mut self.features
+    }
+}
+
+impl<E: AsWeightedTrees> AsWeightedTrees for AverageEnsemble<E> {
+    fn weighted_trees(&self) -> Result<WeightedTrees> {
+        if self.estimators.is_empty() {
+            return Err(WoodsError::NotFitted);
+        }
+        let scale = 1.0 / self.estimators.len() as D;
+        let mut ensemble = WeightedTrees {
+            features: self.features.clone(),
+            base_value: D::default(),
+            trees: vec![],
+        };
+        for est in &self.estimators {
+            let est_trees = est.weighted_trees()?;
+            ensemble.base_value += scale * est_trees.base_value;
+            ensemble.trees.extend(est_trees.trees.into_iter().map(|(weight, nodes)| (scale * weight, nodes)));
+        }
+        Ok(ensemble)
+    }
+}
+
+impl<E: Estimator> Model for AverageEnsemble<E> where Self: Serialize + DeserializeOwned {
+    const KIND: &'static str = "AverageEnsemble";
+    const DTYPE: &'static str = E::F::NAME;
+
+    fn feature_names(&self) -> Option<Vec<String>> {
+        self.features.names.clone()
+    }
+}
+
+impl<E: FeatureImportance> AverageEnsemble<E> {
+    /// Calculate feature importances of each base estimator, one row per estimator.
+    pub fn all_feature_importances(&self, n_features: usize, importance_type: ImportanceType) -> Result<Array2<D>> {
+        self.features.check_n_features(n_features)?;
+        let mut importances = Array2::zeros((self.estimators.len(), n_features));
+        for (mut row, est) in importances.outer_iter_mut().zip(&self.estimators) {
+            row.assign(&est.feature_importances(n_features, importance_type)?);
+        }
+        Ok(importances)
+    }
+}
+
+impl<E: PredictContributions> AverageEnsemble<E> {
+    /// Predict feature contributions of each base estimator, see [`PredictContributions`].
+    pub fn all_predict_contributions(&self, columns: &ArrayView2<'_, E::F>) -> Result<Vec<Array2<D>>> {
+        self.estimators.iter().map(|est| est.predict_contributions(columns)).collect()
+    }
+}
+
+impl<E: FeatureImportance> FeatureImportance for AverageEnsemble<E> {
+    fn feature_importances(&self, n_features: usize, importance_type: ImportanceType) -> Result<Array1<D>> {
+        Ok(self.all_feature_importances(n_features, importance_type)?.sum_axis(Axis(0)))
+    }
+}
+
+impl<P: Copy, T: Estimator + ConstructibleWithCopyArg<Arg=P> + Send + Sync> Estimator for AverageEnsemble<T> {
+    type F = T::F;
+
+    fn fit_unchecked(&mut self, columns: &ArrayView2<'_, T::F>, target: &ArrayView1<'_, T::F>) -> Result<()> {
         self.estimators.par_iter_mut()
-                       .for_each(|est| {
-                           est.fit(columns, target)
-                        });
+                       .map(|est| {
+                           est.fit_unchecked(columns, target)
+                        })
+                       .collect::<Result<()>>()?;
+        self.features = InputFeatures::of(columns);
+        Ok(())
     }
 
-    fn predict(&self, columns: &ArrayView2<'_, D>) -> Array1<D> {
+    fn predict(&self, columns: &ArrayView2<'_, T::F>) -> Result<Array1<T::F>> {
+        self.features.check(columns)?;
         Ensemble::predict_ensemble(self, columns) 
     }
 }
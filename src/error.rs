@@ -0,0 +1,118 @@
+//! Error type of estimators and serialization.
+//!
+//! All fallible operations return [`Result`] with [`WoodsError`],
+//! which is converted to a corresponding Python exception by the extension (`python` feature).
+
+use std::fmt;
+use std::io;
+#[cfg(feature = "python")]
+use pyo3::{PyErr, create_exception};
+#[cfg(feature = "python")]
+use pyo3::exceptions;
+use crate::utils::serialization::UnknownFormatError;
+
+#[cfg(feature = "python")]
+create_exception!(woods, NotFittedError, exceptions::ValueError);
+
+/// Error of estimators training, prediction and serialization.
+#[derive(Debug)]
+pub enum WoodsError {
+    /// Input sizes mismatch, e.g. number of samples in features and target.
+    ShapeMismatch {
+        /// Name of the mismatched dimension.
+        what: &'static str,
+        /// Expected size.
+        expected: usize,
+        /// Actual size.
+        found: usize,
+    },
+    /// Estimator is used before training.
+    NotFitted,
+    /// Training data has no samples or no features.
+    EmptyData,
+    /// Training data contains `NaN` values.
+    NanInput,
+    /// Serialized model can't be decoded.
+    CorruptedModel(String),
+    /// Serialized model has different kind, e.g. `GradientBoosting` instead of `DeepGradientBoosting`.
+    WrongModelKind {
+        /// Kind of model to load.
+        expected: String,
+        /// Kind of model in file.
+        found: String,
+    },
+    /// Imported model has unsupported features, e.g. categorical splits.
+    UnsupportedModel(String),
+    /// Parameter value is not valid, e.g. number of cross-validation folds is too large.
+    InvalidParameter(String),
+    /// Input data can't be parsed, e.g. CSV value is not a number or sparse indices are out of range.
+    InvalidData(String),
+    /// Model file format version is not supported, e.g. it is saved by a newer version.
+    UnsupportedVersion(u32),
+    /// Unknown serialization format.
+    UnknownFormat(UnknownFormatError),
+    /// File system error.
+    Io(io::Error),
+}
+
+/// Result with [`WoodsError`].
+pub type Result<T> = std::result::Result<T, WoodsError>;
+
+impl fmt::Display for WoodsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WoodsError::ShapeMismatch { what, expected, found } => {
+                write!(f, "Shape mismatch: expected {} {}, found {}", expected, what, found)
+            },
+            WoodsError::NotFitted => write!(f, "Estimator is not fitted, call `fit` first"),
+            WoodsError::EmptyData => write!(f, "Training data is empty"),
+            WoodsError::NanInput => write!(f, "Input contains NaN values"),
+            WoodsError::CorruptedModel(msg) => write!(f, "Corrupted model: {}", msg),
+            WoodsError::WrongModelKind { expected, found } => {
+                write!(f, "Wrong model kind: expected `{}`, found `{}`", expected, found)
+            },
+            WoodsError::UnsupportedModel(msg) => write!(f, "Unsupported model: {}", msg),
+            WoodsError::InvalidParameter(msg) => write!(f, "Invalid parameter: {}", msg),
+            WoodsError::InvalidData(msg) => write!(f, "Invalid data: {}", msg),
+            WoodsError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported model format version: {}", version)
+            },
+            WoodsError::UnknownFormat(err) => err.fmt(f),
+            WoodsError::Io(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for WoodsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WoodsError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for WoodsError {
+    fn from(err: io::Error) -> Self {
+        WoodsError::Io(err)
+    }
+}
+
+impl From<UnknownFormatError> for WoodsError {
+    fn from(err: UnknownFormatError) -> Self {
+        WoodsError::UnknownFormat(err)
+    }
+}
+
+#[cfg(feature = "python")]
+impl From<WoodsError> for PyErr {
+    /// Cast error to Python's exception: `NotFittedError` (subclass of `ValueError`),
+    /// `IOError` or `ValueError`.
+    fn from(err: WoodsError) -> PyErr {
+        match err {
+            WoodsError::NotFitted => PyErr::new::<NotFittedError, _>(err.to_string()),
+            WoodsError::Io(io_err) => io_err.into(),
+            _ => PyErr::new::<exceptions::ValueError, _>(err.to_string()),
+        }
+    }
+}
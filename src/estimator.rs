@@ -1,15 +1,224 @@
-use ndarray::{ArrayView2, ArrayView1, Array1, Axis, Slice};
-use ndarray_stats::DeviationExt;
-use crate::utils::numerics::D;
+use ndarray::{ArrayView2, ArrayView1, Array1, Array2, Axis};
+use crate::utils::numerics::{D, Float};
+use crate::utils::sparse::SparseColumns;
+use crate::metrics::{Metric, MeanSquaredError};
+use crate::model_selection::{cross_val_score, KFold};
+use crate::error::{Result, WoodsError};
+use itertools::iproduct;
+use rand::seq::SliceRandom;
+use rayon::prelude::*;
+use std::fmt;
+use std::str::FromStr;
+use serde::{Serialize, Deserialize};
 
 /// Estimator that could be trained and used to make predictions.
 pub trait Estimator {
+    /// Floating point type of data and predictions.
+    type F: Float;
     /// Fit estimator with training data and target.
     /// 
+    /// Input is validated with [`check_fit_input`] before training.
+    /// 
     /// **Important**: each `columns` row correspond to the input feature, not sample.
-    fn fit(&mut self, columns: &ArrayView2<'_, D>, target: &ArrayView1<'_, D>);
+    fn fit(&mut self, columns: &ArrayView2<'_, Self::F>, target: &ArrayView1<'_, Self::F>) -> Result<()> {
+        check_fit_input(columns, target)?;
+        self.fit_unchecked(columns, target)
+    }
+    /// Fit estimator with training data and target without input validation.
+    /// 
+    /// It is used by ensembles to train base estimators on already validated data.
+    fn fit_unchecked(&mut self, columns: &ArrayView2<'_, Self::F>, target: &ArrayView1<'_, Self::F>) -> Result<()>;
     /// Predict with estimator on potentially unseed data.
-    fn predict(&self, columns: &ArrayView2<'_, D>) -> Array1<D>;
+    fn predict(&self, columns: &ArrayView2<'_, Self::F>) -> Result<Array1<Self::F>>;
+}
+
+/// Check that training data is not empty, has no `NaN` values and
+/// number of samples in `columns` and `target` is the same.
+pub fn check_fit_input<F: Float>(columns: &ArrayView2<'_, F>, target: &ArrayView1<'_, F>) -> Result<()> {
+    let (n_features, n_samples) = columns.dim();
+    if n_samples != target.dim() {
+        return Err(WoodsError::ShapeMismatch {
+            what: "target samples",
+            expected: n_samples,
+            found: target.dim(),
+        });
+    }
+    if n_features == 0 || n_samples == 0 {
+        return Err(WoodsError::EmptyData);
+    }
+    if columns.iter().chain(target.iter()).any(|v| v.is_nan()) {
+        return Err(WoodsError::NanInput);
+    }
+    Ok(())
+}
+
+/// Check input of [`SparseEstimator::fit_sparse`] like [`check_fit_input`].
+pub fn check_sparse_fit_input<F: Float>(columns: &SparseColumns<F>, target: &ArrayView1<'_, F>) -> Result<()> {
+    let (n_features, n_samples) = columns.dim();
+    if n_samples != target.dim() {
+        return Err(WoodsError::ShapeMismatch {
+            what: "target samples",
+            expected: n_samples,
+            found: target.dim(),
+        });
+    }
+    if n_features == 0 || n_samples == 0 {
+        return Err(WoodsError::EmptyData);
+    }
+    if columns.values().iter().chain(target.iter()).any(|v| v.is_nan()) {
+        return Err(WoodsError::NanInput);
+    }
+    Ok(())
+}
+
+/// Estimator that can be trained and used to make predictions on sparse data without converting it to dense.
+pub trait SparseEstimator: Estimator {
+    /// Fit estimator with sparse training data and target, input is validated with [`check_sparse_fit_input`].
+    fn fit_sparse(&mut self, columns: &SparseColumns<Self::F>, target: &ArrayView1<'_, Self::F>) -> Result<()> {
+        check_sparse_fit_input(columns, target)?;
+        self.fit_sparse_unchecked(columns, target)
+    }
+    /// Fit estimator with sparse training data and target without input validation.
+    fn fit_sparse_unchecked(&mut self, columns: &SparseColumns<Self::F>, target: &ArrayView1<'_, Self::F>) -> Result<()>;
+    /// Predict with estimator on sparse data.
+    fn predict_sparse(&self, columns: &SparseColumns<Self::F>) -> Result<Array1<Self::F>>;
+}
+
+/// Input features of trained estimator: number of features and optional feature names.
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct InputFeatures {
+    /// Number of features seen during `fit`, `0` if unknown.
+    pub n_features: usize,
+    /// Optional feature names.
+    pub names: Option<Vec<String>>,
+}
+
+impl InputFeatures {
+    /// Make input features description of `columns` without names.
+    pub fn of<F>(columns: &ArrayView2<'_, F>) -> Self {
+        InputFeatures::with_n_features(columns.dim().0)
+    }
+
+    /// Make input features description of `n_features` features without names.
+    pub fn with_n_features(n_features: usize) -> Self {
+        InputFeatures {
+            n_features,
+            names: None,
+        }
+    }
+
+    /// Check that `columns` have the same number of features.
+    /// 
+    /// Check is skipped if number of features is unknown, e.g. for models saved by older versions.
+    pub fn check<F>(&self, columns: &ArrayView2<'_, F>) -> Result<()> {
+        self.check_n_features(columns.dim().0)
+    }
+
+    /// Check that input has the same number of features, see [`InputFeatures::check`].
+    pub fn check_n_features(&self, n_features: usize) -> Result<()> {
+        if self.n_features != 0 && self.n_features != n_features {
+            return Err(WoodsError::ShapeMismatch {
+                what: "features",
+                expected: self.n_features,
+                found: n_features,
+            });
+        }
+        Ok(())
+    }
+
+    /// Set feature names, number of names should be equal to number of features.
+    pub fn set_names(&mut self, names: Vec<String>) -> Result<()> {
+        if names.len() != self.n_features {
+            return Err(WoodsError::ShapeMismatch {
+                what: "feature names",
+                expected: self.n_features,
+                found: names.len(),
+            });
+        }
+        self.names = Some(names);
+        Ok(())
+    }
+}
+
+/// Estimator that remembers input features it was trained on.
+pub trait WithInputFeatures {
+    /// Input features, seen during `fit`.
+    fn input_features(&self) -> &InputFeatures;
+    /// Mutable input features, e.g. to set feature names.
+    fn input_features_mut(&mut self) -> &mut InputFeatures;
+}
+
+/// Estimator with parameters that can be changed after construction, e.g. by hyperparameter search.
+/// 
+/// Changed parameters are used by the next `fit`, fitted model is not affected.
+pub trait WithParameters {
+    /// Parameters type.
+    type Params;
+    /// Current parameters.
+    fn params(&self) -> &Self::Params;
+    /// Mutable parameters.
+    fn params_mut(&mut self) -> &mut Self::Params;
+}
+
+/// Kind of feature importance.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ImportanceType {
+    /// Number of splits by feature.
+    Split,
+    /// Total impurity decrease of splits by feature.
+    #[default]
+    Gain,
+}
+
+/// Available feature importance types.
+pub const IMPORTANCE_TYPES: &[&str; 2] = &["split", "gain"];
+
+/// Unknown feature importance type error.
+#[derive(Debug, Clone)]
+pub struct UnknownImportanceTypeError {
+    /// Importance type name.
+    pub importance_type: String,
+}
+
+impl fmt::Display for UnknownImportanceTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Incorrect importance type: `{}`. Please, use one of: {:?}",
+               self.importance_type,
+               IMPORTANCE_TYPES)
+    }
+}
+
+impl FromStr for ImportanceType {
+    type Err = UnknownImportanceTypeError;
+
+    fn from_str(name: &str) -> std::result::Result<Self, Self::Err> {
+        match name {
+            "split" => Ok(ImportanceType::Split),
+            "gain" => Ok(ImportanceType::Gain),
+            _ => Err(UnknownImportanceTypeError {
+                importance_type: name.into()
+            }),
+        }
+    }
+}
+
+/// Estimator that can measure importance of input features.
+pub trait FeatureImportance {
+    /// Calculate importances of `n_features` input features.
+    /// 
+    /// Importances are not normalized: split counts or total gains are summed over all base estimators.
+    /// Gain importances of models saved without split gains are not supported.
+    /// `n_features` should be equal to number of features seen during `fit`.
+    fn feature_importances(&self, n_features: usize, importance_type: ImportanceType) -> Result<Array1<D>>;
+}
+
+/// Estimator that can explain predictions by additive feature contributions.
+pub trait PredictContributions: Estimator {
+    /// Predict contribution of each feature to each sample prediction.
+    /// 
+    /// Result shape is `(n_samples, n_features + 1)`, the last column is the bias term (expected prediction).
+    /// Sum of each row is equal to the prediction (up to rounding of `f32` predictions).
+    fn predict_contributions(&self, columns: &ArrayView2<'_, Self::F>) -> Result<Array2<D>>;
 }
 
 /// Structure can be constructed with arguments of associated-type `Arg`.
@@ -26,45 +235,77 @@ pub trait ConstructibleWithCopyArg {
     fn new(arg: Self::Arg) -> Self;
 }
 
-/// Fit estimator on training data and calculate mean squared error on validation data.
-pub fn eval_est<Est: Estimator>(
+/// Fit estimator on training data and calculate `metric` on validation data, e.g. [`MeanSquaredError`].
+pub fn eval_est<Est: Estimator, M: Metric<Est::F> + ?Sized>(
     est: &mut Est,
-    train_columns: &ArrayView2<'_, D>,
-    train_target: &ArrayView1<'_, D>,
-    val_columns: &ArrayView2<'_, D>,
-    val_target: &ArrayView1<'_, D>) -> D {
-    est.fit(train_columns, train_target);
-    let preds = est.predict(val_columns);
-    preds.mean_sq_err(val_target).unwrap() as D
+    train_columns: &ArrayView2<'_, Est::F>,
+    train_target: &ArrayView1<'_, Est::F>,
+    val_columns: &ArrayView2<'_, Est::F>,
+    val_target: &ArrayView1<'_, Est::F>,
+    metric: &M) -> Result<D> {
+    if val_target.is_empty() {
+        return Err(WoodsError::EmptyData);
+    }
+    est.fit(train_columns, train_target)?;
+    let preds = est.predict(val_columns)?;
+    Ok(metric.evaluate(&preds.view(), val_target, None))
 }
 
-/// Evaluate estimator ([`eval_est`]) with cross-validation.
+/// Evaluate estimator with `cv`-fold cross-validation ([`KFold`]): mean of validation mean squared errors.
+/// 
+/// See [`cross_val_score`] for scores of each fold and other splitters.
 pub fn eval_est_cv<Est: Estimator>(
         est: &mut Est,
         cv: u8,
-        columns: &ArrayView2<'_, D>,
-        target: &ArrayView1<'_, D>
-    ) -> D {
-    let n_samples: usize = target.dim();
-    let fold_size: usize = n_samples / (cv as usize);
-    let mut res: D = D::default();
-
-    for i in 0..cv {
-        let from = fold_size * (i as usize);
-        let to = std::cmp::min(fold_size * ((i + 1) as usize), n_samples + 1);
-        let fold_columns = columns.slice_axis(Axis(1), Slice::from(from..to));
-        let fold_target = target.slice_axis(Axis(0), Slice::from(from..to));
-        if i > 0 {
-            let val_columns = columns.slice_axis(Axis(1), Slice::from(0..from));
-            let val_target = target.slice_axis(Axis(0), Slice::from(0..from));
-            res += eval_est(est, &fold_columns, &fold_target, &val_columns, &val_target);
-        }
-        if i < cv - 1 {
-            let val_columns = columns.slice_axis(Axis(1), Slice::from(to..));
-            let val_target = target.slice_axis(Axis(0), Slice::from(to..));
-            res += eval_est(est, &fold_columns, &fold_target, &val_columns, &val_target);
-        }
-    }
-    // res / (cv as D)
-    res
-}
\ No newline at end of file
+        columns: &ArrayView2<'_, Est::F>,
+        target: &ArrayView1<'_, Est::F>
+    ) -> Result<D> {
+    let scores = cross_val_score(est, columns, target, &KFold::new(cv as usize), None, &MeanSquaredError)?;
+    Ok(scores.mean().unwrap_or_default())
+}
+
+/// Mean and standard deviation of score deltas over permutation repeats.
+pub struct PermutationImportances {
+    /// Mean score delta of each feature.
+    pub mean: Array1<D>,
+    /// Standard deviation of score delta of each feature.
+    pub std: Array1<D>,
+}
+
+/// Calculate model-agnostic permutation feature importances of trained estimator.
+/// 
+/// Each feature row of `columns` is shuffled `n_repeats` times and the loss of `metric` (see [`Metric::loss`])
+/// of predictions is compared to the loss of predictions on the original data.
+/// Permutations are evaluated in parallel, each worker permutes one row of its own copy of `columns` at a time.
+pub fn permutation_importance<Est, M>(
+        est: &Est,
+        columns: &ArrayView2<'_, Est::F>,
+        target: &ArrayView1<'_, Est::F>,
+        n_repeats: usize,
+        metric: &M
+    ) -> Result<PermutationImportances>
+    where Est: Estimator + Sync,
+          M: Metric<Est::F> + ?Sized {
+    let (n_features, n_samples) = columns.dim();
+    let base_score = metric.loss(&est.predict(columns)?.view(), target, None);
+
+    let deltas: Vec<D> = iproduct!(0..n_features, 0..n_repeats)
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .map_init(|| columns.to_owned(), |permuted, (feature, _)| {
+            let mut permutation: Vec<usize> = (0..n_samples).collect();
+            permutation.shuffle(&mut rand::thread_rng());
+            permuted.row_mut(feature).assign(&columns.row(feature).select(Axis(0), &permutation));
+            let preds = est.predict(&permuted.view());
+            // restore the row for the next permutation
+            permuted.row_mut(feature).assign(&columns.row(feature));
+            Ok(metric.loss(&preds?.view(), target, None) - base_score)
+        })
+        .collect::<Result<_>>()?;
+
+    let deltas = Array2::from_shape_vec((n_features, n_repeats), deltas).unwrap();
+    Ok(PermutationImportances {
+        mean: deltas.mean_axis(Axis(1)).unwrap_or_else(|| Array1::zeros(n_features)),
+        std: deltas.std_axis(Axis(1), 0.0),
+    })
+}
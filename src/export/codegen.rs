@@ -0,0 +1,225 @@
+//! Compilation of trained models to standalone Rust or C source code.
+//!
+//! Generated code has no dependencies and defines `N_FEATURES` and `predict(x)` of a single sample.
+//! Each tree is a function with nested `if`-`else` statements, tree outputs are summed
+//! in the same order and with the same operations as [`Estimator::predict`](crate::estimator::Estimator::predict),
+//! so predictions of generated code are exactly equal to predictions of `float64` models.
+//! Values of `float32` models are written exactly, but generated code computes in `f64`,
+//! so its predictions may differ from `f32` predictions of the model in the last digits.
+//! C code must be compiled without floating-point contraction (e.g. `-ffp-contract=off`) to keep this property.
+
+use std::fmt;
+use std::fs::File;
+use std::io::Write;
+use std::str::FromStr;
+use crate::tree::ExplicitNode;
+use crate::estimator::InputFeatures;
+use crate::utils::numerics::D;
+use crate::error::Result;
+
+/// Available target languages.
+pub const LANGUAGES: [&str; 2] = ["rust", "c"];
+
+/// Target language of generated code.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Language {
+    Rust,
+    C,
+}
+
+/// Unknown target language error.
+#[derive(Debug, Clone)]
+pub struct UnknownLanguageError {
+    /// Language name.
+    pub language: String,
+}
+
+impl fmt::Display for UnknownLanguageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Incorrect language: `{}`. Please, use one of: {:?}", self.language, LANGUAGES)
+    }
+}
+
+impl FromStr for Language {
+    type Err = UnknownLanguageError;
+
+    fn from_str(name: &str) -> std::result::Result<Self, Self::Err> {
+        match name {
+            "rust" => Ok(Language::Rust),
+            "c" => Ok(Language::C),
+            _ => Err(UnknownLanguageError {
+                language: name.into()
+            }),
+        }
+    }
+}
+
+/// Sum of trees: prediction is the sum of tree outputs, optionally scaled and shifted as in gradient boosting.
+#[derive(Debug, Clone)]
+pub struct TreeSum {
+    /// Input features of the model.
+    pub features: InputFeatures,
+    /// Trees with explicit leaves in depth-first order.
+    pub trees: Vec<Vec<ExplicitNode>>,
+    /// Learning rate and mean target value of gradient boosting, prediction is `sum * learning_rate + mean`.
+    ///
+    /// If it is `None`, the model consists of a single tree and prediction is the tree output.
+    pub boosting: Option<(D, D)>,
+}
+
+/// Model that can be compiled to source code.
+pub trait AsTreeSum {
+    /// Make sum of trees, which reproduces predictions of the fitted model exactly.
+    fn tree_sum(&self) -> Result<TreeSum>;
+}
+
+/// Source code of `f64` value.
+fn literal(value: D, language: Language) -> String {
+    match (language, value) {
+        (Language::Rust, v) if v.is_nan() => "f64::NAN".into(),
+        (Language::Rust, v) if v.is_infinite() => format!("{}f64::INFINITY", if v < 0.0 { "-" } else { "" }),
+        (Language::C, v) if v.is_nan() => "NAN".into(),
+        (Language::C, v) if v.is_infinite() => format!("{}HUGE_VAL", if v < 0.0 { "-" } else { "" }),
+        // `Debug` prints the shortest representation that is parsed to the same value, e.g. `1.0` or `1e-7`
+        (_, v) => format!("{:?}", v),
+    }
+}
+
+/// Source code writer with indentation.
+struct Source {
+    language: Language,
+    text: String,
+}
+
+impl Source {
+    fn line(&mut self, depth: usize, line: &str) {
+        for _ in 0..depth {
+            self.text.push_str("    ");
+        }
+        self.text.push_str(line);
+        self.text.push('\n');
+    }
+
+    /// Write node `id` of tree `nodes` as a statement (C) or an expression (Rust) at `depth`.
+    fn node(&mut self, nodes: &[ExplicitNode], id: usize, depth: usize) {
+        match &nodes[id] {
+            ExplicitNode::Split { feature, threshold, children, default_left, .. } => {
+                // `NaN` fails both comparisons, so it goes to the `else` branch
+                let threshold = literal(*threshold, self.language);
+                let (condition, first, second) = if *default_left {
+                    (format!("x[{}] > {}", feature, threshold), children[1], children[0])
+                } else {
+                    (format!("x[{}] <= {}", feature, threshold), children[0], children[1])
+                };
+                match self.language {
+                    Language::Rust => self.line(depth, &format!("if {} {{", condition)),
+                    Language::C => self.line(depth, &format!("if ({}) {{", condition)),
+                }
+                self.node(nodes, first, depth + 1);
+                self.line(depth, "} else {");
+                self.node(nodes, second, depth + 1);
+                self.line(depth, "}");
+            },
+            ExplicitNode::Leaf { value, .. } => {
+                let value = literal(*value, self.language);
+                match self.language {
+                    Language::Rust => self.line(depth, &value),
+                    Language::C => self.line(depth, &format!("return {};", value)),
+                }
+            },
+        }
+    }
+
+    fn rust(&mut self, model: &TreeSum) {
+        self.line(0, "/// Number of input features.");
+        self.line(0, &format!("pub const N_FEATURES: usize = {};", model.features.n_features));
+        if let Some((learning_rate, mean)) = model.boosting {
+            self.line(0, "/// Learning rate of gradient boosting.");
+            self.line(0, &format!("const LEARNING_RATE: f64 = {};", literal(learning_rate, Language::Rust)));
+            self.line(0, "/// Mean target value.");
+            self.line(0, &format!("const MEAN: f64 = {};", literal(mean, Language::Rust)));
+        }
+        for (i, nodes) in model.trees.iter().enumerate() {
+            self.line(0, "");
+            // trees without splits don't use features
+            let x = if let Some(ExplicitNode::Leaf { .. }) = nodes.first() { "_x" } else { "x" };
+            self.line(0, &format!("fn tree_{}({}: &[f64]) -> f64 {{", i, x));
+            self.node(nodes, 0, 1);
+            self.line(0, "}");
+        }
+        self.line(0, "");
+        self.line(0, "/// Predict target value of sample `x` with `N_FEATURES` features.");
+        self.line(0, "pub fn predict(x: &[f64]) -> f64 {");
+        match model.boosting {
+            Some(_) => {
+                self.line(1, "let mut sum = 0.0;");
+                for i in 0..model.trees.len() {
+                    self.line(1, &format!("sum += tree_{}(x);", i));
+                }
+                self.line(1, "sum * LEARNING_RATE + MEAN");
+            },
+            None => self.line(1, "tree_0(x)"),
+        }
+        self.line(0, "}");
+    }
+
+    fn c(&mut self, model: &TreeSum) {
+        self.line(0, "#include <math.h>");
+        self.line(0, "");
+        self.line(0, "/* Number of input features. */");
+        self.line(0, &format!("#define N_FEATURES {}", model.features.n_features));
+        if let Some((learning_rate, mean)) = model.boosting {
+            self.line(0, "/* Learning rate of gradient boosting. */");
+            self.line(0, &format!("#define LEARNING_RATE ({})", literal(learning_rate, Language::C)));
+            self.line(0, "/* Mean target value. */");
+            self.line(0, &format!("#define MEAN ({})", literal(mean, Language::C)));
+        }
+        for (i, nodes) in model.trees.iter().enumerate() {
+            self.line(0, "");
+            self.line(0, &format!("static double tree_{}(const double *x) {{", i));
+            self.node(nodes, 0, 1);
+            self.line(0, "}");
+        }
+        self.line(0, "");
+        self.line(0, "/* Predict target value of sample `x` with `N_FEATURES` features. */");
+        self.line(0, "double predict(const double *x) {");
+        match model.boosting {
+            Some(_) => {
+                self.line(1, "double sum = 0.0;");
+                for i in 0..model.trees.len() {
+                    self.line(1, &format!("sum += tree_{}(x);", i));
+                }
+                self.line(1, "return sum * LEARNING_RATE + MEAN;");
+            },
+            None => self.line(1, "return tree_0(x);"),
+        }
+        self.line(0, "}");
+    }
+}
+
+/// Generate `language` source code of `model`.
+pub fn to_code<M: AsTreeSum>(model: &M, language: Language) -> Result<String> {
+    let tree_sum = model.tree_sum()?;
+    let mut source = Source { language, text: String::new() };
+    let header = format!("Generated by woods {} from a trained model.", env!("CARGO_PKG_VERSION"));
+    match language {
+        Language::Rust => {
+            source.line(0, &format!("// {}", header));
+            source.line(0, "");
+            source.rust(&tree_sum);
+        },
+        Language::C => {
+            source.line(0, &format!("/* {} */", header));
+            source.line(0, "");
+            source.c(&tree_sum);
+        },
+    }
+    Ok(source.text)
+}
+
+/// Save `language` source code of `model` to `filename`.
+pub fn save_code<M: AsTreeSum>(model: &M, language: Language, filename: &str) -> Result<()> {
+    let code = to_code(model, language)?;
+    File::create(filename)?.write_all(code.as_bytes())?;
+    Ok(())
+}
@@ -0,0 +1,144 @@
+//! Import of LightGBM text models (`Booster.save_model("model.txt")`).
+//!
+//! LightGBM splits `x <= threshold` are the same as Woods splits. Missing values are
+//! handled by `decision_type` of each split: `NaN` goes to the default child for `NaN` missing type
+//! and is replaced by zero for `None` missing type.
+//! Imported models predict raw scores, i.e. `Booster.predict(..., raw_score=True)`.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use crate::tree::ExplicitNode;
+use crate::estimator::InputFeatures;
+use crate::utils::numerics::D;
+use crate::error::{Result, WoodsError};
+use super::{WeightedTrees, depth_first};
+
+/// `decision_type` flag of categorical split.
+const CATEGORICAL_MASK: u8 = 1;
+/// `decision_type` flag of default left direction.
+const DEFAULT_LEFT_MASK: u8 = 2;
+/// Missing types, stored in bits 2-3 of `decision_type`.
+const MISSING_NONE: u8 = 0;
+const MISSING_ZERO: u8 = 1;
+const MISSING_NAN: u8 = 2;
+
+/// Make corrupted model error with `msg`.
+fn invalid(msg: &str) -> WoodsError {
+    WoodsError::CorruptedModel(format!("LightGBM model: {}", msg))
+}
+
+/// Section of text model: header or tree, lines `key=value` or `key`.
+type Section<'a> = HashMap<&'a str, &'a str>;
+
+/// Parse space-separated values of `key`, missing key means no values.
+fn values<T: FromStr>(section: &Section<'_>, key: &str) -> Result<Vec<T>> {
+    section.get(key).map_or(Ok(vec![]), |line| {
+        line.split_whitespace()
+            .map(|value| value.parse().map_err(|_| invalid(&format!("incorrect value `{}` of `{}`", value, key))))
+            .collect()
+    })
+}
+
+/// Parse single value of `key`.
+fn value<T: FromStr>(section: &Section<'_>, key: &str) -> Result<T> {
+    values(section, key)?.into_iter().next().ok_or_else(|| invalid(&format!("missing field `{}`", key)))
+}
+
+/// Convert tree section.
+///
+/// Internal nodes are indexed from `0`, leaves are referenced by negative indices `-(leaf + 1)`.
+fn convert_tree(tree: &Section<'_>) -> Result<Vec<ExplicitNode>> {
+    if value::<u8>(tree, "is_linear").unwrap_or(0) != 0 {
+        return Err(WoodsError::UnsupportedModel("linear trees".into()));
+    }
+    let n_leaves: usize = value(tree, "num_leaves")?;
+    let leaf_values: Vec<D> = values(tree, "leaf_value")?;
+    let leaf_counts: Vec<usize> = values(tree, "leaf_count")?;
+    if n_leaves == 0 || leaf_values.len() != n_leaves {
+        return Err(invalid("incorrect number of leaves"));
+    }
+    let leaves = leaf_values.iter().enumerate().map(|(leaf, value)| ExplicitNode::Leaf {
+        value: *value,
+        samples: leaf_counts.get(leaf).copied().unwrap_or(0),
+    });
+    if n_leaves == 1 {
+        return Ok(leaves.collect());
+    }
+
+    let n_internal = n_leaves - 1;
+    let features: Vec<usize> = values(tree, "split_feature")?;
+    let thresholds: Vec<D> = values(tree, "threshold")?;
+    let decision_types: Vec<u8> = values(tree, "decision_type")?;
+    let left: Vec<i64> = values(tree, "left_child")?;
+    let right: Vec<i64> = values(tree, "right_child")?;
+    let gains: Vec<D> = values(tree, "split_gain")?;
+    let counts: Vec<usize> = values(tree, "internal_count")?;
+    if [features.len(), thresholds.len(), decision_types.len(), left.len(), right.len()].iter().any(|n| *n != n_internal) {
+        return Err(invalid("tree arrays have different lengths"));
+    }
+    let child_id = |child: i64| if child >= 0 { child as usize } else { n_internal + (-child - 1) as usize };
+    let mut nodes = (0..n_internal).map(|id| {
+        let decision_type = decision_types[id];
+        if decision_type & CATEGORICAL_MASK != 0 {
+            return Err(WoodsError::UnsupportedModel("categorical splits".into()));
+        }
+        let default_left = match (decision_type >> 2) & 3 {
+            MISSING_NONE => 0.0 <= thresholds[id],
+            MISSING_NAN => decision_type & DEFAULT_LEFT_MASK != 0,
+            MISSING_ZERO => return Err(WoodsError::UnsupportedModel("zero as missing value".into())),
+            _ => return Err(invalid("incorrect decision type")),
+        };
+        Ok(ExplicitNode::Split {
+            feature: features[id],
+            threshold: thresholds[id],
+            children: [child_id(left[id]), child_id(right[id])],
+            default_left,
+            gain: gains.get(id).copied().unwrap_or(0.0),
+            samples: counts.get(id).copied().unwrap_or(0),
+        })
+    }).collect::<Result<Vec<_>>>()?;
+    nodes.extend(leaves);
+    depth_first(&nodes)
+}
+
+/// Import LightGBM text model from `text`.
+///
+/// Only numerical splits of single-output models are supported.
+/// Tree outputs are summed, or averaged for random forest models (`average_output`).
+pub fn from_lightgbm_text(text: &str) -> Result<WeightedTrees> {
+    let mut sections: Vec<Section<'_>> = vec![Section::new()];
+    for line in text.lines().map(str::trim) {
+        if line == "end of trees" {
+            break;
+        }
+        if line.starts_with("Tree=") {
+            sections.push(Section::new());
+        }
+        if !line.is_empty() {
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next().unwrap_or("");
+            sections.last_mut().unwrap().insert(key, parts.next().unwrap_or(""));
+        }
+    }
+    let header = &sections[0];
+    if !header.contains_key("version") && !header.contains_key("tree") {
+        return Err(invalid("missing header"));
+    }
+    if value::<usize>(header, "num_tree_per_iteration").unwrap_or(1) != 1 {
+        return Err(WoodsError::UnsupportedModel("multi-class models".into()));
+    }
+    let n_features = value::<usize>(header, "max_feature_idx")? + 1;
+    let names: Vec<String> = values(header, "feature_names")?;
+    let trees: Vec<Vec<ExplicitNode>> = sections[1..].iter().map(convert_tree).collect::<Result<_>>()?;
+    let weight = if header.contains_key("average_output") { 1.0 / trees.len().max(1) as D } else { 1.0 };
+    let ensemble = WeightedTrees {
+        features: InputFeatures {
+            n_features,
+            names: Some(names).filter(|names| names.len() == n_features),
+        },
+        base_value: 0.0,
+        trees: trees.into_iter().map(|nodes| (weight, nodes)).collect(),
+    };
+    ensemble.check()?;
+    Ok(ensemble)
+}
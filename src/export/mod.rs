@@ -0,0 +1,153 @@
+//! Conversion of trained models to and from other formats.
+//!
+//! Tree-based estimators implement [`AsWeightedTrees`], which represents a model as
+//! an additive ensemble of trees with explicit leaves ([`WeightedTrees`]).
+//! Converters work with this representation:
+//! 1. [`onnx`]: export to ONNX-ML `TreeEnsembleRegressor` graph.
+//! 2. [`xgboost`]: import of XGBoost JSON models and JSON dumps, export to JSON dump.
+//! 3. [`lightgbm`]: import of LightGBM text models.
+//!
+//! [`codegen`] compiles trees and gradient boosting to standalone Rust or C source code.
+//!
+//! Imported models are [`WeightedTrees`], which can be used for prediction, saved and exported.
+
+use ndarray::{ArrayView1, ArrayView2, Array1, Axis};
+use serde::{Serialize, Deserialize};
+use crate::tree::ExplicitNode;
+use crate::estimator::{InputFeatures, WithInputFeatures};
+use crate::utils::numerics::D;
+use crate::utils::serialization::Model;
+use crate::error::{Result, WoodsError};
+
+pub mod onnx;
+pub mod xgboost;
+pub mod lightgbm;
+pub mod codegen;
+
+/// Additive tree ensemble: prediction is `base_value` plus weighted sum of tree predictions.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WeightedTrees {
+    /// Input features, number of features is `0` if unknown.
+    pub features: InputFeatures,
+    /// Constant term of prediction.
+    pub base_value: D,
+    /// Trees with their weights.
+    pub trees: Vec<(D, Vec<ExplicitNode>)>,
+}
+
+impl WeightedTrees {
+    /// Check that each tree is non-empty, children follow their parents and features are in range.
+    ///
+    /// It guarantees that prediction terminates and doesn't index out of bounds.
+    pub fn check(&self) -> Result<()> {
+        for (_, nodes) in &self.trees {
+            if nodes.is_empty() {
+                return Err(WoodsError::CorruptedModel("empty tree".into()));
+            }
+            for (id, node) in nodes.iter().enumerate() {
+                if let ExplicitNode::Split { feature, children, .. } = node {
+                    if children.iter().any(|child| *child <= id || *child >= nodes.len()) {
+                        return Err(WoodsError::CorruptedModel(format!("incorrect children of node {}", id)));
+                    }
+                    if self.features.n_features != 0 && *feature >= self.features.n_features {
+                        return Err(WoodsError::CorruptedModel(format!("incorrect feature of node {}", id)));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Predict with tree `nodes` on sample `features`.
+    fn predict_tree(nodes: &[ExplicitNode], features: &ArrayView1<'_, D>) -> Result<D> {
+        let mut id = 0;
+        loop {
+            match &nodes[id] {
+                ExplicitNode::Split { feature, threshold, children, default_left, .. } => {
+                    let value = *features.get(*feature).ok_or(WoodsError::ShapeMismatch {
+                        what: "features",
+                        expected: feature + 1,
+                        found: features.dim(),
+                    })?;
+                    let go_left = if value.is_nan() { *default_left } else { value <= *threshold };
+                    id = children[!go_left as usize];
+                },
+                ExplicitNode::Leaf { value, .. } => return Ok(*value),
+            }
+        }
+    }
+
+    /// Predict on feature-major `columns`.
+    pub fn predict(&self, columns: &ArrayView2<'_, D>) -> Result<Array1<D>> {
+        if self.trees.is_empty() {
+            return Err(WoodsError::NotFitted);
+        }
+        self.features.check(columns)?;
+        self.check()?;
+        columns.axis_iter(Axis(1)).map(|features| {
+            self.trees.iter().try_fold(self.base_value, |acc, (weight, nodes)| {
+                Ok(acc + weight * WeightedTrees::predict_tree(nodes, &features)?)
+            })
+        }).collect()
+    }
+}
+
+impl WithInputFeatures for WeightedTrees {
+    fn input_features(&self) -> &InputFeatures {
+        &self.features
+    }
+
+    fn input_features_mut(&mut self) -> &mut InputFeatures {
+        &mut self.features
+    }
+}
+
+impl Model for WeightedTrees {
+    const KIND: &'static str = "WeightedTrees";
+
+    fn feature_names(&self) -> Option<Vec<String>> {
+        self.features.names.clone()
+    }
+}
+
+/// Model that can be represented as an additive tree ensemble.
+pub trait AsWeightedTrees {
+    /// Make additive tree ensemble, equivalent to the fitted model.
+    fn weighted_trees(&self) -> Result<WeightedTrees>;
+}
+
+impl AsWeightedTrees for WeightedTrees {
+    fn weighted_trees(&self) -> Result<WeightedTrees> {
+        if self.trees.is_empty() {
+            return Err(WoodsError::NotFitted);
+        }
+        Ok(self.clone())
+    }
+}
+
+/// Reorder tree `nodes` with arbitrary children indices to depth-first order with the root node first.
+///
+/// The root node is `nodes[0]`, unreachable nodes are dropped.
+fn depth_first(nodes: &[ExplicitNode]) -> Result<Vec<ExplicitNode>> {
+    let mut new_ids = vec![usize::MAX; nodes.len()];
+    let mut order = Vec::with_capacity(nodes.len());
+    let mut stack = vec![0usize];
+    while let Some(id) = stack.pop() {
+        if id >= nodes.len() || new_ids[id] != usize::MAX {
+            return Err(WoodsError::CorruptedModel(format!("incorrect reference to node {}", id)));
+        }
+        new_ids[id] = order.len();
+        order.push(id);
+        if let ExplicitNode::Split { children, .. } = &nodes[id] {
+            stack.push(children[1]);
+            stack.push(children[0]);
+        }
+    }
+    Ok(order.iter().map(|id| {
+        let mut node = nodes[*id].clone();
+        if let ExplicitNode::Split { children, .. } = &mut node {
+            *children = [new_ids[children[0]], new_ids[children[1]]];
+        }
+        node
+    }).collect())
+}
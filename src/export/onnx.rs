@@ -0,0 +1,254 @@
+//! Export of additive tree ensembles to ONNX-ML `TreeEnsembleRegressor`.
+//!
+//! The model has a single float input `X` of shape `(N, n_features)` (sample-major, as in ONNX Runtime)
+//! and a single float output `Y` of shape `(N, 1)`.
+//! Tree weights are folded into leaf weights, base value is stored in `base_values`.
+//!
+//! ONNX protobuf is written directly, without dependency on `onnx` or `protobuf` packages.
+//! Thresholds and leaf weights are stored as `f32`, as required by `TreeEnsembleRegressor`.
+
+use std::fs::File;
+use std::io::Write;
+use crate::tree::ExplicitNode;
+use crate::utils::numerics::D;
+use crate::error::Result;
+use super::{AsWeightedTrees, WeightedTrees};
+
+/// ONNX IR version of exported models.
+const IR_VERSION: i64 = 7;
+/// Default (`ai.onnx`) domain opset version.
+const ONNX_OPSET: i64 = 13;
+/// ONNX-ML domain name.
+const ML_DOMAIN: &str = "ai.onnx.ml";
+/// ONNX-ML domain opset version, `TreeEnsembleRegressor` is available since version 1.
+const ML_OPSET: i64 = 1;
+/// Input tensor name.
+pub const INPUT_NAME: &str = "X";
+/// Output tensor name.
+pub const OUTPUT_NAME: &str = "Y";
+
+/// Protobuf wire types.
+const WIRE_VARINT: u64 = 0;
+const WIRE_LENGTH_DELIMITED: u64 = 2;
+
+/// `AttributeProto.AttributeType` values.
+const ATTR_INT: i64 = 2;
+const ATTR_STRING: i64 = 3;
+const ATTR_FLOATS: i64 = 6;
+const ATTR_INTS: i64 = 7;
+const ATTR_STRINGS: i64 = 8;
+
+/// `TensorProto.DataType` of `float`.
+const TENSOR_FLOAT: i64 = 1;
+
+/// Minimal protobuf message encoder.
+#[derive(Default)]
+struct Message {
+    buf: Vec<u8>,
+}
+
+impl Message {
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.buf.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        self.buf.push(value as u8);
+    }
+
+    fn key(&mut self, field: u64, wire_type: u64) {
+        self.varint(field << 3 | wire_type);
+    }
+
+    fn int(mut self, field: u64, value: i64) -> Self {
+        self.key(field, WIRE_VARINT);
+        self.varint(value as u64);
+        self
+    }
+
+    fn bytes(mut self, field: u64, value: &[u8]) -> Self {
+        self.key(field, WIRE_LENGTH_DELIMITED);
+        self.varint(value.len() as u64);
+        self.buf.extend_from_slice(value);
+        self
+    }
+
+    fn string(self, field: u64, value: &str) -> Self {
+        self.bytes(field, value.as_bytes())
+    }
+
+    fn message(self, field: u64, value: Message) -> Self {
+        self.bytes(field, &value.buf)
+    }
+
+    fn packed_ints(self, field: u64, values: &[i64]) -> Self {
+        let mut packed = Message::default();
+        for value in values {
+            packed.varint(*value as u64);
+        }
+        self.bytes(field, &packed.buf)
+    }
+
+    fn packed_floats(self, field: u64, values: &[f32]) -> Self {
+        let packed: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes().to_vec()).collect();
+        self.bytes(field, &packed)
+    }
+}
+
+/// `AttributeProto` constructors.
+fn attribute(name: &str, attr_type: i64) -> Message {
+    Message::default().string(1, name).int(20, attr_type)
+}
+
+fn int_attribute(name: &str, value: i64) -> Message {
+    attribute(name, ATTR_INT).int(3, value)
+}
+
+fn string_attribute(name: &str, value: &str) -> Message {
+    attribute(name, ATTR_STRING).string(4, value)
+}
+
+fn floats_attribute(name: &str, values: &[f32]) -> Message {
+    attribute(name, ATTR_FLOATS).packed_floats(7, values)
+}
+
+fn ints_attribute(name: &str, values: &[i64]) -> Message {
+    attribute(name, ATTR_INTS).packed_ints(8, values)
+}
+
+fn strings_attribute(name: &str, values: &[&str]) -> Message {
+    values.iter().fold(attribute(name, ATTR_STRINGS), |attr, value| attr.string(9, value))
+}
+
+/// `ValueInfoProto` of float tensor with batch dimension `N` and `width` columns.
+fn tensor_info(name: &str, width: usize) -> Message {
+    let shape = Message::default()
+        .message(1, Message::default().string(2, "N"))
+        .message(1, Message::default().int(1, width as i64));
+    let tensor_type = Message::default().int(1, TENSOR_FLOAT).message(2, shape);
+    Message::default()
+        .string(1, name)
+        .message(2, Message::default().message(1, tensor_type))
+}
+
+/// Node attributes of `TreeEnsembleRegressor`, one entry per tree node or per leaf.
+#[derive(Default)]
+struct TreeEnsembleAttributes {
+    nodes_treeids: Vec<i64>,
+    nodes_nodeids: Vec<i64>,
+    nodes_featureids: Vec<i64>,
+    nodes_values: Vec<f32>,
+    nodes_modes: Vec<&'static str>,
+    nodes_truenodeids: Vec<i64>,
+    nodes_falsenodeids: Vec<i64>,
+    nodes_missing_value_tracks_true: Vec<i64>,
+    target_treeids: Vec<i64>,
+    target_nodeids: Vec<i64>,
+    target_weights: Vec<f32>,
+}
+
+impl TreeEnsembleAttributes {
+    /// Add tree `tree_id` nodes with leaf values multiplied by `weight`.
+    fn add_tree(&mut self, tree_id: i64, weight: D, nodes: &[ExplicitNode]) {
+        for (node_id, node) in nodes.iter().enumerate() {
+            self.nodes_treeids.push(tree_id);
+            self.nodes_nodeids.push(node_id as i64);
+            match node {
+                ExplicitNode::Split { feature, threshold, children, default_left, .. } => {
+                    self.nodes_featureids.push(*feature as i64);
+                    self.nodes_values.push(*threshold as f32);
+                    self.nodes_modes.push("BRANCH_LEQ");
+                    self.nodes_truenodeids.push(children[0] as i64);
+                    self.nodes_falsenodeids.push(children[1] as i64);
+                    self.nodes_missing_value_tracks_true.push(*default_left as i64);
+                },
+                ExplicitNode::Leaf { value, .. } => {
+                    self.nodes_featureids.push(0);
+                    self.nodes_values.push(0.0);
+                    self.nodes_modes.push("LEAF");
+                    self.nodes_truenodeids.push(0);
+                    self.nodes_falsenodeids.push(0);
+                    self.nodes_missing_value_tracks_true.push(0);
+                    self.target_treeids.push(tree_id);
+                    self.target_nodeids.push(node_id as i64);
+                    self.target_weights.push((weight * value) as f32);
+                },
+            }
+        }
+    }
+}
+
+/// Make ONNX `TreeEnsembleRegressor` node of additive tree ensemble.
+fn tree_ensemble_node(ensemble: &WeightedTrees) -> Message {
+    let mut attrs = TreeEnsembleAttributes::default();
+    for (tree_id, (weight, nodes)) in ensemble.trees.iter().enumerate() {
+        attrs.add_tree(tree_id as i64, *weight, nodes);
+    }
+    let target_ids = vec![0i64; attrs.target_weights.len()];
+    [
+        ints_attribute("nodes_treeids", &attrs.nodes_treeids),
+        ints_attribute("nodes_nodeids", &attrs.nodes_nodeids),
+        ints_attribute("nodes_featureids", &attrs.nodes_featureids),
+        floats_attribute("nodes_values", &attrs.nodes_values),
+        strings_attribute("nodes_modes", &attrs.nodes_modes),
+        ints_attribute("nodes_truenodeids", &attrs.nodes_truenodeids),
+        ints_attribute("nodes_falsenodeids", &attrs.nodes_falsenodeids),
+        ints_attribute("nodes_missing_value_tracks_true", &attrs.nodes_missing_value_tracks_true),
+        ints_attribute("target_treeids", &attrs.target_treeids),
+        ints_attribute("target_nodeids", &attrs.target_nodeids),
+        ints_attribute("target_ids", &target_ids),
+        floats_attribute("target_weights", &attrs.target_weights),
+        int_attribute("n_targets", 1),
+        floats_attribute("base_values", &[ensemble.base_value as f32]),
+        string_attribute("aggregate_function", "SUM"),
+        string_attribute("post_transform", "NONE"),
+    ].iter().fold(
+        Message::default()
+            .string(1, INPUT_NAME)
+            .string(2, OUTPUT_NAME)
+            .string(3, "TreeEnsembleRegressor")
+            .string(4, "TreeEnsembleRegressor")
+            .string(7, ML_DOMAIN),
+        |node, attr| node.bytes(5, &attr.buf)
+    )
+}
+
+/// Serialize `model` to ONNX model bytes.
+///
+/// Model must be fitted, number of input features is taken from the model
+/// or from the largest feature index used in splits for models saved by older versions.
+pub fn to_onnx<M: AsWeightedTrees>(model: &M) -> Result<Vec<u8>> {
+    let ensemble = model.weighted_trees()?;
+    let n_features = if ensemble.features.n_features > 0 {
+        ensemble.features.n_features
+    } else {
+        ensemble.trees.iter()
+            .flat_map(|(_, nodes)| nodes.iter())
+            .filter_map(|node| match node {
+                ExplicitNode::Split { feature, .. } => Some(feature + 1),
+                ExplicitNode::Leaf { .. } => None,
+            })
+            .max()
+            .unwrap_or(1)
+    };
+    let graph = Message::default()
+        .message(1, tree_ensemble_node(&ensemble))
+        .string(2, "woods")
+        .message(11, tensor_info(INPUT_NAME, n_features))
+        .message(12, tensor_info(OUTPUT_NAME, 1));
+    let model = Message::default()
+        .int(1, IR_VERSION)
+        .string(2, "woods")
+        .string(3, env!("CARGO_PKG_VERSION"))
+        .message(7, graph)
+        .message(8, Message::default().string(1, "").int(2, ONNX_OPSET))
+        .message(8, Message::default().string(1, ML_DOMAIN).int(2, ML_OPSET));
+    Ok(model.buf)
+}
+
+/// Save `model` to ONNX file (`filename`).
+pub fn save_onnx<M: AsWeightedTrees>(model: &M, filename: &str) -> Result<()> {
+    let bytes = to_onnx(model)?;
+    File::create(filename)?.write_all(&bytes)?;
+    Ok(())
+}
@@ -0,0 +1,228 @@
+//! XGBoost models: import of JSON models (`Booster.save_model("model.json")`) and
+//! JSON dumps (`Booster.dump_model(..., dump_format="json")`), export to JSON dump.
+//!
+//! XGBoost splits `x < split_condition` are converted to Woods splits `x <= threshold`
+//! with the previous float as `threshold`, and vice versa on export.
+//! Imported models predict raw margins, i.e. `Booster.predict(..., output_margin=True)`.
+
+use std::io;
+use serde_json::{json, Value};
+use crate::tree::ExplicitNode;
+use crate::estimator::InputFeatures;
+use crate::utils::numerics::{D, next_down, next_up};
+use crate::error::{Result, WoodsError};
+use super::{AsWeightedTrees, WeightedTrees, depth_first};
+
+/// Make corrupted model error with `msg`.
+fn invalid(msg: &str) -> WoodsError {
+    WoodsError::CorruptedModel(format!("XGBoost model: {}", msg))
+}
+
+/// Get field by `path` of nested objects.
+fn get<'a>(value: &'a Value, path: &[&str]) -> Result<&'a Value> {
+    path.iter().try_fold(value, |value, key| {
+        value.get(key).ok_or_else(|| invalid(&format!("missing field `{}`", key)))
+    })
+}
+
+/// Parse number, stored as JSON number or string, e.g. `"5E-1"` or `"[5E-1]"`.
+fn number(value: &Value) -> Result<D> {
+    match value {
+        Value::Number(number) => number.as_f64(),
+        Value::String(text) => text.trim_matches(|c| c == '[' || c == ']').parse().ok(),
+        Value::Bool(flag) => Some(*flag as u8 as D),
+        _ => None,
+    }.ok_or_else(|| invalid(&format!("incorrect number `{}`", value)))
+}
+
+/// Parse array of numbers.
+fn numbers(value: &Value) -> Result<Vec<D>> {
+    value.as_array()
+        .ok_or_else(|| invalid("array is expected"))?
+        .iter()
+        .map(number)
+        .collect()
+}
+
+/// Convert tree of JSON model, nodes are stored in parallel arrays.
+fn convert_tree(tree: &Value) -> Result<Vec<ExplicitNode>> {
+    let left = numbers(get(tree, &["left_children"])?)?;
+    let right = numbers(get(tree, &["right_children"])?)?;
+    let features = numbers(get(tree, &["split_indices"])?)?;
+    let conditions = numbers(get(tree, &["split_conditions"])?)?;
+    let default_left = numbers(get(tree, &["default_left"])?)?;
+    let optional = |key| tree.get(key).map_or(Ok(vec![]), numbers);
+    let gains = optional("loss_changes")?;
+    let covers = optional("sum_hessian")?;
+    if optional("split_type")?.iter().any(|split_type| *split_type != 0.0) {
+        return Err(WoodsError::UnsupportedModel("categorical splits".into()));
+    }
+    let n_nodes = left.len();
+    if [&right, &features, &conditions, &default_left].iter().any(|values| values.len() != n_nodes) {
+        return Err(invalid("tree arrays have different lengths"));
+    }
+    let nodes: Vec<ExplicitNode> = (0..n_nodes).map(|id| {
+        let samples = covers.get(id).copied().unwrap_or(0.0) as usize;
+        if left[id] < 0.0 {
+            ExplicitNode::Leaf { value: conditions[id], samples }
+        } else {
+            ExplicitNode::Split {
+                feature: features[id] as usize,
+                threshold: next_down(conditions[id]),
+                children: [left[id] as usize, right[id] as usize],
+                default_left: default_left[id] != 0.0,
+                gain: gains.get(id).copied().unwrap_or(0.0),
+                samples,
+            }
+        }
+    }).collect();
+    depth_first(&nodes)
+}
+
+/// Convert base score to margin by objective link function.
+fn base_margin(base_score: D, objective: &str) -> D {
+    match objective {
+        "binary:logistic" | "reg:logistic" | "binary:logitraw" => (base_score / (1.0 - base_score)).ln(),
+        "count:poisson" | "reg:gamma" | "reg:tweedie" => base_score.ln(),
+        _ => base_score,
+    }
+}
+
+/// Convert JSON model of `gbtree` booster.
+fn convert_model(model: &Value) -> Result<WeightedTrees> {
+    let learner = get(model, &["learner"])?;
+    let booster = get(learner, &["gradient_booster"])?;
+    match booster.get("name").and_then(Value::as_str) {
+        Some("gbtree") => {},
+        name => return Err(WoodsError::UnsupportedModel(format!("booster `{}`", name.unwrap_or("")))),
+    }
+    let params = get(learner, &["learner_model_param"])?;
+    if params.get("num_class").map_or(Ok(0.0), number)? > 1.0 {
+        return Err(WoodsError::UnsupportedModel("multi-class models".into()));
+    }
+    let n_features = number(get(params, &["num_feature"])?)? as usize;
+    let objective = learner.get("objective")
+        .and_then(|objective| objective.get("name"))
+        .and_then(Value::as_str)
+        .unwrap_or("");
+    let names: Option<Vec<String>> = learner.get("feature_names")
+        .and_then(Value::as_array)
+        .map(|names| names.iter().filter_map(|name| name.as_str().map(String::from)).collect())
+        .filter(|names: &Vec<String>| names.len() == n_features && n_features > 0);
+    let trees = get(booster, &["model", "trees"])?
+        .as_array()
+        .ok_or_else(|| invalid("array of trees is expected"))?
+        .iter()
+        .map(|tree| Ok((1.0, convert_tree(tree)?)))
+        .collect::<Result<_>>()?;
+    Ok(WeightedTrees {
+        features: InputFeatures { n_features, names },
+        base_value: base_margin(number(get(params, &["base_score"])?)?, objective),
+        trees,
+    })
+}
+
+/// Flatten tree `node` of JSON dump to `nodes`, indexed by node ids.
+fn flatten_dump_node(node: &Value, nodes: &mut Vec<Option<ExplicitNode>>) -> Result<()> {
+    let id = number(get(node, &["nodeid"])?)? as usize;
+    let samples = node.get("cover").map_or(Ok(0.0), number)? as usize;
+    let explicit_node = if let Some(value) = node.get("leaf") {
+        ExplicitNode::Leaf { value: number(value)?, samples }
+    } else {
+        let split = get(node, &["split"])?.as_str().unwrap_or("");
+        let feature = split.strip_prefix('f')
+            .and_then(|index| index.parse().ok())
+            .ok_or_else(|| WoodsError::UnsupportedModel(format!("feature name `{}` instead of index", split)))?;
+        let children = [number(get(node, &["yes"])?)? as usize, number(get(node, &["no"])?)? as usize];
+        let missing = node.get("missing").map_or(Ok(children[0] as D), number)? as usize;
+        for child in get(node, &["children"])?.as_array().ok_or_else(|| invalid("array of children is expected"))? {
+            flatten_dump_node(child, nodes)?;
+        }
+        ExplicitNode::Split {
+            feature,
+            threshold: next_down(number(get(node, &["split_condition"])?)?),
+            children,
+            default_left: missing == children[0],
+            gain: node.get("gain").map_or(Ok(0.0), number)?,
+            samples,
+        }
+    };
+    if nodes.len() <= id {
+        nodes.resize(id + 1, None);
+    }
+    nodes[id] = Some(explicit_node);
+    Ok(())
+}
+
+/// Convert JSON dump: array of trees with nested nodes.
+fn convert_dump(trees: &[Value]) -> Result<WeightedTrees> {
+    let trees = trees.iter().map(|tree| {
+        let mut nodes = vec![];
+        flatten_dump_node(tree, &mut nodes)?;
+        let nodes: Vec<ExplicitNode> = nodes.into_iter()
+            .collect::<Option<_>>()
+            .ok_or_else(|| invalid("node ids are not contiguous"))?;
+        Ok((1.0, depth_first(&nodes)?))
+    }).collect::<Result<_>>()?;
+    Ok(WeightedTrees {
+        features: InputFeatures::default(),
+        base_value: 0.0,
+        trees,
+    })
+}
+
+/// Import XGBoost JSON model or JSON dump from `text`.
+///
+/// Base margin is taken from the model (dumps have no base margin, it is `0`),
+/// unless `base_score` is set. Only numerical splits of single-output `gbtree` models are supported.
+pub fn from_xgboost_json(text: &str, base_score: Option<D>) -> Result<WeightedTrees> {
+    let value: Value = serde_json::from_str(text).map_err(|e| invalid(&e.to_string()))?;
+    let mut ensemble = match &value {
+        Value::Array(trees) => convert_dump(trees)?,
+        _ => convert_model(&value)?,
+    };
+    if let Some(base_score) = base_score {
+        ensemble.base_value = base_score;
+    }
+    ensemble.check()?;
+    Ok(ensemble)
+}
+
+/// Make JSON dump of node `id` of tree `nodes`, leaf values are scaled by `weight` and shifted by `bias`.
+fn dump_node(nodes: &[ExplicitNode], id: usize, depth: usize, weight: D, bias: D) -> Value {
+    match &nodes[id] {
+        ExplicitNode::Split { feature, threshold, children, default_left, gain, samples } => json!({
+            "nodeid": id,
+            "depth": depth,
+            "split": format!("f{}", feature),
+            "split_condition": next_up(*threshold),
+            "yes": children[0],
+            "no": children[1],
+            "missing": children[!default_left as usize],
+            "gain": gain,
+            "cover": samples,
+            "children": [
+                dump_node(nodes, children[0], depth + 1, weight, bias),
+                dump_node(nodes, children[1], depth + 1, weight, bias),
+            ],
+        }),
+        ExplicitNode::Leaf { value, samples } => json!({
+            "nodeid": id,
+            "leaf": weight * value + bias,
+            "cover": samples,
+        }),
+    }
+}
+
+/// Export `model` to XGBoost JSON dump: array of trees with nested nodes.
+///
+/// Tree weights are folded into leaf values and base value is added to leaves of the first tree,
+/// so prediction is the sum of leaf values.
+pub fn to_xgboost_json<M: AsWeightedTrees>(model: &M) -> Result<String> {
+    let ensemble = model.weighted_trees()?;
+    let trees: Vec<Value> = ensemble.trees.iter().enumerate().map(|(i, (weight, nodes))| {
+        let bias = if i == 0 { ensemble.base_value } else { 0.0 };
+        dump_node(nodes, 0, 0, *weight, bias)
+    }).collect();
+    Ok(serde_json::to_string_pretty(&trees).map_err(io::Error::from)?)
+}
@@ -0,0 +1,96 @@
+//! Model inspection: partial dependence and individual conditional expectation (ICE) curves.
+//! 
+//! Partial dependence of any [`Estimator`] can be calculated with [`partial_dependence`] by
+//! replacing values of selected features with grid values and predicting on the whole dataset.
+//! Tree-based estimators implement [`PartialDependenceRecursion`], which calculates averaged
+//! curves by weighted tree traversal without predictions on the dataset.
+
+use ndarray::{ArrayView2, Array1, Array2, Axis};
+use rayon::prelude::*;
+use crate::estimator::Estimator;
+use crate::utils::numerics::{D, Float};
+use crate::error::{Result, WoodsError};
+
+/// Average and individual conditional expectation curves.
+pub struct PartialDependence<F = D> {
+    /// Average prediction at each grid point, shape `(n_points)`.
+    pub average: Array1<F>,
+    /// Prediction for each sample at each grid point, shape `(n_samples, n_points)`.
+    pub individual: Array2<F>,
+}
+
+/// Estimator that can calculate partial dependence by tree recursion.
+pub trait PartialDependenceRecursion: Estimator {
+    /// Calculate partial dependence of prediction on `features` at `grid` points.
+    /// 
+    /// Each `grid` row is a point, containing values of `features`.
+    /// Training samples distribution of other features is taken from the tree nodes.
+    fn partial_dependence_recursion(&self, features: &[usize], grid: &ArrayView2<'_, Self::F>) -> Array1<Self::F>;
+}
+
+/// Make grid of all combinations of feature values, one point per row.
+/// 
+/// For example, grid of two features with `n` and `m` values has `n * m` points.
+pub fn cartesian_grid<F: Float>(values: &[Array1<F>]) -> Array2<F> {
+    let n_points = values.iter().map(|v| v.dim()).product();
+    let mut grid = Array2::zeros((n_points, values.len()));
+    let mut period = n_points;
+    for (k, feature_values) in values.iter().enumerate() {
+        period /= feature_values.dim();
+        for (i, mut point) in grid.outer_iter_mut().enumerate() {
+            point[k] = feature_values[(i / period) % feature_values.dim()];
+        }
+    }
+    grid
+}
+
+/// Check that `grid` has one column per feature and `features` are less than `n_features`.
+pub fn check_grid<F>(n_features: usize, features: &[usize], grid: &ArrayView2<'_, F>) -> Result<()> {
+    if grid.dim().1 != features.len() {
+        return Err(WoodsError::ShapeMismatch { what: "grid columns", expected: features.len(), found: grid.dim().1 });
+    }
+    match features.iter().find(|f| **f >= n_features) {
+        Some(f) => Err(WoodsError::InvalidParameter(
+            format!("feature {} is out of range, number of features is {}", f, n_features)
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Calculate partial dependence and ICE curves of `est` predictions on `features`.
+/// 
+/// Each `grid` row is a point, containing values of `features` (see [`cartesian_grid`]).
+/// For each point values of `features` in all samples are replaced with point values
+/// and the estimator predicts on the modified data. Grid points are evaluated in parallel.
+/// 
+/// Grid shape and feature indices are checked with [`check_grid`].
+/// 
+/// **Important**: each `columns` row correspond to the input feature, not sample.
+pub fn partial_dependence<Est: Estimator + Sync>(
+        est: &Est,
+        columns: &ArrayView2<'_, Est::F>,
+        features: &[usize],
+        grid: &ArrayView2<'_, Est::F>
+    ) -> Result<PartialDependence<Est::F>> {
+    check_grid(columns.dim().0, features, grid)?;
+    let n_samples = columns.dim().1;
+    let predictions: Vec<Array1<Est::F>> = (0..grid.dim().0)
+        .into_par_iter()
+        .map(|p| {
+            let mut modified = columns.to_owned();
+            for (k, feature) in features.iter().enumerate() {
+                modified.row_mut(*feature).fill(grid[[p, k]]);
+            }
+            est.predict(&modified.view())
+        })
+        .collect::<Result<_>>()?;
+
+    let mut individual = Array2::zeros((n_samples, predictions.len()));
+    for (mut curve_point, preds) in individual.axis_iter_mut(Axis(1)).zip(&predictions) {
+        curve_point.assign(preds);
+    }
+    Ok(PartialDependence {
+        average: individual.mean_axis(Axis(0)).unwrap_or_else(|| Array1::zeros(predictions.len())),
+        individual,
+    })
+}
@@ -4,22 +4,32 @@
 //! 
 //! All estimators use [`ndarray`] as a tensor framework.
 //! 
+//! # Usage as a Rust library
+//! Core modules ([`estimator`], [`tree`], [`ensemble`], [`utils::serialization`], ...) don't depend on `pyo3`,
+//! Python bindings are built only with `python` feature, the command-line tool (`src/bin/woods.rs`)
+//! with default `cli` feature:
+//! ```text
+//! [dependencies]
+//! woods = { git = "https://github.com/andruekonst/Woods", default-features = false }
+//! ```
+//! CSV and LibSVM files can be loaded by [`data`] module.
+//! 
 //! # Installation of Python extension
 //! 1. Change toolchain to nightly (required by `pyo3`)
-//! ```
+//! ```text
 //! > rustup toolchain install nightly
 //! > rustup default nightly
 //! ```
-//! 2. Run `setup.py` to build / install extension
-//! ```
+//! 2. Run `setup.py` to build / install extension (it enables `python` feature)
+//! ```text
 //! > python setup.py install
 //! ``` 
 //! 
 //! For *Windows* platform it is recommended to use [Anaconda](http://anaconda.com).
 //! 
 //! # Usage examples
-//! 1. Train, save, load and predict with [`DeepGradientBoosting`] model.
-//! ```
+//! 1. Train, save, load and predict with `DeepGradientBoosting` model.
+//! ```python
 //! import woods
 //! from sklearn.datasets import load_boston
 //! from sklearn.model_selection import train_test_split
@@ -39,188 +49,32 @@
 //! 
 //! # save JSON representation of model to filesystem (optional step)
 //! model.save("my_deep_gbm.json", format="json")
+//! # or compressed binary representation: any of "json", "bincode", "msgpack", "cbor"
+//! # with optional "+gzip" or "+zstd" compression
+//! model.save("my_deep_gbm.bin.zst", format="bincode+zstd")
 //! # clean up memory
 //! del model
 //! 
-//! # make empty model and load contents from JSON file
+//! # make empty model and load contents from JSON file, format is detected by file content
 //! loaded_model = woods.DeepGradientBoosting()
-//! loaded_model.load("my_deep_gbm.json", format="json")
+//! loaded_model.load("my_deep_gbm.json")
 //! 
 //! # evaluate quality of loaded model on test data
 //! print("r^2 score:", r2_score(y_test, loaded_model.predict(X_test)))
 //! ```
 
+pub mod error;
+pub mod data;
 pub mod estimator;
 pub mod ensemble;
+pub mod export;
+pub mod inspection;
+pub mod metrics;
+pub mod model_selection;
 // pub mod rule;
 pub mod tree;
 // pub mod boosting;
 // pub mod deep_boosting;
 pub mod utils;
-
-use crate::estimator::{Estimator, ConstructibleWithCopyArg, ConstructibleWithArg};
-use tree::rule::{RandomSplitRule, SplitRule};
-use crate::tree::{TreeParameters, DecisionTreeImpl};
-use crate::ensemble::boosting::{GradientBoostingParameters, GradientBoostingImpl, TreeGBM};
-use crate::ensemble::deep_boosting::{DeepBoostingParameters, DeepBoostingImpl};
-use crate::ensemble::AverageEnsemble;
-use utils::numerics::D as DType;
-use utils::serialization::{load, save};
-
-use ndarray::{ArrayView2, Array2};
-use numpy::{IntoPyArray, PyArray2, PyArray1};
-use pyo3::prelude::{pymodule, Py, PyModule, PyResult, Python, pyclass, pymethods, PyObject};
-
-
-fn to_columns<D: numpy::types::TypeNum>(x: &PyArray2<D>) -> Array2<D> {
-    let arr: ArrayView2<'_, D> = x.as_array();
-    arr.t().to_owned()
-}
-
-#[pyclass(module="woods")]
-pub struct DecisionRule {
-    rule: RandomSplitRule
-}
-
-#[pymethods]
-impl DecisionRule {
-    #[new]
-    fn new() -> Self {
-        DecisionRule {
-            rule: RandomSplitRule::new()
-        }
-    }
-    
-    fn fit(&mut self, x: &PyArray2<DType>, y: &PyArray1<DType>) {
-        let features = to_columns(x);
-        let target = y.as_array();
-        assert_eq!(features.dim().1, target.dim());
-        self.rule.fit(&features.view(), &target);
-    }
-
-    fn predict(&self, py: Python<'_>, x: &PyArray2<DType>) -> Py<PyArray1<DType>> {
-        let features = to_columns(x);
-        self.rule.predict(&features.view()).into_pyarray(py).to_owned()
-    }
-}
-
-#[pyclass(module="woods")]
-pub struct DecisionTree {
-    tree: DecisionTreeImpl<RandomSplitRule>
-}
-
-#[pymethods]
-impl DecisionTree {
-    #[new]
-    fn new(depth: Option<u8>, min_samples_split: Option<usize>) -> Self {
-        let params = TreeParameters::new(depth, min_samples_split);
-        DecisionTree {
-            tree: DecisionTreeImpl::new(params)
-        }
-    }
-    
-    fn fit(&mut self, x: &PyArray2<DType>, y: &PyArray1<DType>) {
-        let features = to_columns(x);
-        let target = y.as_array();
-        assert_eq!(features.dim().1, target.dim());
-        self.tree.fit(&features.view(), &target);
-    }
-
-    fn predict(&self, py: Python<'_>, x: &PyArray2<DType>) -> Py<PyArray1<DType>> {
-        let features = to_columns(x);
-        self.tree.predict(&features.view()).into_pyarray(py).to_owned()
-    }
-
-    fn save(&self, filename: &str, format: Option<&str>) -> PyResult<()> {
-        save(&self.tree, filename, format)
-    }
-
-    fn load(&mut self, filename: &str, format: Option<&str>) -> PyResult<()> {
-        load(&mut self.tree, filename, format)
-    }
-}
-
-#[pyclass(module="woods")]
-pub struct GradientBoosting {
-    gbm: TreeGBM
-}
-
-#[pymethods]
-impl GradientBoosting {
-    #[new]
-    fn new(depth: Option<u8>, min_samples_split: Option<usize>, n_estimators: Option<u32>,
-           learning_rate: Option<DType>) -> Self {
-        let est_params = TreeParameters::new(depth, min_samples_split);
-        let params = GradientBoostingParameters::new(est_params, n_estimators, learning_rate);
-        GradientBoosting {
-            gbm: GradientBoostingImpl::new(params)
-        }
-    }
-    
-    fn fit(&mut self, x: &PyArray2<DType>, y: &PyArray1<DType>) {
-        let features = to_columns(x);
-        let target = y.as_array();
-        assert_eq!(features.dim().1, target.dim());
-        self.gbm.fit(&features.view(), &target);
-    }
-
-    fn predict(&self, py: Python<'_>, x: &PyArray2<DType>) -> Py<PyArray1<DType>> {
-        let features = to_columns(x);
-        self.gbm.predict(&features.view()).into_pyarray(py).to_owned()
-    }
-
-    fn save(&self, filename: &str, format: Option<&str>) -> PyResult<()> {
-        save(&self.gbm, filename, format)
-    }
-
-    fn load(&mut self, filename: &str, format: Option<&str>) -> PyResult<()> {
-        load(&mut self.gbm, filename, format)
-    }
-}
-
-#[pyclass(module="woods")]
-pub struct DeepGradientBoosting {
-    dgbm: DeepBoostingImpl<AverageEnsemble<TreeGBM>>
-}
-
-#[pymethods]
-impl DeepGradientBoosting {
-    #[new]
-    fn new(n_estimators: Option<u32>, layer_width: Option<u32>, learning_rate: Option<DType>) -> Self {
-        let params = DeepBoostingParameters::new(n_estimators, layer_width, learning_rate);
-        DeepGradientBoosting {
-            dgbm: DeepBoostingImpl::new(params)
-        }
-    }
-    
-    fn fit(&mut self, x: &PyArray2<DType>, y: &PyArray1<DType>) {
-        let features = to_columns(x);
-        let target = y.as_array();
-        assert_eq!(features.dim().1, target.dim());
-        self.dgbm.fit(&features.view(), &target);
-    }
-
-    fn predict(&self, py: Python<'_>, x: &PyArray2<DType>) -> Py<PyArray1<DType>> {
-        let features = to_columns(x);
-        self.dgbm.predict(&features.view()).into_pyarray(py).to_owned()
-    }
-
-    fn save(&self, filename: &str, format: Option<&str>) -> PyResult<()> {
-        save(&self.dgbm, filename, format)
-    }
-
-    fn load(&mut self, filename: &str, format: Option<&str>) -> PyResult<()> {
-        load(&mut self.dgbm, filename, format)
-    }
-}
-
-
-#[pymodule]
-fn woods(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
-    m.add_class::<DecisionRule>()?;
-    m.add_class::<DecisionTree>()?;
-    m.add_class::<GradientBoosting>()?;
-    m.add_class::<DeepGradientBoosting>()?;
-
-    Ok(())
-}
\ No newline at end of file
+#[cfg(feature = "python")]
+mod python;
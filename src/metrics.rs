@@ -0,0 +1,350 @@
+//! Metrics of predictions: regression losses, classification and ranking scores.
+//!
+//! Each metric implements [`Metric`] and can be found by name with [`metric_by_name`]:
+//!
+//! | Name | Metric | Better |
+//! |------|--------|--------|
+//! | `mse` | [`MeanSquaredError`] | lower |
+//! | `rmse` | [`RootMeanSquaredError`] | lower |
+//! | `mae` | [`MeanAbsoluteError`] | lower |
+//! | `r2` | [`R2`] (coefficient of determination) | higher |
+//! | `mape` | [`MeanAbsolutePercentageError`] | lower |
+//! | `quantile`, `quantile@alpha` | [`QuantileLoss`] (pinball loss, default `alpha` is `0.5`) | lower |
+//! | `logloss` | [`LogLoss`] (binary cross-entropy of probabilities) | lower |
+//! | `auc` | [`Auc`] (area under ROC curve) | higher |
+//! | `accuracy` | [`Accuracy`] | higher |
+//! | `ndcg`, `ndcg@k` | [`Ndcg`] (normalized discounted cumulative gain) | higher |
+//!
+//! Classification metrics expect labels `0` and `1` (`auc` treats positive labels as the positive class),
+//! predictions of `logloss` and `auc` are probabilities or scores of the positive class.
+//! Metrics are weighted by optional sample weights, except `ndcg`.
+
+use ndarray::ArrayView1;
+use crate::utils::numerics::{D, Float};
+use crate::error::{Result, WoodsError};
+
+/// Available metric names, see [`metric_by_name`].
+pub const METRICS: [&str; 10] = ["mse", "rmse", "mae", "r2", "mape", "quantile", "logloss", "auc", "accuracy", "ndcg"];
+
+/// Metric of predictions and target.
+pub trait Metric<F: Float = D>: Send + Sync {
+    /// Metric name, e.g. `quantile@0.9`.
+    fn name(&self) -> String;
+
+    /// Calculate metric of `preds` and `target` of the same length, optionally weighted by sample `weights`.
+    fn evaluate(&self, preds: &ArrayView1<'_, F>, target: &ArrayView1<'_, F>, weights: Option<&ArrayView1<'_, D>>) -> D;
+
+    /// Higher values are better (scores like R^2 or AUC), otherwise metric is a loss.
+    fn greater_is_better(&self) -> bool {
+        false
+    }
+
+    /// Calculate metric of samples with query `groups`, e.g. average over queries for ranking metrics.
+    ///
+    /// Groups are ignored by default.
+    fn evaluate_groups(&self, preds: &ArrayView1<'_, F>, target: &ArrayView1<'_, F>,
+                       weights: Option<&ArrayView1<'_, D>>, groups: &[usize]) -> D {
+        let _ = groups;
+        self.evaluate(preds, target, weights)
+    }
+
+    /// Loss to minimize: the metric, negated if higher values are better.
+    fn loss(&self, preds: &ArrayView1<'_, F>, target: &ArrayView1<'_, F>, weights: Option<&ArrayView1<'_, D>>) -> D {
+        let value = self.evaluate(preds, target, weights);
+        if self.greater_is_better() { -value } else { value }
+    }
+}
+
+/// Find metric by `name` from [`METRICS`] with optional parameter after `@`, e.g. `quantile@0.9` or `ndcg@10`.
+pub fn metric_by_name<F: Float>(name: &str) -> Result<Box<dyn Metric<F>>> {
+    let (base, param) = match name.find('@') {
+        Some(at) => (&name[..at], Some(&name[at + 1..])),
+        None => (name, None),
+    };
+    let incorrect_param = || WoodsError::InvalidParameter(format!("Incorrect parameter of metric `{}`", name));
+    let metric: Box<dyn Metric<F>> = match (base, param) {
+        ("mse", None) => Box::new(MeanSquaredError),
+        ("rmse", None) => Box::new(RootMeanSquaredError),
+        ("mae", None) => Box::new(MeanAbsoluteError),
+        ("r2", None) => Box::new(R2),
+        ("mape", None) => Box::new(MeanAbsolutePercentageError),
+        ("quantile", None) => Box::new(QuantileLoss::new(0.5)),
+        ("quantile", Some(alpha)) => match alpha.parse::<D>() {
+            Ok(alpha) if alpha > 0.0 && alpha < 1.0 => Box::new(QuantileLoss::new(alpha)),
+            _ => return Err(incorrect_param()),
+        },
+        ("logloss", None) => Box::new(LogLoss),
+        ("auc", None) => Box::new(Auc),
+        ("accuracy", None) => Box::new(Accuracy),
+        ("ndcg", None) => Box::new(Ndcg::new(None)),
+        ("ndcg", Some(k)) => Box::new(Ndcg::new(Some(k.parse().map_err(|_| incorrect_param())?))),
+        _ => return Err(WoodsError::InvalidParameter(
+            format!("Incorrect metric: `{}`. Please, use one of: {:?}", name, METRICS)
+        )),
+    };
+    Ok(metric)
+}
+
+/// Weighted mean of `error` of each prediction and target.
+fn weighted_mean<F: Float, E: Fn(D, D) -> D>(preds: &ArrayView1<'_, F>, target: &ArrayView1<'_, F>,
+                                             weights: Option<&ArrayView1<'_, D>>, error: E) -> D {
+    let errors = preds.iter().zip(target.iter()).map(|(p, t)| error(p.to_d(), t.to_d()));
+    match weights {
+        Some(weights) => {
+            let sum: D = errors.zip(weights.iter()).map(|(e, w)| e * w).sum();
+            sum / weights.sum()
+        },
+        None => errors.sum::<D>() / preds.dim() as D,
+    }
+}
+
+/// Mean squared error.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MeanSquaredError;
+
+impl<F: Float> Metric<F> for MeanSquaredError {
+    fn name(&self) -> String {
+        "mse".into()
+    }
+
+    fn evaluate(&self, preds: &ArrayView1<'_, F>, target: &ArrayView1<'_, F>, weights: Option<&ArrayView1<'_, D>>) -> D {
+        weighted_mean(preds, target, weights, |p, t| (p - t) * (p - t))
+    }
+}
+
+/// Root of mean squared error.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RootMeanSquaredError;
+
+impl<F: Float> Metric<F> for RootMeanSquaredError {
+    fn name(&self) -> String {
+        "rmse".into()
+    }
+
+    fn evaluate(&self, preds: &ArrayView1<'_, F>, target: &ArrayView1<'_, F>, weights: Option<&ArrayView1<'_, D>>) -> D {
+        MeanSquaredError.evaluate(preds, target, weights).sqrt()
+    }
+}
+
+/// Mean absolute error.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MeanAbsoluteError;
+
+impl<F: Float> Metric<F> for MeanAbsoluteError {
+    fn name(&self) -> String {
+        "mae".into()
+    }
+
+    fn evaluate(&self, preds: &ArrayView1<'_, F>, target: &ArrayView1<'_, F>, weights: Option<&ArrayView1<'_, D>>) -> D {
+        weighted_mean(preds, target, weights, |p, t| (p - t).abs())
+    }
+}
+
+/// Coefficient of determination R^2.
+///
+/// Constant target gives `1` for perfect predictions, otherwise `0`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct R2;
+
+impl<F: Float> Metric<F> for R2 {
+    fn name(&self) -> String {
+        "r2".into()
+    }
+
+    fn evaluate(&self, preds: &ArrayView1<'_, F>, target: &ArrayView1<'_, F>, weights: Option<&ArrayView1<'_, D>>) -> D {
+        let mean = weighted_mean(target, target, weights, |t, _| t);
+        let residual = weighted_mean(preds, target, weights, |p, t| (t - p) * (t - p));
+        let total = weighted_mean(target, target, weights, |t, _| (t - mean) * (t - mean));
+        if total == 0.0 {
+            return if residual == 0.0 { 1.0 } else { 0.0 };
+        }
+        1.0 - residual / total
+    }
+
+    fn greater_is_better(&self) -> bool {
+        true
+    }
+}
+
+/// Mean absolute percentage error, relative to absolute target values (not multiplied by 100).
+///
+/// Zero target values are replaced by machine epsilon.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MeanAbsolutePercentageError;
+
+impl<F: Float> Metric<F> for MeanAbsolutePercentageError {
+    fn name(&self) -> String {
+        "mape".into()
+    }
+
+    fn evaluate(&self, preds: &ArrayView1<'_, F>, target: &ArrayView1<'_, F>, weights: Option<&ArrayView1<'_, D>>) -> D {
+        weighted_mean(preds, target, weights, |p, t| (p - t).abs() / t.abs().max(D::EPSILON))
+    }
+}
+
+/// Quantile (pinball) loss of predictions of `alpha` quantile.
+#[derive(Debug, Clone, Copy)]
+pub struct QuantileLoss {
+    /// Quantile level in `(0, 1)`, `0.5` corresponds to a half of mean absolute error.
+    pub alpha: D,
+}
+
+impl QuantileLoss {
+    pub fn new(alpha: D) -> Self {
+        QuantileLoss { alpha }
+    }
+}
+
+impl<F: Float> Metric<F> for QuantileLoss {
+    fn name(&self) -> String {
+        format!("quantile@{}", self.alpha)
+    }
+
+    fn evaluate(&self, preds: &ArrayView1<'_, F>, target: &ArrayView1<'_, F>, weights: Option<&ArrayView1<'_, D>>) -> D {
+        let alpha = self.alpha;
+        weighted_mean(preds, target, weights, |p, t| if t >= p { alpha * (t - p) } else { (1.0 - alpha) * (p - t) })
+    }
+}
+
+/// Binary cross-entropy of predicted probabilities, clipped to `[1e-15, 1 - 1e-15]`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogLoss;
+
+/// Minimal probability of [`LogLoss`].
+const LOG_LOSS_EPS: D = 1e-15;
+
+impl<F: Float> Metric<F> for LogLoss {
+    fn name(&self) -> String {
+        "logloss".into()
+    }
+
+    fn evaluate(&self, preds: &ArrayView1<'_, F>, target: &ArrayView1<'_, F>, weights: Option<&ArrayView1<'_, D>>) -> D {
+        weighted_mean(preds, target, weights, |p, t| {
+            let p = p.clamp(LOG_LOSS_EPS, 1.0 - LOG_LOSS_EPS);
+            -(t * p.ln() + (1.0 - t) * (1.0 - p).ln())
+        })
+    }
+}
+
+/// Area under ROC curve, samples with positive target are positive.
+///
+/// Tied predictions count as a half of correctly ordered pair, `NaN` if target has one class.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Auc;
+
+impl<F: Float> Metric<F> for Auc {
+    fn name(&self) -> String {
+        "auc".into()
+    }
+
+    fn evaluate(&self, preds: &ArrayView1<'_, F>, target: &ArrayView1<'_, F>, weights: Option<&ArrayView1<'_, D>>) -> D {
+        let weight = |i: usize| weights.map_or(1.0, |w| w[i]);
+        let mut order: Vec<usize> = (0..preds.dim()).collect();
+        order.sort_by(|a, b| preds[*a].partial_cmp(&preds[*b]).unwrap_or(std::cmp::Ordering::Equal));
+        let (mut area, mut negatives, mut positives) = (0.0, 0.0, 0.0);
+        let mut start = 0;
+        while start < order.len() {
+            let end = start + order[start..].iter().take_while(|i| preds[**i] == preds[order[start]]).count().max(1);
+            let (mut tied_negatives, mut tied_positives) = (0.0, 0.0);
+            for i in &order[start..end] {
+                if target[*i].to_d() > 0.0 {
+                    tied_positives += weight(*i);
+                } else {
+                    tied_negatives += weight(*i);
+                }
+            }
+            area += tied_positives * (negatives + tied_negatives / 2.0);
+            negatives += tied_negatives;
+            positives += tied_positives;
+            start = end;
+        }
+        area / (positives * negatives)
+    }
+
+    fn greater_is_better(&self) -> bool {
+        true
+    }
+}
+
+/// Fraction of predictions, rounded to the nearest integer, which are equal to target labels.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Accuracy;
+
+impl<F: Float> Metric<F> for Accuracy {
+    fn name(&self) -> String {
+        "accuracy".into()
+    }
+
+    fn evaluate(&self, preds: &ArrayView1<'_, F>, target: &ArrayView1<'_, F>, weights: Option<&ArrayView1<'_, D>>) -> D {
+        weighted_mean(preds, target, weights, |p, t| (p.round() == t) as u8 as D)
+    }
+
+    fn greater_is_better(&self) -> bool {
+        true
+    }
+}
+
+/// Normalized discounted cumulative gain of samples ranked by predictions, target values are relevance labels.
+///
+/// Gain of relevance `r` is `2^r - 1`, discount of position `i` (from `0`) is `log2(i + 2)`.
+/// Queries without relevant samples have NDCG `1`. Sample weights are ignored.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Ndcg {
+    /// Number of top ranked samples, all samples by default.
+    pub k: Option<usize>,
+}
+
+impl Ndcg {
+    pub fn new(k: Option<usize>) -> Self {
+        Ndcg { k }
+    }
+
+    /// NDCG of one query with predictions and relevance of samples.
+    fn query_ndcg(&self, mut samples: Vec<(D, D)>) -> D {
+        let k = self.k.unwrap_or(samples.len());
+        let dcg = |samples: &[(D, D)]| -> D {
+            samples.iter().take(k).enumerate()
+                .map(|(i, s)| (s.1.exp2() - 1.0) / ((i + 2) as D).log2())
+                .sum()
+        };
+        samples.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+        let actual = dcg(&samples);
+        samples.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        let ideal = dcg(&samples);
+        if ideal == 0.0 { 1.0 } else { actual / ideal }
+    }
+}
+
+impl<F: Float> Metric<F> for Ndcg {
+    fn name(&self) -> String {
+        match self.k {
+            Some(k) => format!("ndcg@{}", k),
+            None => "ndcg".into(),
+        }
+    }
+
+    /// NDCG of all samples as a single query.
+    fn evaluate(&self, preds: &ArrayView1<'_, F>, target: &ArrayView1<'_, F>, _weights: Option<&ArrayView1<'_, D>>) -> D {
+        self.query_ndcg(preds.iter().zip(target.iter()).map(|(p, t)| (p.to_d(), t.to_d())).collect())
+    }
+
+    fn greater_is_better(&self) -> bool {
+        true
+    }
+
+    /// Mean NDCG of queries.
+    fn evaluate_groups(&self, preds: &ArrayView1<'_, F>, target: &ArrayView1<'_, F>,
+                       _weights: Option<&ArrayView1<'_, D>>, groups: &[usize]) -> D {
+        let mut queries: Vec<Vec<(D, D)>> = vec![];
+        let mut query_index = std::collections::HashMap::new();
+        for ((p, t), group) in preds.iter().zip(target.iter()).zip(groups) {
+            let index = *query_index.entry(*group).or_insert_with(|| {
+                queries.push(vec![]);
+                queries.len() - 1
+            });
+            queries[index].push((p.to_d(), t.to_d()));
+        }
+        let n_queries = queries.len();
+        queries.into_iter().map(|samples| self.query_ndcg(samples)).sum::<D>() / n_queries as D
+    }
+}
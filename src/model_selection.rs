@@ -0,0 +1,238 @@
+//! Model selection: cross-validation splitters and scores.
+//!
+//! Splitters ([`KFold`], [`ShuffleKFold`], [`GroupKFold`], [`TimeSeriesSplit`]) produce train and
+//! validation sample indices of each fold, [`cross_val_score`] fits estimator on train samples
+//! and evaluates it on validation samples of each fold.
+//!
+//! # Example
+//! ```
+//! # use ndarray::{Array1, Array2};
+//! # use woods::estimator::ConstructibleWithCopyArg;
+//! # use woods::metrics::MeanSquaredError;
+//! # use woods::model_selection::{cross_val_score, ShuffleKFold};
+//! # use woods::tree::{DecisionTreeImpl, TreeParameters};
+//! # use woods::tree::rule::RandomSplitRule;
+//! # fn main() -> woods::error::Result<()> {
+//! let columns = Array2::from_shape_fn((3, 100), |(i, j)| ((i + 1) * j % 17) as f64);
+//! let target: Array1<f64> = columns.row(0).mapv(|v| 2.0 * v);
+//! let mut tree = DecisionTreeImpl::<RandomSplitRule>::new(TreeParameters::new(Some(4), None));
+//! let scores = cross_val_score(&mut tree, &columns.view(), &target.view(),
+//!                              &ShuffleKFold::new(5, Some(0)), None, &MeanSquaredError)?;
+//! assert_eq!(scores.dim(), 5);
+//! # Ok(())
+//! # }
+//! ```
+
+use ndarray::{ArrayView1, ArrayView2, Array1, Axis};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use crate::estimator::Estimator;
+use crate::metrics::Metric;
+use crate::utils::numerics::D;
+use crate::error::{Result, WoodsError};
+
+/// Train and validation sample indices of a fold.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fold {
+    pub train: Vec<usize>,
+    pub test: Vec<usize>,
+}
+
+/// Cross-validation splitter.
+pub trait Splitter {
+    /// Split `n_samples` samples into folds, `groups` (group of each sample) are required by [`GroupKFold`].
+    fn split(&self, n_samples: usize, groups: Option<&[usize]>) -> Result<Vec<Fold>>;
+}
+
+/// Check that `n_splits` folds can be made of `n_items` samples or groups.
+fn check_n_splits(n_splits: usize, n_items: usize, items: &str) -> Result<()> {
+    if n_splits < 2 {
+        return Err(WoodsError::InvalidParameter(format!("number of splits must be at least 2, got {}", n_splits)));
+    }
+    if n_splits > n_items {
+        return Err(WoodsError::InvalidParameter(
+            format!("number of splits {} is greater than the number of {} {}", n_splits, items, n_items)
+        ));
+    }
+    Ok(())
+}
+
+/// Split `order` of samples into `n_splits` consecutive folds, first `n_samples % n_splits` folds have one more sample.
+fn consecutive_folds(order: &[usize], n_splits: usize) -> Vec<Fold> {
+    let n_samples = order.len();
+    let mut start = 0;
+    (0..n_splits).map(|i| {
+        let size = n_samples / n_splits + (i < n_samples % n_splits) as usize;
+        let end = start + size;
+        let fold = Fold {
+            train: order[..start].iter().chain(&order[end..]).copied().collect(),
+            test: order[start..end].to_vec(),
+        };
+        start = end;
+        fold
+    }).collect()
+}
+
+/// K-fold cross-validation: each of consecutive `n_splits` folds is used once for validation,
+/// the rest of samples are used for training.
+#[derive(Debug, Clone, Copy)]
+pub struct KFold {
+    pub n_splits: usize,
+}
+
+impl KFold {
+    pub fn new(n_splits: usize) -> Self {
+        KFold { n_splits }
+    }
+}
+
+impl Splitter for KFold {
+    fn split(&self, n_samples: usize, _groups: Option<&[usize]>) -> Result<Vec<Fold>> {
+        check_n_splits(self.n_splits, n_samples, "samples")?;
+        let order: Vec<usize> = (0..n_samples).collect();
+        Ok(consecutive_folds(&order, self.n_splits))
+    }
+}
+
+/// K-fold cross-validation ([`KFold`]) of shuffled samples.
+#[derive(Debug, Clone, Copy)]
+pub struct ShuffleKFold {
+    pub n_splits: usize,
+    /// Seed of random shuffle, random by default.
+    pub seed: Option<u64>,
+}
+
+impl ShuffleKFold {
+    pub fn new(n_splits: usize, seed: Option<u64>) -> Self {
+        ShuffleKFold { n_splits, seed }
+    }
+}
+
+impl Splitter for ShuffleKFold {
+    fn split(&self, n_samples: usize, _groups: Option<&[usize]>) -> Result<Vec<Fold>> {
+        check_n_splits(self.n_splits, n_samples, "samples")?;
+        let mut rng = match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let mut order: Vec<usize> = (0..n_samples).collect();
+        order.shuffle(&mut rng);
+        let mut folds = consecutive_folds(&order, self.n_splits);
+        for fold in folds.iter_mut() {
+            fold.train.sort_unstable();
+            fold.test.sort_unstable();
+        }
+        Ok(folds)
+    }
+}
+
+/// K-fold cross-validation with non-overlapping groups: samples of a group are in the same fold.
+///
+/// Groups are assigned to folds from the largest one to the fold with the least number of samples,
+/// so folds have approximately the same size.
+#[derive(Debug, Clone, Copy)]
+pub struct GroupKFold {
+    pub n_splits: usize,
+}
+
+impl GroupKFold {
+    pub fn new(n_splits: usize) -> Self {
+        GroupKFold { n_splits }
+    }
+}
+
+impl Splitter for GroupKFold {
+    fn split(&self, n_samples: usize, groups: Option<&[usize]>) -> Result<Vec<Fold>> {
+        let groups = groups.ok_or_else(|| WoodsError::InvalidParameter("groups are required by `GroupKFold`".into()))?;
+        if groups.len() != n_samples {
+            return Err(WoodsError::ShapeMismatch { what: "groups", expected: n_samples, found: groups.len() });
+        }
+        let n_groups = groups.iter().max().map_or(0, |g| g + 1);
+        let mut sizes = vec![0usize; n_groups];
+        for group in groups {
+            sizes[*group] += 1;
+        }
+        let mut order: Vec<usize> = (0..n_groups).filter(|g| sizes[*g] > 0).collect();
+        check_n_splits(self.n_splits, order.len(), "groups")?;
+        order.sort_by_key(|g| std::cmp::Reverse(sizes[*g]));
+
+        let mut fold_sizes = vec![0usize; self.n_splits];
+        let mut group_folds = vec![0usize; n_groups];
+        for group in order {
+            let fold = (0..self.n_splits).min_by_key(|f| fold_sizes[*f]).unwrap();
+            fold_sizes[fold] += sizes[group];
+            group_folds[group] = fold;
+        }
+        Ok((0..self.n_splits).map(|fold| {
+            let (test, train) = (0..n_samples).partition(|i| group_folds[groups[*i]] == fold);
+            Fold { train, test }
+        }).collect())
+    }
+}
+
+/// Time series cross-validation: validation folds are consecutive blocks of the last samples,
+/// each fold is trained on all samples before its validation block.
+///
+/// Samples must be ordered by time, validation blocks have `n_samples / (n_splits + 1)` samples.
+#[derive(Debug, Clone, Copy)]
+pub struct TimeSeriesSplit {
+    pub n_splits: usize,
+}
+
+impl TimeSeriesSplit {
+    pub fn new(n_splits: usize) -> Self {
+        TimeSeriesSplit { n_splits }
+    }
+}
+
+impl Splitter for TimeSeriesSplit {
+    fn split(&self, n_samples: usize, _groups: Option<&[usize]>) -> Result<Vec<Fold>> {
+        check_n_splits(self.n_splits, n_samples.saturating_sub(1), "samples except the first one")?;
+        let test_size = n_samples / (self.n_splits + 1);
+        let first_test = n_samples - self.n_splits * test_size;
+        Ok((0..self.n_splits).map(|i| {
+            let start = first_test + i * test_size;
+            Fold {
+                train: (0..start).collect(),
+                test: (start..start + test_size).collect(),
+            }
+        }).collect())
+    }
+}
+
+/// Fit `est` on train samples of each fold of `splitter` and calculate `metric` of predictions
+/// on validation samples.
+///
+/// Returns `metric` value of each fold, if `groups` are set, validation samples are evaluated
+/// with their groups (see [`Metric::evaluate_groups`]).
+pub fn cross_val_score<Est, S, M>(
+        est: &mut Est,
+        columns: &ArrayView2<'_, Est::F>,
+        target: &ArrayView1<'_, Est::F>,
+        splitter: &S,
+        groups: Option<&[usize]>,
+        metric: &M
+    ) -> Result<Array1<D>>
+    where Est: Estimator,
+          S: Splitter + ?Sized,
+          M: Metric<Est::F> + ?Sized {
+    let n_samples = target.dim();
+    if columns.dim().1 != n_samples {
+        return Err(WoodsError::ShapeMismatch { what: "target samples", expected: columns.dim().1, found: n_samples });
+    }
+    splitter.split(n_samples, groups)?.iter().map(|fold| {
+        let train_columns = columns.select(Axis(1), &fold.train);
+        let train_target = target.select(Axis(0), &fold.train);
+        est.fit(&train_columns.view(), &train_target.view())?;
+        let preds = est.predict(&columns.select(Axis(1), &fold.test).view())?;
+        let test_target = target.select(Axis(0), &fold.test);
+        Ok(match groups {
+            Some(groups) => {
+                let test_groups: Vec<usize> = fold.test.iter().map(|i| groups[*i]).collect();
+                metric.evaluate_groups(&preds.view(), &test_target.view(), None, &test_groups)
+            },
+            None => metric.evaluate(&preds.view(), &test_target.view(), None),
+        })
+    }).collect()
+}
@@ -0,0 +1,1502 @@
+//! Python extension: scikit-learn compatible estimators, `Dataset` and inspection functions.
+//!
+//! The extension is built with `python` feature (see `setup.py`), Rust API of the crate doesn't depend on it.
+
+use crate::estimator::{Estimator, ConstructibleWithCopyArg, ConstructibleWithArg, FeatureImportance, ImportanceType, PredictContributions,
+                       SparseEstimator, WithInputFeatures, WithParameters};
+use crate::tree::rule::{RandomSplitRule, SplitRule};
+use crate::tree::{TreeParameters, DecisionTreeImpl};
+use crate::tree::dump::DEFAULT_DUMP_PRECISION;
+use crate::ensemble::boosting::{GradientBoostingParameters, GradientBoostingImpl, TreeGBM};
+use crate::ensemble::deep_boosting::{DeepBoostingParameters, DeepBoostingImpl};
+use crate::ensemble::AverageEnsemble;
+use crate::inspection::PartialDependenceRecursion;
+use crate::error::WoodsError;
+use crate::{error, estimator, inspection, metrics, utils};
+use crate::export::{onnx, xgboost, lightgbm, AsWeightedTrees, WeightedTrees};
+use crate::export::codegen::{self, AsTreeSum, Language};
+use crate::utils::numerics::{D as DType, Float, FLOAT_TYPES};
+use crate::utils::serialization::{load, save_with_metadata, to_bytes, from_bytes, header_from_bytes, Model, ModelHeader};
+use crate::utils::sparse::SparseColumns;
+
+use ndarray::{ArrayView1, ArrayView2, Array1, Array2, CowArray, Dimension, Ix1, Ix2};
+use numpy::{IntoPyArray, PyArray, PyArray2, PyArray1, TypeNum};
+use pyo3::prelude::{pymodule, pyfunction, pyproto, AsPyRef, Py, PyModule, PyResult, Python, pyclass, pymethods, PyObject, PyErr, PyAny,
+                    PyRef, PyCell};
+use pyo3::{exceptions, wrap_pyfunction, PyNativeType, PyObjectProtocol};
+use pyo3::types::{PyBytes, PyDict, PyTuple};
+use pyo3::type_object::{PyTypeObject, PyTypeInfo};
+use pyo3::ToPyObject;
+use std::collections::{BTreeMap, HashMap};
+use std::cmp::Ordering;
+
+
+/// Floating point type of Python models and data, chosen by `dtype` argument.
+trait PyFloat: Float + TypeNum {
+    /// Features and target of `dataset` if they have this type.
+    fn dataset_arrays(dataset: &Dataset) -> Option<&DatasetArrays<Self>>;
+}
+
+impl PyFloat for f32 {
+    fn dataset_arrays(dataset: &Dataset) -> Option<&DatasetArrays<Self>> {
+        match &dataset.arrays {
+            Typed::Float32(arrays) => Some(arrays),
+            Typed::Float64(_) => None,
+        }
+    }
+}
+
+impl PyFloat for f64 {
+    fn dataset_arrays(dataset: &Dataset) -> Option<&DatasetArrays<Self>> {
+        match &dataset.arrays {
+            Typed::Float32(_) => None,
+            Typed::Float64(arrays) => Some(arrays),
+        }
+    }
+}
+
+/// Value of `float32` or `float64` type: model or data of Python class with `dtype`.
+enum Typed<T32, T64> {
+    Float32(T32),
+    Float64(T64),
+}
+
+impl<T32, T64> Typed<T32, T64> {
+    /// Name of floating point type.
+    fn dtype(&self) -> &'static str {
+        match self {
+            Typed::Float32(_) => f32::NAME,
+            Typed::Float64(_) => f64::NAME,
+        }
+    }
+}
+
+/// Evaluate `$body` with `$value` bound to the contents of `Typed` value `$typed` of any floating point type.
+macro_rules! typed {
+    ($typed:expr, $value:ident => $body:expr) => {
+        match $typed {
+            Typed::Float32($value) => $body,
+            Typed::Float64($value) => $body,
+        }
+    };
+}
+
+/// Parse `dtype` (name, numpy type or `numpy.dtype`), `None` is `float64`.
+fn to_dtype(dtype: &PyAny) -> PyResult<&'static str> {
+    let numpy = dtype.py().import("numpy")?;
+    let name = numpy.call1("dtype", (dtype,)).and_then(|d| d.getattr("name")?.extract::<String>()).ok();
+    FLOAT_TYPES.iter().copied().find(|t| Some(*t) == name.as_deref()).ok_or_else(|| {
+        PyErr::new::<exceptions::ValueError, _>(
+            format!("Incorrect dtype: `{}`. Please, use one of: {:?}", dtype, FLOAT_TYPES)
+        )
+    })
+}
+
+/// Get `dtype` of saved model `header`, models without readable header are `float64`.
+fn saved_dtype(header: error::Result<ModelHeader>) -> String {
+    header.map_or_else(|_| f64::NAME.into(), |header| header.dtype().into())
+}
+
+/// Convert `x` (numpy array of any dtype and layout, pandas object, list, ...) to numpy array of type `F`
+/// and dimension `Dim`.
+/// 
+/// Arrays of type `F` are not copied unless their strides are negative or unaligned, which are not supported by `ndarray`.
+fn to_float_array<F: PyFloat, Dim: Dimension>(x: &PyAny) -> PyResult<&PyArray<F, Dim>> {
+    let numpy = x.py().import("numpy")?;
+    let mut arr = numpy.call1("asarray", (x, F::NAME))?;
+    let ndim: usize = arr.getattr("ndim")?.extract()?;
+    if Some(ndim) != Dim::NDIM {
+        return Err(PyErr::new::<exceptions::ValueError, _>(
+            format!("Expected {}D array, got {}D array", Dim::NDIM.unwrap_or(0), ndim)
+        ));
+    }
+    let strides: Vec<isize> = arr.getattr("strides")?.extract()?;
+    let item_size = std::mem::size_of::<F>() as isize;
+    if strides.iter().any(|s| *s < 0 || s % item_size != 0) || !arr.getattr("flags")?.getattr("aligned")?.is_true()? {
+        arr = numpy.call1("asfortranarray", (arr,))?;
+    }
+    arr.extract()
+}
+
+/// Feature-major view of Python input data.
+enum Columns<'py, F: PyFloat> {
+    /// Transposed numpy array and column names of pandas `DataFrame`.
+    Array(ArrayView2<'py, F>, Option<Vec<String>>),
+    /// `Dataset` of type `F`.
+    Dataset(PyRef<'py, Dataset>),
+    /// Features, target and feature names of `Dataset` of other type, converted to `F`.
+    Converted(Array2<F>, Option<Array1<F>>, Option<Vec<String>>),
+}
+
+impl<F: PyFloat> Columns<'_, F> {
+    fn view(&self) -> ArrayView2<'_, F> {
+        match self {
+            Columns::Array(columns, _) => columns.view(),
+            Columns::Dataset(dataset) => F::dataset_arrays(dataset).unwrap().columns.view(),
+            Columns::Converted(columns, _, _) => columns.view(),
+        }
+    }
+
+    /// Features in standard layout (contiguous features), fit is faster on it.
+    /// 
+    /// Only sample-major (C-ordered) arrays are copied.
+    fn standard_layout(&self) -> CowArray<'_, F, Ix2> {
+        match self {
+            Columns::Array(columns, _) => columns.as_standard_layout(),
+            _ => self.view().into(),
+        }
+    }
+
+    /// Feature names of `Dataset` or pandas `DataFrame` with string column names.
+    fn feature_names(&self) -> Option<Vec<String>> {
+        match self {
+            Columns::Array(_, names) => names.clone(),
+            Columns::Dataset(dataset) => dataset.feature_names.clone(),
+            Columns::Converted(_, _, names) => names.clone(),
+        }
+    }
+
+    /// Target `y` or target of `Dataset` if `y` is not set.
+    fn target<'a>(&'a self, y: Option<&'a PyAny>) -> PyResult<ArrayView1<'a, F>> {
+        let dataset_target = match self {
+            Columns::Array(..) => None,
+            Columns::Dataset(dataset) => F::dataset_arrays(dataset).unwrap().target.as_ref(),
+            Columns::Converted(_, target, _) => target.as_ref(),
+        };
+        let target = match (y, dataset_target) {
+            (Some(y), _) => to_float_array(y)?.as_array(),
+            (None, Some(target)) => target.view(),
+            _ => return Err(PyErr::new::<exceptions::ValueError, _>("Target `y` is required for data without target")),
+        };
+        let n_samples = self.view().dim().1;
+        if target.dim() != n_samples {
+            return Err(WoodsError::ShapeMismatch { what: "target samples", expected: n_samples, found: target.dim() }.into());
+        }
+        Ok(target)
+    }
+}
+
+/// Convert samples `x` with shape `(n_samples, n_features)` or `Dataset` to feature-major columns of type `F`.
+/// 
+/// Fortran-ordered arrays and `Dataset` features of type `F` are used without copying,
+/// other arrays, pandas `DataFrame` and sequences are converted to `F`.
+/// `scipy.sparse` matrices are converted to dense arrays, see [`to_sparse`] for estimators with sparse input.
+fn to_columns<F: PyFloat>(x: &PyAny) -> PyResult<Columns<'_, F>> {
+    let x = if is_sparse(x)? { x.call_method0("toarray")? } else { x };
+    if let Ok(dataset) = x.extract::<PyRef<'_, Dataset>>() {
+        if F::dataset_arrays(&dataset).is_some() {
+            return Ok(Columns::Dataset(dataset));
+        }
+        return Ok(typed!(&dataset.arrays, arrays => Columns::Converted(
+            arrays.columns.mapv(|v| F::from_d(v.to_d())),
+            arrays.target.as_ref().map(|target| target.mapv(|v| F::from_d(v.to_d()))),
+            dataset.feature_names.clone(),
+        )));
+    }
+    let names = if x.hasattr("columns")? {
+        x.getattr("columns")?.iter()?.map(|name| name?.extract::<String>()).collect::<PyResult<Vec<_>>>().ok()
+    } else {
+        None
+    };
+    Ok(Columns::Array(to_float_array::<F, Ix2>(x)?.as_array().reversed_axes(), names))
+}
+
+/// Check that `x` is `scipy.sparse` matrix, `scipy` is not required for other inputs.
+fn is_sparse(x: &PyAny) -> PyResult<bool> {
+    match x.py().import("scipy.sparse") {
+        Ok(sparse) => sparse.call1("issparse", (x,))?.is_true(),
+        Err(_) => Ok(false),
+    }
+}
+
+/// Convert `scipy.sparse` matrix `x` with shape `(n_samples, n_features)` to sparse columns of type `F`,
+/// other inputs give `None`.
+/// 
+/// The matrix is converted to CSC format with sorted indices, which is the layout of [`SparseColumns`].
+fn to_sparse<F: PyFloat>(x: &PyAny) -> PyResult<Option<SparseColumns<F>>> {
+    if !is_sparse(x)? {
+        return Ok(None);
+    }
+    let csc = x.call_method0("tocsc")?.call_method1("astype", (F::NAME,))?;
+    csc.call_method0("sum_duplicates")?;
+    let (n_samples, _): (usize, usize) = csc.getattr("shape")?.extract()?;
+    let indices = |name| -> PyResult<Vec<usize>> { csc.getattr(name)?.call_method0("tolist")?.extract() };
+    let values = to_float_array::<F, Ix1>(csc.getattr("data")?)?.as_array().to_vec();
+    Ok(Some(SparseColumns::from_csc(n_samples, indices("indptr")?, indices("indices")?, values)?))
+}
+
+/// Target `y` of sparse samples, it is required.
+fn sparse_target<F: PyFloat>(y: Option<&PyAny>) -> PyResult<&PyArray1<F>> {
+    let y = y.ok_or_else(|| PyErr::new::<exceptions::ValueError, _>("Target `y` is required for sparse input"))?;
+    to_float_array(y)
+}
+
+/// Fit `est` on samples `x` (array, pandas `DataFrame`, `Dataset` or `scipy.sparse` matrix) and set
+/// `feature_names` or names of `Dataset` or `DataFrame` columns.
+fn fit_sparse_estimator<E>(est: &mut E, x: &PyAny, y: Option<&PyAny>, feature_names: Option<Vec<String>>) -> PyResult<()>
+    where E: SparseEstimator + WithInputFeatures, E::F: PyFloat {
+    let names = match to_sparse::<E::F>(x)? {
+        Some(columns) => {
+            est.fit_sparse(&columns, &sparse_target(y)?.as_array())?;
+            None
+        },
+        None => {
+            let features = to_columns(x)?;
+            est.fit(&features.standard_layout().view(), &features.target(y)?)?;
+            features.feature_names()
+        },
+    };
+    if let Some(names) = feature_names.or(names) {
+        est.input_features_mut().set_names(names)?;
+    }
+    Ok(())
+}
+
+/// Predict with `est` on samples `x`, `scipy.sparse` matrices are not converted to dense.
+fn predict_sparse_estimator<E>(est: &E, x: &PyAny) -> PyResult<Array1<E::F>>
+    where E: SparseEstimator, E::F: PyFloat {
+    Ok(match to_sparse::<E::F>(x)? {
+        Some(columns) => est.predict_sparse(&columns)?,
+        None => est.predict(&to_columns(x)?.view())?,
+    })
+}
+
+/// Metric of `est` predictions on samples `x` (R^2 by default), see [`predict_sparse_estimator`] and [`evaluate_metric`].
+fn score_sparse_estimator<E>(est: &E, x: &PyAny, y: Option<&PyAny>, sample_weight: Option<&PyAny>,
+                             metric: Option<&str>) -> PyResult<DType>
+    where E: SparseEstimator, E::F: PyFloat {
+    match to_sparse::<E::F>(x)? {
+        Some(columns) => evaluate_metric(&est.predict_sparse(&columns)?.view(), &sparse_target(y)?.as_array(),
+                                         sample_weight, metric),
+        None => {
+            let features = to_columns(x)?;
+            evaluate_metric(&est.predict(&features.view())?.view(), &features.target(y)?, sample_weight, metric)
+        },
+    }
+}
+
+/// Parse optional importance type name, default is `gain`.
+fn to_importance_type(importance_type: Option<&str>) -> PyResult<ImportanceType> {
+    match importance_type {
+        None => Ok(ImportanceType::default()),
+        Some(name) => name.parse().map_err(|e: estimator::UnknownImportanceTypeError| {
+            PyErr::new::<exceptions::ValueError, _>(e.to_string())
+        }),
+    }
+}
+
+/// Collect optional user metadata of saved model.
+fn to_metadata(metadata: Option<HashMap<String, String>>) -> BTreeMap<String, String> {
+    metadata.map(|m| m.into_iter().collect()).unwrap_or_default()
+}
+
+/// Serialization format of pickled models.
+const PICKLE_FORMAT: &str = "bincode";
+
+/// Serialize `model` to pickle state.
+fn pickle_state<M: Model>(py: Python<'_>, model: &M) -> PyResult<PyObject> {
+    let bytes = to_bytes(model, Some(PICKLE_FORMAT))?;
+    Ok(PyBytes::new(py, &bytes).into())
+}
+
+/// Restore `model` from pickle `state`.
+fn set_pickle_state<M: Model>(model: &mut M, state: &PyBytes) -> PyResult<()> {
+    from_bytes(model, state.as_bytes(), Some(PICKLE_FORMAT))?;
+    Ok(())
+}
+
+/// Make pickle `__reduce__` result of estimator class `C`: `(C, (), state)`.
+fn reduce<C: PyTypeObject>(py: Python<'_>, state: PyObject) -> PyObject {
+    (py.get_type::<C>(), PyTuple::empty(py), state).to_object(py)
+}
+
+/// Export `model` to ONNX: save it to `filename` if set, otherwise return model bytes.
+fn export_onnx<M: AsWeightedTrees>(py: Python<'_>, model: &M, filename: Option<&str>) -> PyResult<PyObject> {
+    match filename {
+        Some(filename) => {
+            onnx::save_onnx(model, filename)?;
+            Ok(py.None())
+        },
+        None => Ok(PyBytes::new(py, &onnx::to_onnx(model)?).into()),
+    }
+}
+
+/// Export `model` to XGBoost JSON dump: save it to `filename` if set, otherwise return JSON string.
+fn export_xgboost<M: AsWeightedTrees>(py: Python<'_>, model: &M, filename: Option<&str>) -> PyResult<PyObject> {
+    let dump = xgboost::to_xgboost_json(model)?;
+    match filename {
+        Some(filename) => {
+            std::fs::write(filename, dump)?;
+            Ok(py.None())
+        },
+        None => Ok(dump.to_object(py)),
+    }
+}
+
+/// Generate `lang` source code of `model` (`rust` by default): save it to `filename` if set, otherwise return it.
+fn export_code<M: AsTreeSum>(py: Python<'_>, model: &M, lang: Option<&str>, filename: Option<&str>) -> PyResult<PyObject> {
+    let language: Language = lang.unwrap_or("rust").parse().map_err(|e: codegen::UnknownLanguageError| {
+        PyErr::new::<exceptions::ValueError, _>(e.to_string())
+    })?;
+    match filename {
+        Some(filename) => {
+            codegen::save_code(model, language, filename)?;
+            Ok(py.None())
+        },
+        None => Ok(codegen::to_code(model, language)?.to_object(py)),
+    }
+}
+
+/// Make Graphviz DOT representation of `tree`: save it to `filename` if set, otherwise return it.
+fn export_dot<S: SplitRule>(py: Python<'_>, tree: &DecisionTreeImpl<S>, filename: Option<&str>,
+                            precision: Option<usize>) -> PyResult<PyObject> {
+    let dot = tree.to_dot(precision.unwrap_or(DEFAULT_DUMP_PRECISION))?;
+    match filename {
+        Some(filename) => {
+            std::fs::write(filename, dot)?;
+            Ok(py.None())
+        },
+        None => Ok(dot.to_object(py)),
+    }
+}
+
+/// Python estimator with scikit-learn parameters (`get_params` and `set_params`).
+/// 
+/// scikit-learn `clone` requires `get_params` to return the same objects that were passed to the constructor,
+/// so parameter objects passed by user are returned while they are equal to the model parameters.
+trait EstimatorParams {
+    /// Parameter names in order of constructor arguments.
+    const PARAM_NAMES: &'static [&'static str];
+    /// Current parameter values.
+    fn param_values(&self, py: Python<'_>) -> Vec<PyObject>;
+    /// Set parameter `name` to `value`, `None` sets the default value.
+    fn set_param(&mut self, name: &str, value: &PyAny) -> PyResult<()>;
+    /// Parameter objects passed by user.
+    fn user_params(&self) -> &HashMap<String, PyObject>;
+    /// Mutable parameter objects passed by user.
+    fn user_params_mut(&mut self) -> &mut HashMap<String, PyObject>;
+}
+
+/// Set parameters of `est` from constructor arguments `values`, missing arguments keep default values.
+fn init_params<E: EstimatorParams>(est: &mut E, values: &[Option<&PyAny>]) -> PyResult<()> {
+    for (name, value) in E::PARAM_NAMES.iter().zip(values) {
+        if let Some(value) = value {
+            est.set_param(name, value)?;
+            est.user_params_mut().insert(name.to_string(), (*value).into());
+        }
+    }
+    Ok(())
+}
+
+/// Get parameters of `est` as a dictionary.
+fn get_params<E: EstimatorParams>(py: Python<'_>, est: &E) -> PyResult<PyObject> {
+    let params = PyDict::new(py);
+    for (name, value) in E::PARAM_NAMES.iter().zip(est.param_values(py)) {
+        let value = match est.user_params().get(*name) {
+            Some(user_value) if user_value.as_ref(py).compare(&value).ok() == Some(Ordering::Equal) => {
+                user_value.clone_ref(py)
+            },
+            _ => value,
+        };
+        params.set_item(name, value)?;
+    }
+    Ok(params.into())
+}
+
+/// Set parameters of `est` from dictionary `params`.
+fn set_params<E: EstimatorParams>(est: &mut E, params: Option<&PyDict>) -> PyResult<()> {
+    for (name, value) in params.into_iter().flatten() {
+        let name: String = name.extract()?;
+        if !E::PARAM_NAMES.contains(&name.as_str()) {
+            return Err(PyErr::new::<exceptions::ValueError, _>(
+                format!("Incorrect parameter: `{}`. Please, use one of: {:?}", name, E::PARAM_NAMES)
+            ));
+        }
+        est.set_param(&name, value)?;
+        est.user_params_mut().insert(name, value.into());
+    }
+    Ok(())
+}
+
+/// Make `repr` of `est` with its parameters, e.g. `DecisionTree(depth=3, min_samples_split=2)`.
+fn estimator_repr<E: EstimatorParams + PyTypeInfo>(est: &E) -> PyResult<String> {
+    let gil = Python::acquire_gil();
+    let py = gil.python();
+    let params = E::PARAM_NAMES.iter().zip(est.param_values(py)).map(|(name, value)| {
+        Ok(format!("{}={}", name, value.as_ref(py).repr()?))
+    }).collect::<PyResult<Vec<_>>>()?;
+    Ok(format!("{}({})", E::NAME, params.join(", ")))
+}
+
+/// Metric of `preds` and `target` by optional name (see [`metrics::METRICS`]), optionally weighted by `sample_weight`.
+/// 
+/// Default metric is coefficient of determination R^2, as in scikit-learn.
+fn evaluate_metric<F: Float>(preds: &ArrayView1<'_, F>, target: &ArrayView1<'_, F>,
+                             sample_weight: Option<&PyAny>, metric: Option<&str>) -> PyResult<DType> {
+    let metric = metrics::metric_by_name::<F>(metric.unwrap_or("r2"))?;
+    if target.dim() != preds.dim() {
+        return Err(WoodsError::ShapeMismatch { what: "target samples", expected: preds.dim(), found: target.dim() }.into());
+    }
+    let weights = match sample_weight {
+        Some(weights) => Some(to_float_array::<DType, Ix1>(weights)?.as_array().to_owned()),
+        None => None,
+    };
+    if let Some(weights) = &weights {
+        if weights.dim() != preds.dim() {
+            return Err(WoodsError::ShapeMismatch { what: "sample weights", expected: preds.dim(), found: weights.dim() }.into());
+        }
+    }
+    Ok(metric.evaluate(preds, target, weights.as_ref().map(|w| w.view()).as_ref()))
+}
+
+/// Get number of features, seen by `model` during `fit`.
+fn fitted_n_features<M: WithInputFeatures>(model: &M) -> PyResult<usize> {
+    match model.input_features().n_features {
+        0 => Err(WoodsError::NotFitted.into()),
+        n_features => Ok(n_features),
+    }
+}
+
+/// Features and target of `Dataset`.
+struct DatasetArrays<F> {
+    /// Features in standard layout with shape `(n_features, n_samples)`.
+    columns: Array2<F>,
+    target: Option<Array1<F>>,
+}
+
+impl<F: PyFloat> DatasetArrays<F> {
+    fn new(columns: &Columns<'_, F>, y: Option<&PyAny>) -> PyResult<Self> {
+        let target = match y {
+            Some(y) => Some(columns.target(Some(y))?.to_owned()),
+            None => None,
+        };
+        Ok(DatasetArrays {
+            columns: columns.standard_layout().into_owned(),
+            target,
+        })
+    }
+}
+
+/// Data with shape `(n_samples, n_features)`, converted to feature-major layout of `dtype` once
+/// for repeated fits and predictions without conversion.
+#[pyclass(module="woods")]
+pub struct Dataset {
+    arrays: Typed<DatasetArrays<f32>, DatasetArrays<f64>>,
+    feature_names: Option<Vec<String>>,
+}
+
+#[pymethods]
+impl Dataset {
+    /// Make dataset of samples `x` with optional target `y`, `feature_names` and `dtype` (`float64` by default).
+    /// 
+    /// Column names of pandas `DataFrame` are used as feature names by default.
+    #[new]
+    fn new(x: &PyAny, y: Option<&PyAny>, feature_names: Option<Vec<String>>, dtype: Option<&PyAny>) -> PyResult<Self> {
+        let (arrays, names) = if dtype.map_or(Ok(f64::NAME), to_dtype)? == f32::NAME {
+            let columns = to_columns::<f32>(x)?;
+            (Typed::Float32(DatasetArrays::new(&columns, y)?), columns.feature_names())
+        } else {
+            let columns = to_columns::<f64>(x)?;
+            (Typed::Float64(DatasetArrays::new(&columns, y)?), columns.feature_names())
+        };
+        let feature_names = feature_names.or(names);
+        let n_features = typed!(&arrays, arrays => arrays.columns.dim().0);
+        match &feature_names {
+            Some(names) if names.len() != n_features => {
+                Err(WoodsError::ShapeMismatch { what: "feature names", expected: n_features, found: names.len() }.into())
+            },
+            _ => Ok(Dataset { arrays, feature_names }),
+        }
+    }
+
+    #[getter]
+    fn n_samples(&self) -> usize {
+        typed!(&self.arrays, arrays => arrays.columns.dim().1)
+    }
+
+    #[getter]
+    fn n_features(&self) -> usize {
+        typed!(&self.arrays, arrays => arrays.columns.dim().0)
+    }
+
+    #[getter]
+    fn feature_names(&self) -> Option<Vec<String>> {
+        self.feature_names.clone()
+    }
+
+    #[getter]
+    fn dtype(&self) -> &'static str {
+        self.arrays.dtype()
+    }
+}
+
+#[pyclass(module="woods")]
+pub struct DecisionRule {
+    rule: RandomSplitRule
+}
+
+#[pymethods]
+impl DecisionRule {
+    #[new]
+    fn new() -> Self {
+        DecisionRule {
+            rule: RandomSplitRule::new()
+        }
+    }
+    
+    fn fit(&mut self, x: &PyAny, y: Option<&PyAny>) -> PyResult<()> {
+        let features = to_columns(x)?;
+        let target = features.target(y)?;
+        self.rule.fit(&features.standard_layout().view(), &target)?;
+        Ok(())
+    }
+
+    fn predict(&self, py: Python<'_>, x: &PyAny) -> PyResult<Py<PyArray1<DType>>> {
+        let features = to_columns(x)?;
+        Ok(self.rule.predict(&features.view())?.into_pyarray(py).to_owned())
+    }
+
+    fn __getstate__(&self, py: Python<'_>) -> PyResult<PyObject> {
+        pickle_state(py, &self.rule)
+    }
+
+    fn __setstate__(&mut self, state: &PyBytes) -> PyResult<()> {
+        set_pickle_state(&mut self.rule, state)
+    }
+
+    fn __reduce__(&self, py: Python<'_>) -> PyResult<PyObject> {
+        Ok(reduce::<DecisionRule>(py, self.__getstate__(py)?))
+    }
+}
+
+/// Decision tree of `float32` or `float64` type.
+type TypedTree = Typed<DecisionTreeImpl<RandomSplitRule<f32>>, DecisionTreeImpl<RandomSplitRule<f64>>>;
+
+#[pyclass(module="woods")]
+pub struct DecisionTree {
+    tree: TypedTree,
+    /// Parameter objects passed by user, see [`EstimatorParams`].
+    user_params: HashMap<String, PyObject>,
+}
+
+impl DecisionTree {
+    /// Replace the model by empty model of `dtype` with the same parameters if its type differs.
+    fn set_dtype(&mut self, dtype: &str) {
+        if self.tree.dtype() != dtype {
+            let params = *typed!(&self.tree, tree => tree.params());
+            self.tree = if dtype == f32::NAME {
+                Typed::Float32(DecisionTreeImpl::new(params))
+            } else {
+                Typed::Float64(DecisionTreeImpl::new(params))
+            };
+        }
+    }
+}
+
+#[pymethods]
+impl DecisionTree {
+    #[new]
+    fn new(depth: Option<&PyAny>, min_samples_split: Option<&PyAny>, dtype: Option<&PyAny>,
+           max_features: Option<&PyAny>) -> PyResult<Self> {
+        let mut est = DecisionTree {
+            tree: Typed::Float64(DecisionTreeImpl::new(TreeParameters::default())),
+            user_params: HashMap::new(),
+        };
+        init_params(&mut est, &[depth, min_samples_split, dtype, max_features])?;
+        Ok(est)
+    }
+    
+    /// Fit model on samples `x` (array, pandas `DataFrame`, `Dataset` or `scipy.sparse` matrix) and return it.
+    /// 
+    /// Target `y` may be omitted for `Dataset` with target. Optional `feature_names`
+    /// (names of `Dataset` or `DataFrame` columns by default) are stored in `feature_names_in_`.
+    /// Sparse matrices are not converted to dense, zero values are not stored.
+    fn fit(slf: &PyCell<Self>, x: &PyAny, y: Option<&PyAny>, feature_names: Option<Vec<String>>) -> PyResult<PyObject> {
+        let mut est = slf.try_borrow_mut()?;
+        typed!(&mut est.tree, tree => fit_sparse_estimator(tree, x, y, feature_names))?;
+        Ok(slf.to_object(slf.py()))
+    }
+
+    /// Metric of predictions on `x`, optionally weighted by `sample_weight`, default is coefficient of determination R^2.
+    /// 
+    /// `metric` is one of `mse`, `rmse`, `mae`, `r2`, `mape`, `quantile[@alpha]`, `logloss`, `auc`, `accuracy`, `ndcg[@k]`.
+    fn score(&self, x: &PyAny, y: Option<&PyAny>, sample_weight: Option<&PyAny>, metric: Option<&str>) -> PyResult<DType> {
+        typed!(&self.tree, tree => score_sparse_estimator(tree, x, y, sample_weight, metric))
+    }
+
+    /// Get model parameters, `deep` is accepted for compatibility with scikit-learn.
+    fn get_params(&self, py: Python<'_>, deep: Option<bool>) -> PyResult<PyObject> {
+        let _ = deep;
+        get_params(py, self)
+    }
+
+    /// Set model parameters and return the model, parameters are used by the next `fit`.
+    #[args(params = "**")]
+    fn set_params(slf: &PyCell<Self>, params: Option<&PyDict>) -> PyResult<PyObject> {
+        set_params(&mut *slf.try_borrow_mut()?, params)?;
+        Ok(slf.to_object(slf.py()))
+    }
+
+    fn __sklearn_is_fitted__(&self) -> bool {
+        self.n_features_in_().is_ok()
+    }
+
+    #[classattr]
+    fn _estimator_type() -> &'static str {
+        "regressor"
+    }
+
+    /// Predict target values of samples `x` (dense or `scipy.sparse`), predictions have the model `dtype`.
+    fn predict(&self, py: Python<'_>, x: &PyAny) -> PyResult<PyObject> {
+        typed!(&self.tree, tree => Ok(predict_sparse_estimator(tree, x)?.into_pyarray(py).to_object(py)))
+    }
+
+    /// Predict feature contributions (SHAP values) with shape `(n_samples, n_features + 1)`.
+    /// 
+    /// The last column contains the bias term, rows sum up to predictions.
+    fn predict_contributions(&self, py: Python<'_>, x: &PyAny) -> PyResult<Py<PyArray2<DType>>> {
+        typed!(&self.tree, tree => {
+            let features = to_columns(x)?;
+            Ok(tree.predict_contributions(&features.view())?.into_pyarray(py).to_owned())
+        })
+    }
+
+    /// Save model to file with optional user `metadata`, stored in the file header.
+    /// 
+    /// Default `format` is inferred from the file extension, e.g. `.bin.zst` for `bincode+zstd`, or `json`.
+    fn save(&self, filename: &str, format: Option<&str>, metadata: Option<HashMap<String, String>>) -> PyResult<()> {
+        Ok(typed!(&self.tree, tree => save_with_metadata(tree, filename, format, to_metadata(metadata)))?)
+    }
+
+    /// Load model from file, `format` is detected by file content by default.
+    /// 
+    /// The model `dtype` is changed to the type of the saved model.
+    fn load(&mut self, filename: &str, format: Option<&str>) -> PyResult<()> {
+        self.set_dtype(&saved_dtype(utils::serialization::read_header(filename, format)));
+        typed!(&mut self.tree, tree => load(tree, filename, format))?;
+        Ok(())
+    }
+
+    /// Export model to ONNX `TreeEnsembleRegressor` with float input `X` of shape `(N, n_features)`.
+    /// 
+    /// Model is saved to `filename` if it is set, otherwise ONNX model bytes are returned.
+    fn to_onnx(&self, py: Python<'_>, filename: Option<&str>) -> PyResult<PyObject> {
+        typed!(&self.tree, tree => export_onnx(py, tree, filename))
+    }
+
+    /// Export model to XGBoost JSON dump (`Booster.dump_model(..., dump_format="json")` format).
+    /// 
+    /// Model is saved to `filename` if it is set, otherwise JSON string is returned.
+    fn to_xgboost_json(&self, py: Python<'_>, filename: Option<&str>) -> PyResult<PyObject> {
+        typed!(&self.tree, tree => export_xgboost(py, tree, filename))
+    }
+
+    /// Compile model to standalone `lang` (`rust` or `c`) source code with `predict(x)` function of a single sample.
+    /// 
+    /// Generated code reproduces predictions of `float64` models exactly, it is saved to `filename` if it is set,
+    /// otherwise it is returned.
+    fn export_code(&self, py: Python<'_>, lang: Option<&str>, filename: Option<&str>) -> PyResult<PyObject> {
+        typed!(&self.tree, tree => export_code(py, tree, lang, filename))
+    }
+
+    /// Human-readable text dump of the tree with `precision` decimal digits (4 by default).
+    fn dump_text(&self, precision: Option<usize>) -> PyResult<String> {
+        Ok(typed!(&self.tree, tree => tree.dump_text(precision.unwrap_or(DEFAULT_DUMP_PRECISION)))?)
+    }
+
+    /// Graphviz DOT representation of the tree with `precision` decimal digits (4 by default).
+    /// 
+    /// It is saved to `filename` if it is set, otherwise it is returned.
+    fn to_dot(&self, py: Python<'_>, filename: Option<&str>, precision: Option<usize>) -> PyResult<PyObject> {
+        typed!(&self.tree, tree => export_dot(py, tree, filename, precision))
+    }
+
+    fn __getstate__(&self, py: Python<'_>) -> PyResult<PyObject> {
+        typed!(&self.tree, tree => pickle_state(py, tree))
+    }
+
+    fn __setstate__(&mut self, state: &PyBytes) -> PyResult<()> {
+        self.set_dtype(&saved_dtype(header_from_bytes(state.as_bytes(), Some(PICKLE_FORMAT))));
+        typed!(&mut self.tree, tree => set_pickle_state(tree, state))
+    }
+
+    fn __reduce__(&self, py: Python<'_>) -> PyResult<PyObject> {
+        Ok(reduce::<DecisionTree>(py, self.__getstate__(py)?))
+    }
+
+    /// Calculate feature importances of `importance_type` (`split` or `gain`).
+    fn feature_importances(&self, py: Python<'_>, importance_type: Option<&str>) -> PyResult<Py<PyArray1<DType>>> {
+        let importance_type = to_importance_type(importance_type)?;
+        let n_features = self.n_features_in_()?;
+        let importances = typed!(&self.tree, tree => tree.feature_importances(n_features, importance_type))?;
+        Ok(importances.into_pyarray(py).to_owned())
+    }
+
+    #[getter]
+    fn feature_importances_(&self, py: Python<'_>) -> PyResult<Py<PyArray1<DType>>> {
+        self.feature_importances(py, None)
+    }
+
+    #[getter]
+    fn n_features_in_(&self) -> PyResult<usize> {
+        typed!(&self.tree, tree => fitted_n_features(tree))
+    }
+
+    #[getter]
+    fn feature_names_in_(&self) -> Option<Vec<String>> {
+        typed!(&self.tree, tree => tree.input_features().names.clone())
+    }
+
+    #[getter]
+    fn dtype(&self) -> &'static str {
+        self.tree.dtype()
+    }
+}
+
+/// Gradient boosting of `float32` or `float64` trees.
+type TypedGBM = Typed<TreeGBM<f32>, TreeGBM<f64>>;
+
+#[pyclass(module="woods")]
+pub struct GradientBoosting {
+    gbm: TypedGBM,
+    /// Parameter objects passed by user, see [`EstimatorParams`].
+    user_params: HashMap<String, PyObject>,
+}
+
+impl GradientBoosting {
+    /// Replace the model by empty model of `dtype` with the same parameters if its type differs.
+    fn set_dtype(&mut self, dtype: &str) {
+        if self.gbm.dtype() != dtype {
+            let params = *typed!(&self.gbm, gbm => gbm.params());
+            self.gbm = if dtype == f32::NAME {
+                Typed::Float32(GradientBoostingImpl::new(params))
+            } else {
+                Typed::Float64(GradientBoostingImpl::new(params))
+            };
+        }
+    }
+}
+
+/// Get fitted tree `i` of gradient boosting `gbm`.
+fn boosted_tree<F: Float>(gbm: &TreeGBM<F>, i: usize) -> PyResult<&DecisionTreeImpl<RandomSplitRule<F>>> {
+    fitted_n_features(gbm)?;
+    gbm.estimator(i).ok_or_else(|| PyErr::new::<exceptions::IndexError, _>(format!("Tree index {} is out of range", i)))
+}
+
+#[pymethods]
+impl GradientBoosting {
+    #[new]
+    fn new(depth: Option<&PyAny>, min_samples_split: Option<&PyAny>, n_estimators: Option<&PyAny>,
+           learning_rate: Option<&PyAny>, dtype: Option<&PyAny>, max_features: Option<&PyAny>) -> PyResult<Self> {
+        let mut est = GradientBoosting {
+            gbm: Typed::Float64(GradientBoostingImpl::new(GradientBoostingParameters::default())),
+            user_params: HashMap::new(),
+        };
+        init_params(&mut est, &[depth, min_samples_split, n_estimators, learning_rate, dtype, max_features])?;
+        Ok(est)
+    }
+
+    /// Fit model on samples `x` (array, pandas `DataFrame`, `Dataset` or `scipy.sparse` matrix) and return it.
+    /// 
+    /// Target `y` may be omitted for `Dataset` with target. Optional `feature_names`
+    /// (names of `Dataset` or `DataFrame` columns by default) are stored in `feature_names_in_`.
+    /// Sparse matrices are not converted to dense, zero values are not stored.
+    fn fit(slf: &PyCell<Self>, x: &PyAny, y: Option<&PyAny>, feature_names: Option<Vec<String>>) -> PyResult<PyObject> {
+        let mut est = slf.try_borrow_mut()?;
+        typed!(&mut est.gbm, gbm => fit_sparse_estimator(gbm, x, y, feature_names))?;
+        Ok(slf.to_object(slf.py()))
+    }
+
+    /// Metric of predictions on `x`, optionally weighted by `sample_weight`, default is coefficient of determination R^2.
+    /// 
+    /// `metric` is one of `mse`, `rmse`, `mae`, `r2`, `mape`, `quantile[@alpha]`, `logloss`, `auc`, `accuracy`, `ndcg[@k]`.
+    fn score(&self, x: &PyAny, y: Option<&PyAny>, sample_weight: Option<&PyAny>, metric: Option<&str>) -> PyResult<DType> {
+        typed!(&self.gbm, gbm => score_sparse_estimator(gbm, x, y, sample_weight, metric))
+    }
+
+    /// Get model parameters, `deep` is accepted for compatibility with scikit-learn.
+    fn get_params(&self, py: Python<'_>, deep: Option<bool>) -> PyResult<PyObject> {
+        let _ = deep;
+        get_params(py, self)
+    }
+
+    /// Set model parameters and return the model, parameters are used by the next `fit`.
+    #[args(params = "**")]
+    fn set_params(slf: &PyCell<Self>, params: Option<&PyDict>) -> PyResult<PyObject> {
+        set_params(&mut *slf.try_borrow_mut()?, params)?;
+        Ok(slf.to_object(slf.py()))
+    }
+
+    fn __sklearn_is_fitted__(&self) -> bool {
+        self.n_features_in_().is_ok()
+    }
+
+    #[classattr]
+    fn _estimator_type() -> &'static str {
+        "regressor"
+    }
+
+    /// Predict target values of samples `x` (dense or `scipy.sparse`), predictions have the model `dtype`.
+    fn predict(&self, py: Python<'_>, x: &PyAny) -> PyResult<PyObject> {
+        typed!(&self.gbm, gbm => Ok(predict_sparse_estimator(gbm, x)?.into_pyarray(py).to_object(py)))
+    }
+
+    /// Predict feature contributions (SHAP values) with shape `(n_samples, n_features + 1)`.
+    /// 
+    /// The last column contains the bias term, rows sum up to predictions.
+    fn predict_contributions(&self, py: Python<'_>, x: &PyAny) -> PyResult<Py<PyArray2<DType>>> {
+        typed!(&self.gbm, gbm => {
+            let features = to_columns(x)?;
+            Ok(gbm.predict_contributions(&features.view())?.into_pyarray(py).to_owned())
+        })
+    }
+
+    /// Save model to file with optional user `metadata`, stored in the file header.
+    /// 
+    /// Default `format` is inferred from the file extension, e.g. `.bin.zst` for `bincode+zstd`, or `json`.
+    fn save(&self, filename: &str, format: Option<&str>, metadata: Option<HashMap<String, String>>) -> PyResult<()> {
+        Ok(typed!(&self.gbm, gbm => save_with_metadata(gbm, filename, format, to_metadata(metadata)))?)
+    }
+
+    /// Load model from file, `format` is detected by file content by default.
+    /// 
+    /// The model `dtype` is changed to the type of the saved model.
+    fn load(&mut self, filename: &str, format: Option<&str>) -> PyResult<()> {
+        self.set_dtype(&saved_dtype(utils::serialization::read_header(filename, format)));
+        typed!(&mut self.gbm, gbm => load(gbm, filename, format))?;
+        Ok(())
+    }
+
+    /// Export model to ONNX `TreeEnsembleRegressor` with float input `X` of shape `(N, n_features)`.
+    /// 
+    /// Model is saved to `filename` if it is set, otherwise ONNX model bytes are returned.
+    fn to_onnx(&self, py: Python<'_>, filename: Option<&str>) -> PyResult<PyObject> {
+        typed!(&self.gbm, gbm => export_onnx(py, gbm, filename))
+    }
+
+    /// Export model to XGBoost JSON dump (`Booster.dump_model(..., dump_format="json")` format).
+    /// 
+    /// Model is saved to `filename` if it is set, otherwise JSON string is returned.
+    fn to_xgboost_json(&self, py: Python<'_>, filename: Option<&str>) -> PyResult<PyObject> {
+        typed!(&self.gbm, gbm => export_xgboost(py, gbm, filename))
+    }
+
+    /// Compile model to standalone `lang` (`rust` or `c`) source code with `predict(x)` function of a single sample.
+    /// 
+    /// Generated code reproduces predictions of `float64` models exactly, it is saved to `filename` if it is set,
+    /// otherwise it is returned.
+    fn export_code(&self, py: Python<'_>, lang: Option<&str>, filename: Option<&str>) -> PyResult<PyObject> {
+        typed!(&self.gbm, gbm => export_code(py, gbm, lang, filename))
+    }
+
+    /// Human-readable text dump of tree `i` with `precision` decimal digits (4 by default).
+    /// 
+    /// Tree outputs are multiplied by learning rate and added to mean target value to make predictions.
+    fn dump_text(&self, i: usize, precision: Option<usize>) -> PyResult<String> {
+        Ok(typed!(&self.gbm, gbm => boosted_tree(gbm, i)?.dump_text(precision.unwrap_or(DEFAULT_DUMP_PRECISION)))?)
+    }
+
+    /// Graphviz DOT representation of tree `i` with `precision` decimal digits (4 by default).
+    /// 
+    /// It is saved to `filename` if it is set, otherwise it is returned.
+    fn to_dot(&self, py: Python<'_>, i: usize, filename: Option<&str>, precision: Option<usize>) -> PyResult<PyObject> {
+        typed!(&self.gbm, gbm => export_dot(py, boosted_tree(gbm, i)?, filename, precision))
+    }
+
+    fn __getstate__(&self, py: Python<'_>) -> PyResult<PyObject> {
+        typed!(&self.gbm, gbm => pickle_state(py, gbm))
+    }
+
+    fn __setstate__(&mut self, state: &PyBytes) -> PyResult<()> {
+        self.set_dtype(&saved_dtype(header_from_bytes(state.as_bytes(), Some(PICKLE_FORMAT))));
+        typed!(&mut self.gbm, gbm => set_pickle_state(gbm, state))
+    }
+
+    fn __reduce__(&self, py: Python<'_>) -> PyResult<PyObject> {
+        Ok(reduce::<GradientBoosting>(py, self.__getstate__(py)?))
+    }
+
+    /// Calculate feature importances of `importance_type` (`split` or `gain`).
+    fn feature_importances(&self, py: Python<'_>, importance_type: Option<&str>) -> PyResult<Py<PyArray1<DType>>> {
+        let importance_type = to_importance_type(importance_type)?;
+        let n_features = self.n_features_in_()?;
+        let importances = typed!(&self.gbm, gbm => gbm.feature_importances(n_features, importance_type))?;
+        Ok(importances.into_pyarray(py).to_owned())
+    }
+
+    #[getter]
+    fn feature_importances_(&self, py: Python<'_>) -> PyResult<Py<PyArray1<DType>>> {
+        self.feature_importances(py, None)
+    }
+
+    #[getter]
+    fn n_features_in_(&self) -> PyResult<usize> {
+        typed!(&self.gbm, gbm => fitted_n_features(gbm))
+    }
+
+    #[getter]
+    fn feature_names_in_(&self) -> Option<Vec<String>> {
+        typed!(&self.gbm, gbm => gbm.input_features().names.clone())
+    }
+
+    #[getter]
+    fn dtype(&self) -> &'static str {
+        self.gbm.dtype()
+    }
+}
+
+/// Deep gradient boosting of `float32` or `float64` trees.
+type TypedDeepGBM = Typed<DeepBoostingImpl<AverageEnsemble<TreeGBM<f32>>>, DeepBoostingImpl<AverageEnsemble<TreeGBM<f64>>>>;
+
+#[pyclass(module="woods")]
+pub struct DeepGradientBoosting {
+    dgbm: TypedDeepGBM,
+    /// Parameter objects passed by user, see [`EstimatorParams`].
+    user_params: HashMap<String, PyObject>,
+}
+
+impl DeepGradientBoosting {
+    /// Replace the model by empty model of `dtype` with the same parameters if its type differs.
+    fn set_dtype(&mut self, dtype: &str) {
+        if self.dgbm.dtype() != dtype {
+            let params = typed!(&self.dgbm, dgbm => dgbm.params()).clone();
+            self.dgbm = if dtype == f32::NAME {
+                Typed::Float32(DeepBoostingImpl::new(params))
+            } else {
+                Typed::Float64(DeepBoostingImpl::new(params))
+            };
+        }
+    }
+}
+
+#[pymethods]
+impl DeepGradientBoosting {
+    /// Gradient boosting parameters of each layer are searched by `cv`-fold cross-validation over all combinations
+    /// of `search_depth`, `search_max_features`, `search_n_estimators` and `search_learning_rate`, the best
+    /// combination has the best mean `search_metric` (`mse` by default). Search is skipped if `layer_params`
+    /// dictionary (`depth`, `min_samples_split`, `max_features`, `n_estimators`, `learning_rate`) is set.
+    #[new]
+    #[allow(clippy::too_many_arguments)] // scikit-learn parameters are constructor arguments
+    fn new(n_estimators: Option<&PyAny>, layer_width: Option<&PyAny>, learning_rate: Option<&PyAny>,
+           dtype: Option<&PyAny>, search_depth: Option<&PyAny>, search_n_estimators: Option<&PyAny>,
+           search_learning_rate: Option<&PyAny>, cv: Option<&PyAny>, search_metric: Option<&PyAny>,
+           layer_params: Option<&PyAny>, search_max_features: Option<&PyAny>) -> PyResult<Self> {
+        let mut est = DeepGradientBoosting {
+            dgbm: Typed::Float64(DeepBoostingImpl::new(DeepBoostingParameters::default())),
+            user_params: HashMap::new(),
+        };
+        init_params(&mut est, &[n_estimators, layer_width, learning_rate, dtype, search_depth, search_n_estimators,
+                                search_learning_rate, cv, search_metric, layer_params, search_max_features])?;
+        Ok(est)
+    }
+    
+    /// Fit model on samples `x` (array, pandas `DataFrame` or `Dataset`) and return it.
+    /// 
+    /// Target `y` may be omitted for `Dataset` with target. Optional `feature_names`
+    /// (names of `Dataset` or `DataFrame` columns by default) are stored in `feature_names_in_`.
+    fn fit(slf: &PyCell<Self>, x: &PyAny, y: Option<&PyAny>, feature_names: Option<Vec<String>>) -> PyResult<PyObject> {
+        let mut est = slf.try_borrow_mut()?;
+        typed!(&mut est.dgbm, dgbm => {
+            let features = to_columns(x)?;
+            dgbm.fit(&features.standard_layout().view(), &features.target(y)?)?;
+            if let Some(names) = feature_names.or_else(|| features.feature_names()) {
+                dgbm.input_features_mut().set_names(names)?;
+            }
+        });
+        Ok(slf.to_object(slf.py()))
+    }
+
+    /// Metric of predictions on `x`, optionally weighted by `sample_weight`, default is coefficient of determination R^2.
+    /// 
+    /// `metric` is one of `mse`, `rmse`, `mae`, `r2`, `mape`, `quantile[@alpha]`, `logloss`, `auc`, `accuracy`, `ndcg[@k]`.
+    fn score(&self, x: &PyAny, y: Option<&PyAny>, sample_weight: Option<&PyAny>, metric: Option<&str>) -> PyResult<DType> {
+        typed!(&self.dgbm, dgbm => {
+            let features = to_columns(x)?;
+            evaluate_metric(&dgbm.predict(&features.view())?.view(), &features.target(y)?, sample_weight, metric)
+        })
+    }
+
+    /// Get model parameters, `deep` is accepted for compatibility with scikit-learn.
+    fn get_params(&self, py: Python<'_>, deep: Option<bool>) -> PyResult<PyObject> {
+        let _ = deep;
+        get_params(py, self)
+    }
+
+    /// Set model parameters and return the model, parameters are used by the next `fit`.
+    #[args(params = "**")]
+    fn set_params(slf: &PyCell<Self>, params: Option<&PyDict>) -> PyResult<PyObject> {
+        set_params(&mut *slf.try_borrow_mut()?, params)?;
+        Ok(slf.to_object(slf.py()))
+    }
+
+    fn __sklearn_is_fitted__(&self) -> bool {
+        self.n_features_in_().is_ok()
+    }
+
+    #[classattr]
+    fn _estimator_type() -> &'static str {
+        "regressor"
+    }
+
+    /// Predict target values of samples `x`, predictions have the model `dtype`.
+    fn predict(&self, py: Python<'_>, x: &PyAny) -> PyResult<PyObject> {
+        typed!(&self.dgbm, dgbm => {
+            let features = to_columns(x)?;
+            Ok(dgbm.predict(&features.view())?.into_pyarray(py).to_object(py))
+        })
+    }
+
+    /// Predict feature contributions with shape `(n_samples, n_features + 1)`.
+    /// 
+    /// Contributions of generated layer features are attributed back to the input features,
+    /// the last column contains the bias term, rows sum up to predictions.
+    fn predict_contributions(&self, py: Python<'_>, x: &PyAny) -> PyResult<Py<PyArray2<DType>>> {
+        typed!(&self.dgbm, dgbm => {
+            let features = to_columns(x)?;
+            Ok(dgbm.predict_contributions(&features.view())?.into_pyarray(py).to_owned())
+        })
+    }
+
+    /// Save model to file with optional user `metadata`, stored in the file header.
+    /// 
+    /// Default `format` is inferred from the file extension, e.g. `.bin.zst` for `bincode+zstd`, or `json`.
+    fn save(&self, filename: &str, format: Option<&str>, metadata: Option<HashMap<String, String>>) -> PyResult<()> {
+        Ok(typed!(&self.dgbm, dgbm => save_with_metadata(dgbm, filename, format, to_metadata(metadata)))?)
+    }
+
+    /// Load model from file, `format` is detected by file content by default.
+    /// 
+    /// The model `dtype` is changed to the type of the saved model.
+    fn load(&mut self, filename: &str, format: Option<&str>) -> PyResult<()> {
+        self.set_dtype(&saved_dtype(utils::serialization::read_header(filename, format)));
+        typed!(&mut self.dgbm, dgbm => load(dgbm, filename, format))?;
+        Ok(())
+    }
+
+    fn __getstate__(&self, py: Python<'_>) -> PyResult<PyObject> {
+        typed!(&self.dgbm, dgbm => pickle_state(py, dgbm))
+    }
+
+    fn __setstate__(&mut self, state: &PyBytes) -> PyResult<()> {
+        self.set_dtype(&saved_dtype(header_from_bytes(state.as_bytes(), Some(PICKLE_FORMAT))));
+        typed!(&mut self.dgbm, dgbm => set_pickle_state(dgbm, state))
+    }
+
+    fn __reduce__(&self, py: Python<'_>) -> PyResult<PyObject> {
+        Ok(reduce::<DeepGradientBoosting>(py, self.__getstate__(py)?))
+    }
+
+    /// Calculate feature importances of `importance_type` (`split` or `gain`).
+    fn feature_importances(&self, py: Python<'_>, importance_type: Option<&str>) -> PyResult<Py<PyArray1<DType>>> {
+        let importance_type = to_importance_type(importance_type)?;
+        let n_features = self.n_features_in_()?;
+        let importances = typed!(&self.dgbm, dgbm => dgbm.feature_importances(n_features, importance_type))?;
+        Ok(importances.into_pyarray(py).to_owned())
+    }
+
+    #[getter]
+    fn feature_importances_(&self, py: Python<'_>) -> PyResult<Py<PyArray1<DType>>> {
+        self.feature_importances(py, None)
+    }
+
+    #[getter]
+    fn n_features_in_(&self) -> PyResult<usize> {
+        typed!(&self.dgbm, dgbm => fitted_n_features(dgbm))
+    }
+
+    #[getter]
+    fn feature_names_in_(&self) -> Option<Vec<String>> {
+        typed!(&self.dgbm, dgbm => dgbm.input_features().names.clone())
+    }
+
+    #[getter]
+    fn dtype(&self) -> &'static str {
+        self.dgbm.dtype()
+    }
+}
+
+/// Tree ensemble, imported from other libraries.
+#[pyclass(module="woods")]
+pub struct TreeEnsemble {
+    trees: WeightedTrees
+}
+
+#[pymethods]
+impl TreeEnsemble {
+    #[new]
+    fn new() -> Self {
+        TreeEnsemble {
+            trees: WeightedTrees::default()
+        }
+    }
+
+    /// Import XGBoost JSON model or JSON dump, predictions are raw margins.
+    /// 
+    /// Optional `base_score` overrides the base margin of the model (JSON dumps have no base margin).
+    #[staticmethod]
+    fn from_xgboost(filename: &str, base_score: Option<DType>) -> PyResult<Self> {
+        let text = std::fs::read_to_string(filename)?;
+        Ok(TreeEnsemble {
+            trees: xgboost::from_xgboost_json(&text, base_score)?
+        })
+    }
+
+    /// Import LightGBM text model, predictions are raw scores.
+    #[staticmethod]
+    fn from_lightgbm(filename: &str) -> PyResult<Self> {
+        let text = std::fs::read_to_string(filename)?;
+        Ok(TreeEnsemble {
+            trees: lightgbm::from_lightgbm_text(&text)?
+        })
+    }
+
+    fn predict(&self, py: Python<'_>, x: &PyAny) -> PyResult<Py<PyArray1<DType>>> {
+        let features = to_columns(x)?;
+        Ok(self.trees.predict(&features.view())?.into_pyarray(py).to_owned())
+    }
+
+    /// Save model to file with optional user `metadata`, stored in the file header.
+    /// 
+    /// Default `format` is inferred from the file extension, e.g. `.bin.zst` for `bincode+zstd`, or `json`.
+    fn save(&self, filename: &str, format: Option<&str>, metadata: Option<HashMap<String, String>>) -> PyResult<()> {
+        Ok(save_with_metadata(&self.trees, filename, format, to_metadata(metadata))?)
+    }
+
+    /// Load model from file, `format` is detected by file content by default.
+    fn load(&mut self, filename: &str, format: Option<&str>) -> PyResult<()> {
+        load(&mut self.trees, filename, format)?;
+        Ok(())
+    }
+
+    /// Export model to ONNX `TreeEnsembleRegressor` with float input `X` of shape `(N, n_features)`.
+    /// 
+    /// Model is saved to `filename` if it is set, otherwise ONNX model bytes are returned.
+    fn to_onnx(&self, py: Python<'_>, filename: Option<&str>) -> PyResult<PyObject> {
+        export_onnx(py, &self.trees, filename)
+    }
+
+    /// Export model to XGBoost JSON dump (`Booster.dump_model(..., dump_format="json")` format).
+    /// 
+    /// Model is saved to `filename` if it is set, otherwise JSON string is returned.
+    fn to_xgboost_json(&self, py: Python<'_>, filename: Option<&str>) -> PyResult<PyObject> {
+        export_xgboost(py, &self.trees, filename)
+    }
+
+    fn __getstate__(&self, py: Python<'_>) -> PyResult<PyObject> {
+        pickle_state(py, &self.trees)
+    }
+
+    fn __setstate__(&mut self, state: &PyBytes) -> PyResult<()> {
+        set_pickle_state(&mut self.trees, state)
+    }
+
+    fn __reduce__(&self, py: Python<'_>) -> PyResult<PyObject> {
+        Ok(reduce::<TreeEnsemble>(py, self.__getstate__(py)?))
+    }
+
+    #[getter]
+    fn n_features_in_(&self) -> PyResult<usize> {
+        fitted_n_features(&self.trees)
+    }
+
+    #[getter]
+    fn feature_names_in_(&self) -> Option<Vec<String>> {
+        self.trees.input_features().names.clone()
+    }
+}
+
+impl EstimatorParams for DecisionTree {
+    const PARAM_NAMES: &'static [&'static str] = &["depth", "min_samples_split", "dtype", "max_features"];
+
+    fn param_values(&self, py: Python<'_>) -> Vec<PyObject> {
+        let params = typed!(&self.tree, tree => tree.params());
+        vec![params.depth.to_object(py), params.min_samples_split.to_object(py), self.tree.dtype().to_object(py),
+             params.max_features.to_object(py)]
+    }
+
+    fn set_param(&mut self, name: &str, value: &PyAny) -> PyResult<()> {
+        let (params, default) = (typed!(&mut self.tree, tree => tree.params_mut()), TreeParameters::default());
+        match name {
+            "depth" => params.depth = value.extract::<Option<u8>>()?.unwrap_or(default.depth),
+            "min_samples_split" => params.min_samples_split =
+                value.extract::<Option<usize>>()?.unwrap_or(default.min_samples_split),
+            "max_features" => params.max_features = value.extract::<Option<usize>>()?.unwrap_or(default.max_features),
+            _ => self.set_dtype(to_dtype(value)?),
+        }
+        Ok(())
+    }
+
+    fn user_params(&self) -> &HashMap<String, PyObject> {
+        &self.user_params
+    }
+
+    fn user_params_mut(&mut self) -> &mut HashMap<String, PyObject> {
+        &mut self.user_params
+    }
+}
+
+impl EstimatorParams for GradientBoosting {
+    const PARAM_NAMES: &'static [&'static str] = &["depth", "min_samples_split", "n_estimators", "learning_rate", "dtype",
+                                                   "max_features"];
+
+    fn param_values(&self, py: Python<'_>) -> Vec<PyObject> {
+        let params = typed!(&self.gbm, gbm => gbm.params());
+        vec![params.est_params.depth.to_object(py), params.est_params.min_samples_split.to_object(py),
+             params.n_estimators.to_object(py), params.learning_rate.to_object(py), self.gbm.dtype().to_object(py),
+             params.est_params.max_features.to_object(py)]
+    }
+
+    fn set_param(&mut self, name: &str, value: &PyAny) -> PyResult<()> {
+        let (params, default) = (typed!(&mut self.gbm, gbm => gbm.params_mut()),
+                                 GradientBoostingParameters::<TreeParameters>::default());
+        match name {
+            "depth" => params.est_params.depth = value.extract::<Option<u8>>()?.unwrap_or(default.est_params.depth),
+            "min_samples_split" => params.est_params.min_samples_split =
+                value.extract::<Option<usize>>()?.unwrap_or(default.est_params.min_samples_split),
+            "n_estimators" => params.n_estimators = value.extract::<Option<u32>>()?.unwrap_or(default.n_estimators),
+            "learning_rate" => params.learning_rate = value.extract::<Option<DType>>()?.unwrap_or(default.learning_rate),
+            "max_features" => params.est_params.max_features =
+                value.extract::<Option<usize>>()?.unwrap_or(default.est_params.max_features),
+            _ => self.set_dtype(to_dtype(value)?),
+        }
+        Ok(())
+    }
+
+    fn user_params(&self) -> &HashMap<String, PyObject> {
+        &self.user_params
+    }
+
+    fn user_params_mut(&mut self) -> &mut HashMap<String, PyObject> {
+        &mut self.user_params
+    }
+}
+
+/// Convert dictionary of fixed deep boosting layer parameters, missing parameters have default values.
+fn to_layer_params(dict: &PyDict) -> PyResult<GradientBoostingParameters<TreeParameters>> {
+    let mut params = GradientBoostingParameters::<TreeParameters>::default();
+    for (name, value) in dict {
+        match name.extract::<&str>()? {
+            "depth" => params.est_params.depth = value.extract()?,
+            "min_samples_split" => params.est_params.min_samples_split = value.extract()?,
+            "max_features" => params.est_params.max_features = value.extract()?,
+            "n_estimators" => params.n_estimators = value.extract()?,
+            "learning_rate" => params.learning_rate = value.extract()?,
+            name => return Err(PyErr::new::<exceptions::ValueError, _>(format!(
+                "Incorrect layer parameter: `{}`. Please, use one of: {:?}",
+                name, ["depth", "min_samples_split", "max_features", "n_estimators", "learning_rate"]
+            ))),
+        }
+    }
+    Ok(params)
+}
+
+impl EstimatorParams for DeepGradientBoosting {
+    const PARAM_NAMES: &'static [&'static str] = &["n_estimators", "layer_width", "learning_rate", "dtype",
+                                                   "search_depth", "search_n_estimators", "search_learning_rate",
+                                                   "cv", "search_metric", "layer_params", "search_max_features"];
+
+    fn param_values(&self, py: Python<'_>) -> Vec<PyObject> {
+        let params = typed!(&self.dgbm, dgbm => dgbm.params());
+        let search = &params.layer_search;
+        let layer_params = params.layer_params.map(|layer_params| {
+            let dict = PyDict::new(py);
+            dict.set_item("depth", layer_params.est_params.depth).unwrap();
+            dict.set_item("min_samples_split", layer_params.est_params.min_samples_split).unwrap();
+            dict.set_item("max_features", layer_params.est_params.max_features).unwrap();
+            dict.set_item("n_estimators", layer_params.n_estimators).unwrap();
+            dict.set_item("learning_rate", layer_params.learning_rate).unwrap();
+            dict.to_object(py)
+        });
+        vec![params.n_estimators.to_object(py), params.layer_width.to_object(py), params.learning_rate.to_object(py),
+             self.dgbm.dtype().to_object(py), search.depth.to_object(py), search.n_estimators.to_object(py),
+             search.learning_rate.to_object(py), search.n_folds.to_object(py), search.metric.to_object(py),
+             layer_params.to_object(py), search.max_features.to_object(py)]
+    }
+
+    fn set_param(&mut self, name: &str, value: &PyAny) -> PyResult<()> {
+        let (params, default) = (typed!(&mut self.dgbm, dgbm => dgbm.params_mut()), DeepBoostingParameters::default());
+        let search = &mut params.layer_search;
+        match name {
+            "n_estimators" => params.n_estimators = value.extract::<Option<u32>>()?.unwrap_or(default.n_estimators),
+            "layer_width" => params.layer_width = value.extract::<Option<u32>>()?.unwrap_or(default.layer_width),
+            "learning_rate" => params.learning_rate = value.extract::<Option<DType>>()?.unwrap_or(default.learning_rate),
+            "search_depth" => search.depth = value.extract::<Option<Vec<u8>>>()?.unwrap_or(default.layer_search.depth),
+            "search_n_estimators" => search.n_estimators =
+                value.extract::<Option<Vec<u32>>>()?.unwrap_or(default.layer_search.n_estimators),
+            "search_learning_rate" => search.learning_rate =
+                value.extract::<Option<Vec<DType>>>()?.unwrap_or(default.layer_search.learning_rate),
+            "search_max_features" => search.max_features =
+                value.extract::<Option<Vec<usize>>>()?.unwrap_or(default.layer_search.max_features),
+            "cv" => search.n_folds = value.extract::<Option<usize>>()?.unwrap_or(default.layer_search.n_folds),
+            "search_metric" => {
+                let metric = value.extract::<Option<String>>()?.unwrap_or(default.layer_search.metric);
+                metrics::metric_by_name::<DType>(&metric)?;
+                search.metric = metric;
+            },
+            "layer_params" => params.layer_params = match value.extract::<Option<&PyDict>>()? {
+                Some(dict) => Some(to_layer_params(dict)?),
+                None => None,
+            },
+            _ => self.set_dtype(to_dtype(value)?),
+        }
+        Ok(())
+    }
+
+    fn user_params(&self) -> &HashMap<String, PyObject> {
+        &self.user_params
+    }
+
+    fn user_params_mut(&mut self) -> &mut HashMap<String, PyObject> {
+        &mut self.user_params
+    }
+}
+
+#[pyproto]
+impl PyObjectProtocol for DecisionTree {
+    fn __repr__(&self) -> PyResult<String> {
+        estimator_repr(self)
+    }
+}
+
+#[pyproto]
+impl PyObjectProtocol for GradientBoosting {
+    fn __repr__(&self) -> PyResult<String> {
+        estimator_repr(self)
+    }
+}
+
+#[pyproto]
+impl PyObjectProtocol for DeepGradientBoosting {
+    fn __repr__(&self) -> PyResult<String> {
+        estimator_repr(self)
+    }
+}
+
+/// Evaluate `$body` with reference to the Rust model (`$model`) of Python estimator `$est` of any `dtype`.
+/// 
+/// Returns `TypeError` from the enclosing function if `$est` is not a Woods estimator.
+macro_rules! with_estimator {
+    ($est:expr, $model:ident => $body:expr) => {
+        if let Ok(cls) = $est.extract::<PyRef<'_, DecisionRule>>() {
+            let $model = &cls.rule;
+            $body
+        } else if let Ok(cls) = $est.extract::<PyRef<'_, DecisionTree>>() {
+            typed!(&cls.tree, $model => $body)
+        } else if let Ok(cls) = $est.extract::<PyRef<'_, GradientBoosting>>() {
+            typed!(&cls.gbm, $model => $body)
+        } else if let Ok(cls) = $est.extract::<PyRef<'_, DeepGradientBoosting>>() {
+            typed!(&cls.dgbm, $model => $body)
+        } else {
+            return Err(PyErr::new::<exceptions::TypeError, _>("Unsupported estimator type"));
+        }
+    };
+}
+
+/// Default number of permutations of each feature.
+const DEFAULT_PERMUTATION_N_REPEATS: usize = 5usize;
+/// Pair of mean and standard deviation arrays.
+type MeanStdArrays = (Py<PyArray1<DType>>, Py<PyArray1<DType>>);
+
+/// Calculate permutation feature importances of trained `est`.
+/// 
+/// Returns mean and standard deviation of metric loss increase over `n_repeats` shuffles of each feature,
+/// `metric` is a name of [`metrics::METRICS`], default is `mse`.
+#[pyfunction]
+fn permutation_importance(py: Python<'_>, est: &PyAny, x: &PyAny, y: Option<&PyAny>,
+                          n_repeats: Option<usize>, metric: Option<&str>)
+        -> PyResult<MeanStdArrays> {
+    let n_repeats = n_repeats.unwrap_or(DEFAULT_PERMUTATION_N_REPEATS);
+    let importances = with_estimator!(est, model => {
+        let features = to_columns(x)?;
+        let target = features.target(y)?;
+        let metric = metrics::metric_by_name(metric.unwrap_or("mse"))?;
+        estimator::permutation_importance(model, &features.view(), &target, n_repeats, metric.as_ref())?
+    });
+    Ok((importances.mean.into_pyarray(py).to_owned(), importances.std.into_pyarray(py).to_owned()))
+}
+
+/// Pair of average and optional individual conditional expectation arrays, they have the model `dtype`.
+type DependenceArrays = (PyObject, Option<PyObject>);
+
+/// Convert samples `x` and `grid` of partial dependence on `features` to type `F` and check their shapes.
+fn dependence_input<'py, F: PyFloat>(x: &'py PyAny, grid: &'py PyAny, features: &[usize])
+        -> PyResult<(Columns<'py, F>, ArrayView2<'py, F>)> {
+    let columns = to_columns(x)?;
+    let grid = to_float_array::<F, Ix2>(grid)?.as_array();
+    inspection::check_grid(columns.view().dim().0, features, &grid)?;
+    Ok((columns, grid))
+}
+
+/// Calculate partial dependence of `est` predictions on `features` at `grid` points.
+/// 
+/// Each `grid` row contains values of `features`. Method `brute` (default) returns average
+/// and individual conditional expectation curves; method `recursion` (`DecisionTree` and `GradientBoosting` only)
+/// returns average curve calculated by tree traversal and `None` instead of individual curves.
+#[pyfunction]
+fn partial_dependence(py: Python<'_>, est: &PyAny, x: &PyAny, features: Vec<usize>,
+                      grid: &PyAny, method: Option<&str>)
+        -> PyResult<DependenceArrays> {
+    match method.unwrap_or("brute") {
+        "brute" => Ok(with_estimator!(est, model => {
+            let (columns, grid) = dependence_input(x, grid, &features)?;
+            let dependence = inspection::partial_dependence(model, &columns.view(), &features, &grid)?;
+            (dependence.average.into_pyarray(py).to_object(py),
+             Some(dependence.individual.into_pyarray(py).to_object(py)))
+        })),
+        "recursion" => {
+            let average = if let Ok(cls) = est.extract::<PyRef<'_, DecisionTree>>() {
+                typed!(&cls.tree, tree => {
+                    let (_, grid) = dependence_input(x, grid, &features)?;
+                    tree.partial_dependence_recursion(&features, &grid).into_pyarray(py).to_object(py)
+                })
+            } else if let Ok(cls) = est.extract::<PyRef<'_, GradientBoosting>>() {
+                typed!(&cls.gbm, gbm => {
+                    let (_, grid) = dependence_input(x, grid, &features)?;
+                    gbm.partial_dependence_recursion(&features, &grid).into_pyarray(py).to_object(py)
+                })
+            } else {
+                return Err(PyErr::new::<exceptions::TypeError, _>(
+                    "Method `recursion` is supported only by `DecisionTree` and `GradientBoosting`"
+                ));
+            };
+            Ok((average, None))
+        },
+        name => Err(PyErr::new::<exceptions::ValueError, _>(
+            format!("Incorrect method: `{}`. Please, use one of: {:?}", name, ["brute", "recursion"])
+        )),
+    }
+}
+
+/// Read header of the model file: format and crate versions, model kind, `dtype`, timestamp,
+/// feature names and user metadata.
+#[pyfunction]
+fn read_header(py: Python<'_>, filename: &str, format: Option<&str>) -> PyResult<PyObject> {
+    let model_header = utils::serialization::read_header(filename, format)?;
+    let dtype = model_header.dtype().to_string();
+    let ModelHeader { format_version, crate_version, model_kind, timestamp, feature_names, metadata } = model_header;
+    let header = PyDict::new(py);
+    header.set_item("format_version", format_version)?;
+    header.set_item("crate_version", crate_version)?;
+    header.set_item("model_kind", model_kind)?;
+    header.set_item("dtype", dtype)?;
+    header.set_item("timestamp", timestamp)?;
+    header.set_item("feature_names", feature_names)?;
+    header.set_item("metadata", metadata.into_iter().collect::<HashMap<_, _>>())?;
+    Ok(header.into())
+}
+
+#[pymodule]
+fn woods(py: Python<'_>, m: &PyModule) -> PyResult<()> {
+    m.add_class::<Dataset>()?;
+    m.add_class::<DecisionRule>()?;
+    m.add_class::<DecisionTree>()?;
+    m.add_class::<GradientBoosting>()?;
+    m.add_class::<DeepGradientBoosting>()?;
+    m.add_class::<TreeEnsemble>()?;
+    m.add_wrapped(wrap_pyfunction!(permutation_importance))?;
+    m.add_wrapped(wrap_pyfunction!(partial_dependence))?;
+    m.add_wrapped(wrap_pyfunction!(read_header))?;
+    m.add("NotFittedError", py.get_type::<error::NotFittedError>())?;
+
+    Ok(())
+}
\ No newline at end of file
@@ -0,0 +1,160 @@
+//! Human-readable text dump and Graphviz DOT export of decision trees.
+//!
+//! Nodes show split feature and threshold, node value (mean target value),
+//! number of training samples and impurity (mean squared error) of internal nodes.
+//! Features are named by input feature names if they are set, otherwise as `x[i]`.
+
+use crate::utils::numerics::{D, Float};
+use crate::error::{Result, WoodsError};
+use super::DecisionTreeImpl;
+use super::rule::SplitRule;
+
+/// Default number of decimal digits of numbers in dumps.
+pub const DEFAULT_DUMP_PRECISION: usize = 4;
+
+/// Split of internal node.
+struct DumpSplit {
+    feature: usize,
+    threshold: D,
+    /// Mean squared error of node samples, `None` for models without split gains or sample counts.
+    impurity: Option<D>,
+    /// Indices of left and right children in dump nodes.
+    children: [usize; 2],
+}
+
+/// Node of tree dump, nodes are stored in depth-first order.
+struct DumpNode {
+    value: D,
+    /// Number of training samples, `None` for models trained without recording sample counts.
+    samples: Option<usize>,
+    split: Option<DumpSplit>,
+}
+
+impl<S: SplitRule> DecisionTreeImpl<S> {
+    /// Name of `feature` in dumps.
+    fn feature_label(&self, feature: usize) -> String {
+        match &self.features.names {
+            Some(names) if feature < names.len() => names[feature].clone(),
+            _ => format!("x[{}]", feature),
+        }
+    }
+
+    /// Nodes of fitted tree with explicit leaves in depth-first order.
+    fn dump_nodes(&self) -> Result<Vec<DumpNode>> {
+        let root_value = self.root_value.ok_or(WoodsError::NotFitted);
+        if self.splitters.is_empty() {
+            return Ok(vec![DumpNode { value: root_value?.to_d(), samples: None, split: None }]);
+        }
+        let root_value = root_value.unwrap_or_else(|_| {
+            let values = self.splitters[0].get_split().unwrap().values;
+            let samples = self.children_samples(0);
+            (values[0] * samples[0] + values[1] * samples[1]) / (samples[0] + samples[1])
+        }).to_d();
+        let samples = self.node_samples.first().map(|s| s[0] + s[1]);
+        let mut nodes = Vec::with_capacity(2 * self.splitters.len() + 1);
+        self.push_dump_node(0, root_value, samples, &mut nodes);
+        Ok(nodes)
+    }
+
+    /// Append dump nodes of internal node `id` subtree to `nodes` and return index of the node.
+    fn push_dump_node(&self, id: usize, value: D, samples: Option<usize>, nodes: &mut Vec<DumpNode>) -> usize {
+        let split_info = self.splitters[id].get_split().unwrap();
+        let children_samples = self.node_samples.get(id).copied();
+        let index = nodes.len();
+        nodes.push(DumpNode { value, samples, split: None });
+        let mut push_child = |side: usize| {
+            let value = split_info.values[side].to_d();
+            let samples = children_samples.map(|s| s[side]);
+            let route = self.routes[id][side];
+            if route >= 0 {
+                self.push_dump_node(route as usize, value, samples, nodes)
+            } else {
+                nodes.push(DumpNode { value, samples, split: None });
+                nodes.len() - 1
+            }
+        };
+        let children = [push_child(0), push_child(1)];
+        let impurity = match (split_info.gain, children_samples) {
+            (Some(gain), Some(s)) => Some((split_info.impurity.to_d() + gain.to_d()) / (s[0] + s[1]).max(1) as D),
+            _ => None,
+        };
+        nodes[index].split = Some(DumpSplit {
+            feature: split_info.feature,
+            threshold: split_info.threshold.to_d(),
+            impurity,
+            children,
+        });
+        index
+    }
+
+    /// Text dump of fitted tree with `precision` decimal digits, e.g.:
+    /// ```text
+    /// if x[3] > 0.5100:  # value = 22.5328, samples = 379, squared_error = 84.4198
+    ///     value = 29.8412  # samples = 155
+    /// else:
+    ///     value = 17.4863  # samples = 224
+    /// ```
+    /// Samples with `NaN` feature values go to the `else` branch.
+    pub fn dump_text(&self, precision: usize) -> Result<String> {
+        let nodes = self.dump_nodes()?;
+        let mut text = String::new();
+        self.dump_text_node(&nodes, 0, 0, precision, &mut text);
+        Ok(text)
+    }
+
+    fn dump_text_node(&self, nodes: &[DumpNode], index: usize, depth: usize, precision: usize, text: &mut String) {
+        let node = &nodes[index];
+        let indent = "    ".repeat(depth);
+        let samples = node.samples.map(|s| format!("samples = {}", s));
+        match &node.split {
+            Some(split) => {
+                let mut info = vec![format!("value = {:.*}", precision, node.value)];
+                info.extend(samples);
+                info.extend(split.impurity.map(|impurity| format!("squared_error = {:.*}", precision, impurity)));
+                text.push_str(&format!("{}if {} > {:.*}:  # {}\n", indent, self.feature_label(split.feature),
+                                       precision, split.threshold, info.join(", ")));
+                self.dump_text_node(nodes, split.children[1], depth + 1, precision, text);
+                text.push_str(&format!("{}else:\n", indent));
+                self.dump_text_node(nodes, split.children[0], depth + 1, precision, text);
+            },
+            None => {
+                text.push_str(&format!("{}value = {:.*}", indent, precision, node.value));
+                if let Some(samples) = samples {
+                    text.push_str(&format!("  # {}", samples));
+                }
+                text.push('\n');
+            },
+        }
+    }
+
+    /// Graphviz DOT representation of fitted tree with `precision` decimal digits.
+    ///
+    /// Left edges (`True`) correspond to samples with feature values not greater than threshold or `NaN`.
+    pub fn to_dot(&self, precision: usize) -> Result<String> {
+        let nodes = self.dump_nodes()?;
+        let mut dot = String::from("digraph Tree {\n");
+        dot.push_str("node [shape=box, style=\"rounded\", fontname=\"helvetica\"] ;\n");
+        dot.push_str("edge [fontname=\"helvetica\"] ;\n");
+        for (index, node) in nodes.iter().enumerate() {
+            let mut label = vec![];
+            if let Some(split) = &node.split {
+                label.push(format!("{} <= {:.*}", self.feature_label(split.feature), precision, split.threshold));
+                label.extend(split.impurity.map(|impurity| format!("squared_error = {:.*}", precision, impurity)));
+            }
+            if let Some(samples) = node.samples {
+                label.push(format!("samples = {}", samples));
+            }
+            label.push(format!("value = {:.*}", precision, node.value));
+            let label = label.join("\n").replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+            dot.push_str(&format!("{} [label=\"{}\"] ;\n", index, label));
+            if let Some(split) = &node.split {
+                dot.push_str(&format!("{} -> {} [labeldistance=2.5, labelangle=45, headlabel=\"True\"] ;\n",
+                                      index, split.children[0]));
+                dot.push_str(&format!("{} -> {} [labeldistance=2.5, labelangle=-45, headlabel=\"False\"] ;\n",
+                                      index, split.children[1]));
+            }
+        }
+        dot.push_str("}\n");
+        Ok(dot)
+    }
+}
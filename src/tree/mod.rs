@@ -3,14 +3,27 @@
 //! Each decision tree is based on [`SplitRule`].
 //! 
 //! Currently, only [`rule::RandomSplitRule`] is implemented.
+//! 
+//! Trees can be trained on dense or sparse ([`SparseColumns`]) features.
 
 use ndarray::{ArrayView2, ArrayView1, Array1, Axis};
 // use crate::rule::{SplitRule};
-use crate::estimator::{Estimator, ConstructibleWithCopyArg};
-use crate::utils::numerics::D;
+use crate::estimator::{Estimator, ConstructibleWithCopyArg, FeatureImportance, ImportanceType,
+                       InputFeatures, SparseEstimator, WithInputFeatures, WithParameters};
+use crate::inspection::PartialDependenceRecursion;
+use crate::export::{AsWeightedTrees, WeightedTrees};
+use crate::export::codegen::{AsTreeSum, TreeSum};
+use crate::utils::numerics::{D, Float};
+use crate::error::{Result, WoodsError};
+use crate::utils::serialization::Model;
+use crate::utils::sparse::SparseColumns;
 use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
+use num_traits::FromPrimitive;
 
 pub mod rule;
+pub mod shap;
+pub mod dump;
 
 use rule::SplitRule;
 
@@ -18,6 +31,15 @@ use rule::SplitRule;
 const DEFAULT_TREE_DEPTH: u8 = 3u8;
 /// Default tree min samples split parameter value
 const DEFAULT_TREE_MIN_SAMPLES_SPLIT: usize = 2usize;
+/// Default number of candidate features of each split
+const DEFAULT_TREE_MAX_FEATURES: usize = 1usize;
+
+fn default_max_features() -> usize {
+    DEFAULT_TREE_MAX_FEATURES
+}
+
+/// Min number of samples in node to build its subtrees in parallel
+const PARALLEL_BUILD_MIN_SAMPLES: usize = 1024usize;
 
 /// Decision Tree Parameters.
 #[derive(Serialize, Deserialize, Clone, Copy)]
@@ -26,6 +48,10 @@ pub struct TreeParameters {
     pub depth: u8,
     /// Min number of samples to split node
     pub min_samples_split: usize,
+    /// Number of randomly selected features (at least one, at most all features),
+    /// the best of their random splits is used at each node
+    #[serde(default = "default_max_features")]
+    pub max_features: usize,
 }
 
 impl TreeParameters {
@@ -33,20 +59,99 @@ impl TreeParameters {
     pub fn new(depth: Option<u8>, min_samples_split: Option<usize>) -> Self {
         TreeParameters {
             depth: depth.unwrap_or(DEFAULT_TREE_DEPTH),
-            min_samples_split: min_samples_split.unwrap_or(DEFAULT_TREE_MIN_SAMPLES_SPLIT)
+            min_samples_split: min_samples_split.unwrap_or(DEFAULT_TREE_MIN_SAMPLES_SPLIT),
+            max_features: DEFAULT_TREE_MAX_FEATURES,
         }
     }
 }
 
+impl Default for TreeParameters {
+    fn default() -> Self {
+        TreeParameters::new(None, None)
+    }
+}
+
 /// Decision Tree Implementation.
+/// 
+/// Floating point type of data and predictions is the type of split rule ([`SplitRule::F`]).
 #[derive(Serialize, Deserialize)]
-pub struct DecisionTreeImpl<Splitter> {
+#[serde(bound(serialize = "Splitter: SplitRule + Serialize", deserialize = "Splitter: SplitRule + Deserialize<'de>"))]
+pub struct DecisionTreeImpl<Splitter: SplitRule> {
     /// Configuration
     params: TreeParameters,
     /// Split rules
     splitters: Vec<Splitter>,
     /// Routes from each node to left and right children
     routes: Vec<[i64; 2]>,
+    /// Number of training samples in left and right children of each node
+    #[serde(default)]
+    node_samples: Vec<[usize; 2]>,
+    /// Mean target value of root node, it is used as prediction if tree has no splits
+    /// (`None` if tree is not fitted)
+    #[serde(default)]
+    root_value: Option<Splitter::F>,
+    /// Input features
+    #[serde(default)]
+    features: InputFeatures,
+}
+
+/// Node of fitted tree with explicit leaves, see [`DecisionTreeImpl::explicit_nodes`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ExplicitNode {
+    /// Internal node: samples with `feature` value not greater than `threshold` go to `children[0]`,
+    /// other samples go to `children[1]`.
+    Split {
+        feature: usize,
+        threshold: D,
+        /// Indices of left and right children.
+        children: [usize; 2],
+        /// Whether samples with `NaN` feature value go to the left child.
+        default_left: bool,
+        /// Impurity decrease of the split, `0` if unknown.
+        gain: D,
+        /// Number of training samples in node, `0` if unknown.
+        samples: usize,
+    },
+    /// Leaf with prediction `value`.
+    Leaf {
+        value: D,
+        /// Number of training samples in leaf, `0` if unknown.
+        samples: usize,
+    },
+}
+
+/// Training features of tree: dense or sparse.
+#[derive(Clone, Copy)]
+enum TreeInput<'a, 'b, F> {
+    Dense(&'a ArrayView2<'b, F>),
+    Sparse(&'a SparseColumns<F>),
+}
+
+/// Nodes of subtree, root node is the first one.
+struct Subtree<Splitter> {
+    splitters: Vec<Splitter>,
+    routes: Vec<[i64; 2]>,
+    node_samples: Vec<[usize; 2]>,
+}
+
+impl<S> Subtree<S> {
+    /// Append `child` subtree nodes and route root `side` to it.
+    fn attach(&mut self, side: usize, child: Subtree<S>) {
+        if child.splitters.is_empty() {
+            return;
+        }
+        let offset = self.splitters.len() as i64;
+        self.routes[0][side] = offset;
+        self.routes.extend(child.routes.iter().map(|route| {
+            let mut route = *route;
+            for child_id in route.iter_mut().filter(|id| **id >= 0) {
+                *child_id += offset;
+            }
+            route
+        }));
+        self.splitters.extend(child.splitters);
+        self.node_samples.extend(child.node_samples);
+    }
 }
 
 impl<S: SplitRule> DecisionTreeImpl<S> {
@@ -55,63 +160,284 @@ impl<S: SplitRule> DecisionTreeImpl<S> {
             params,
             splitters: vec![],
             routes: vec![],
+            node_samples: vec![],
+            root_value: None,
+            features: InputFeatures::default(),
         }
     }
 
-    fn build_tree(&mut self, columns: &ArrayView2<'_, D>, target: &ArrayView1<'_, D>,
-                  indices: Option<&Vec<usize>>, inv_depth: u8) -> i64 {
-        if inv_depth == 0 || target.dim() == 0 {
-            return -1;
+    /// Number of training samples in left and right children of node `id`.
+    /// 
+    /// Trees saved before sample counts were recorded have no training distribution, both children
+    /// get equal weights then: contributions and partial dependence are path-independent averages
+    /// over leaves instead of averages over training samples, see [`shap`].
+    fn children_samples(&self, id: usize) -> [S::F; 2] {
+        let samples = self.node_samples.get(id).copied().unwrap_or([1, 1]);
+        [S::F::from_usize(samples[0]).unwrap(), S::F::from_usize(samples[1]).unwrap()]
+    }
+
+    /// Nodes of fitted tree with explicit leaves in depth-first order, root node is the first one.
+    /// 
+    /// Tree without splits consists of a single leaf with mean target value.
+    /// Values are converted to the default floating point type exactly.
+    pub fn explicit_nodes(&self) -> Result<Vec<ExplicitNode>> {
+        if self.splitters.is_empty() {
+            let value = self.root_value.ok_or(WoodsError::NotFitted)?.to_d();
+            return Ok(vec![ExplicitNode::Leaf { value, samples: 0 }]);
         }
-        if let Some(ind) = indices {
-            if ind.len() < self.params.min_samples_split {
-                return -1;
+        let mut nodes = Vec::with_capacity(2 * self.splitters.len() + 1);
+        let samples = self.node_samples.first().map_or(0, |s| s[0] + s[1]);
+        self.push_explicit_node(0, samples, &mut nodes);
+        Ok(nodes)
+    }
+
+    /// Append explicit nodes of node `id` subtree to `nodes` and return index of the node.
+    fn push_explicit_node(&self, id: usize, samples: usize, nodes: &mut Vec<ExplicitNode>) -> usize {
+        let split_info = self.splitters[id].get_split().unwrap();
+        let children_samples = self.node_samples.get(id).copied().unwrap_or([0, 0]);
+        let index = nodes.len();
+        nodes.push(ExplicitNode::Leaf { value: D::default(), samples });
+        let mut push_child = |side: usize| {
+            let route = self.routes[id][side];
+            if route >= 0 {
+                self.push_explicit_node(route as usize, children_samples[side], nodes)
+            } else {
+                nodes.push(ExplicitNode::Leaf { value: split_info.values[side].to_d(), samples: children_samples[side] });
+                nodes.len() - 1
+            }
+        };
+        let children = [push_child(0), push_child(1)];
+        nodes[index] = ExplicitNode::Split {
+            feature: split_info.feature,
+            threshold: split_info.threshold.to_d(),
+            children,
+            default_left: true,
+            gain: split_info.gain.map_or(0.0, |gain| gain.to_d()),
+            samples,
+        };
+        index
+    }
+
+    /// Predict value of a sample with feature values, returned by `value`.
+    fn predict_sample<V: Fn(usize) -> S::F>(&self, value: V) -> S::F {
+        let mut cur: i64 = 0;
+        let mut val;
+        loop {
+            let split_info = self.splitters[cur as usize].get_split().unwrap();
+            let cond: bool = value(split_info.feature) > split_info.threshold;
+            cur = self.routes[cur as usize][cond as usize] as i64;
+            val = split_info.values[cond as usize];
+            if cur < 0 {
+                break;
             }
         }
+        val
+    }
+
+    /// Prediction of tree without splits for `n_samples` samples.
+    fn predict_root(&self, n_samples: usize) -> Result<Array1<S::F>> {
+        let value = self.root_value.ok_or(WoodsError::NotFitted)?;
+        Ok(Array1::from_elem(n_samples, value))
+    }
+
+    /// Partial dependence of node `id` subtree prediction on `features` at grid `point`.
+    fn partial_dependence_node(&self, id: usize, features: &[usize], point: &ArrayView1<'_, S::F>) -> S::F {
+        let split_info = self.splitters[id].get_split().unwrap();
+        let side_value = |side: usize| {
+            let route = self.routes[id][side];
+            if route >= 0 {
+                self.partial_dependence_node(route as usize, features, point)
+            } else {
+                split_info.values[side]
+            }
+        };
+        match features.iter().position(|f| *f == split_info.feature) {
+            Some(k) => side_value((point[k] > split_info.threshold) as usize),
+            None => {
+                let samples = self.children_samples(id);
+                (side_value(0) * samples[0] + side_value(1) * samples[1]) / (samples[0] + samples[1])
+            }
+        }
+    }
+}
+
+impl<S: SplitRule + Send + Sync> DecisionTreeImpl<S> {
+    /// Build subtree on samples by `indices` and return its nodes in depth-first order.
+    ///
+    /// Left and right subtrees of large enough nodes are built in parallel.
+    fn build_tree(&self, columns: TreeInput<'_, '_, S::F>, target: &ArrayView1<'_, S::F>,
+                  indices: Option<&Vec<usize>>, inv_depth: u8) -> Subtree<S> {
+        let mut subtree = Subtree { splitters: vec![], routes: vec![], node_samples: vec![] };
+        if inv_depth == 0 || target.dim() == 0 {
+            return subtree;
+        }
+        let n_samples = indices.map_or(target.dim(), |ind| ind.len());
+        if indices.is_some() && n_samples < self.params.min_samples_split {
+            return subtree;
+        }
 
         let mut splitter = S::new();
-        if let None = splitter.fit_by_indices(columns, target, indices) {
-            return -1;
+        let fitted = match columns {
+            TreeInput::Dense(columns) => splitter.fit_by_indices(columns, target, indices, self.params.max_features),
+            TreeInput::Sparse(columns) => {
+                splitter.fit_sparse_by_indices(columns, target, indices, self.params.max_features)
+            },
+        };
+        if fitted.is_none() {
+            return subtree;
         }
-        let split = splitter.split_indices(columns, target, indices);
-        self.splitters.push(splitter);
-        let id = self.splitters.len() - 1;
-        let left_id = self.build_tree(columns, target, Some(&split.indices[0]), inv_depth - 1);
-        let right_id = self.build_tree(columns, target, Some(&split.indices[1]), inv_depth - 1);
+        let split = match columns {
+            TreeInput::Dense(columns) => splitter.split_indices(columns, target, indices),
+            TreeInput::Sparse(columns) => splitter.split_sparse_indices(columns, indices),
+        };
+        let build_child = |child_indices| {
+            self.build_tree(columns, target, Some(child_indices), inv_depth - 1)
+        };
+        let (left, right) = if n_samples >= PARALLEL_BUILD_MIN_SAMPLES {
+            rayon::join(|| build_child(&split.indices[0]), || build_child(&split.indices[1]))
+        } else {
+            (build_child(&split.indices[0]), build_child(&split.indices[1]))
+        };
+
+        subtree.splitters.push(splitter);
+        subtree.routes.push([-1i64; 2]);
+        subtree.node_samples.push([split.indices[0].len(), split.indices[1].len()]);
+        subtree.attach(0, left);
+        subtree.attach(1, right);
+        subtree
+    }
+
+    /// Build tree on all samples of `columns` with `n_features` features.
+    fn fit_input(&mut self, columns: TreeInput<'_, '_, S::F>, n_features: usize, target: &ArrayView1<'_, S::F>) {
+        let tree = self.build_tree(columns, target, None, self.params.depth);
+        self.splitters = tree.splitters;
+        self.routes = tree.routes;
+        self.node_samples = tree.node_samples;
+        self.root_value = target.mean();
+        self.features = InputFeatures::with_n_features(n_features);
+    }
+}
+
+impl<S: SplitRule + Send + Sync> Estimator for DecisionTreeImpl<S> {
+    type F = S::F;
+
+    fn fit_unchecked(&mut self, columns: &ArrayView2<'_, S::F>, target: &ArrayView1<'_, S::F>) -> Result<()> {
+        self.fit_input(TreeInput::Dense(columns), columns.dim().0, target);
+        Ok(())
+    }
+
+    fn predict(&self, columns: &ArrayView2<'_, S::F>) -> Result<Array1<S::F>> {
+        self.features.check(columns)?;
+        if self.splitters.is_empty() {
+            return self.predict_root(columns.dim().1);
+        }
+        Ok(columns.axis_iter(Axis(1)).map(|features| {
+            self.predict_sample(|feature| features[feature])
+        }).collect::<Array1<S::F>>())
+    }
+}
+
+impl<S: SplitRule + Send + Sync> SparseEstimator for DecisionTreeImpl<S> {
+    fn fit_sparse_unchecked(&mut self, columns: &SparseColumns<S::F>, target: &ArrayView1<'_, S::F>) -> Result<()> {
+        self.fit_input(TreeInput::Sparse(columns), columns.n_features(), target);
+        Ok(())
+    }
+
+    /// Predict on sparse `columns`, feature values of samples are found by binary search.
+    fn predict_sparse(&self, columns: &SparseColumns<S::F>) -> Result<Array1<S::F>> {
+        self.features.check_n_features(columns.n_features())?;
+        if self.splitters.is_empty() {
+            return self.predict_root(columns.n_samples());
+        }
+        Ok((0..columns.n_samples()).map(|sample| {
+            self.predict_sample(|feature| columns.get(feature, sample))
+        }).collect::<Array1<S::F>>())
+    }
+}
 
-        self.routes[id][0] = left_id;
-        self.routes[id][1] = right_id;
+impl<S: SplitRule> WithInputFeatures for DecisionTreeImpl<S> {
+    fn input_features(&self) -> &InputFeatures {
+        &self.features
+    }
 
-        id as i64
+    fn input_features_mut(&mut self) -> &mut InputFeatures {
+        &mut self.features
     }
 }
 
-impl<S: SplitRule> Estimator for DecisionTreeImpl<S> {
-    fn fit(&mut self, columns: &ArrayView2<'_, D>, target: &ArrayView1<'_, D>) {
-        self.splitters.clear();
-        self.routes.clear();
+impl<S: SplitRule> WithParameters for DecisionTreeImpl<S> {
+    type Params = TreeParameters;
+
+    fn params(&self) -> &TreeParameters {
+        &self.params
+    }
+
+    fn params_mut(&mut self) -> &mut TreeParameters {
+        &mut self.params
+    }
+}
 
-        let n_nodes = 2usize.pow(self.params.depth.into());
-        self.routes.resize(n_nodes, [-1i64; 2]);
+impl<S: SplitRule> AsWeightedTrees for DecisionTreeImpl<S> {
+    fn weighted_trees(&self) -> Result<WeightedTrees> {
+        Ok(WeightedTrees {
+            features: self.features.clone(),
+            base_value: D::default(),
+            trees: vec![(1.0, self.explicit_nodes()?)],
+        })
+    }
+}
 
-        self.build_tree(columns, target, None, self.params.depth);
+impl<S: SplitRule> AsTreeSum for DecisionTreeImpl<S> {
+    fn tree_sum(&self) -> Result<TreeSum> {
+        Ok(TreeSum {
+            features: self.features.clone(),
+            trees: vec![self.explicit_nodes()?],
+            boosting: None,
+        })
     }
+}
 
-    fn predict(&self, columns: &ArrayView2<'_, D>) -> Array1<D> {
-        columns.axis_iter(Axis(1)).map(|features| {
-            let mut cur: i64 = 0;
-            let mut val;
-            loop {
-                let split_info = self.splitters[cur as usize].get_split().unwrap();
-                let cond: bool = features[split_info.feature] > split_info.threshold;
-                cur = self.routes[cur as usize][cond as usize] as i64;
-                val = split_info.values[cond as usize];
-                if cur < 0 {
-                    break;
-                }
+impl<S: SplitRule> Model for DecisionTreeImpl<S> where Self: Serialize + DeserializeOwned {
+    const KIND: &'static str = "DecisionTree";
+    const DTYPE: &'static str = S::F::NAME;
+
+    fn feature_names(&self) -> Option<Vec<String>> {
+        self.features.names.clone()
+    }
+}
+
+impl<S: SplitRule> FeatureImportance for DecisionTreeImpl<S> {
+    fn feature_importances(&self, n_features: usize, importance_type: ImportanceType) -> Result<Array1<D>> {
+        self.features.check_n_features(n_features)?;
+        let mut importances = Array1::zeros(n_features);
+        for split_info in self.splitters.iter().filter_map(|s| s.get_split()) {
+            // number of features is unknown for models saved by older versions
+            if split_info.feature >= n_features {
+                return Err(WoodsError::ShapeMismatch {
+                    what: "features",
+                    expected: split_info.feature + 1,
+                    found: n_features,
+                });
             }
-            val
-        }).collect::<Array1<D>>()
+            importances[split_info.feature] += match importance_type {
+                ImportanceType::Split => 1.0 as D,
+                ImportanceType::Gain => split_info.gain.ok_or_else(|| WoodsError::UnsupportedModel(
+                    "gain importances of model without stored split gains, use split importances".into()
+                ))?.to_d(),
+            };
+        }
+        Ok(importances)
+    }
+}
+
+impl<S: SplitRule + Send + Sync> PartialDependenceRecursion for DecisionTreeImpl<S> {
+    fn partial_dependence_recursion(&self, features: &[usize], grid: &ArrayView2<'_, S::F>) -> Array1<S::F> {
+        if self.splitters.is_empty() {
+            return Array1::from_elem(grid.dim().0, self.root_value.unwrap_or_default());
+        }
+        grid.outer_iter().map(|point| {
+            self.partial_dependence_node(0, features, &point)
+        }).collect::<Array1<S::F>>()
     }
 }
 
@@ -1,55 +1,70 @@
 use ndarray::{ArrayView2, ArrayView1, Array1};
 use rand;
 use rand::Rng;
+use rand::seq::index::sample;
 // use rand::distributions::Uniform;
-use average::Variance;
-// use ndarray::parallel::prelude::*;
+use average::{Estimate, Variance, Merge};
+use rayon::prelude::*;
 use serde::{Serialize, Deserialize};
-use crate::utils::numerics::{D, NonNan};
+use crate::utils::numerics::{D, Float, NonNan};
 use crate::estimator::Estimator;
+use crate::error::{Result, WoodsError};
+use crate::utils::serialization::Model;
 use crate::utils::array::*;
+use crate::utils::sparse::SparseColumns;
 
 /// Split information.
 #[derive(Default, Debug, Serialize, Deserialize)]
-pub struct Split {
+#[serde(bound = "F: Float")]
+pub struct Split<F = D> {
     /// Feature id / number.
     pub feature: usize,
     /// Threshold for splitting.
-    pub threshold: D,
+    pub threshold: F,
     /// Split impurity. Lower values correspond to better split.
-    pub impurity: D,
+    pub impurity: F,
+    /// Impurity decrease, i.e. difference between node impurity and split impurity
+    /// (`None` for models saved without split gains).
+    #[serde(default)]
+    pub gain: Option<F>,
     /// Left and right mean values.
-    pub values: [D; 2]
+    pub values: [F; 2]
 }
 
+/// Minimal amount of work (number of samples times number of candidate features)
+/// to evaluate candidate features in parallel.
+const PARALLEL_SPLIT_MIN_WORK: usize = 1usize << 14;
+
 /// Random Split Rule implementation.
 /// 
 /// Each feature split threshold value is selected randomly from uniform distribution.
 /// Then feature with smallest impurity is used.
 #[derive(Debug, Serialize, Deserialize)]
-pub struct RandomSplitRule {
+#[serde(bound = "F: Float")]
+pub struct RandomSplitRule<F = D> {
     /// Split information. If it is `None` after `fit`, training failed.
-    pub split_info: Option<Split>
+    pub split_info: Option<Split<F>>
 }
 
-fn find_split<'a, 'b>(
-        column: &'a ArrayView1<'_, D>,
-        target: &ArrayView1<'_, D>,
+/// Find random split of `column`, variances are accumulated in the default floating point type.
+fn find_split<'a, 'b, F: Float>(
+        column: &'a ArrayView1<'_, F>,
+        target: &ArrayView1<'_, F>,
         indices: Option<&'b Vec<usize>>,
         id: usize
-    ) -> Option<Split> {
-    let min: D;
-    let max: D;
+    ) -> Option<Split<F>> {
+    let min: F;
+    let max: F;
     if indices.is_some() {
-        min = column.iter_explicit_by_index(indices.unwrap()).map(NonNan::from).min().map(NonNan::into)?;
-        max = column.iter_explicit_by_index(indices.unwrap()).map(NonNan::from).max().map(NonNan::into)?;
+        min = column.iter_explicit_by_index(indices.unwrap()).map(NonNan::from).min().map(NonNan::into_inner)?;
+        max = column.iter_explicit_by_index(indices.unwrap()).map(NonNan::from).max().map(NonNan::into_inner)?;
     } else {
-        min = column.iter().map(NonNan::from).min().map(NonNan::into)?;
-        max = column.iter().map(NonNan::from).max().map(NonNan::into)?;
+        min = column.iter().map(NonNan::from).min().map(NonNan::into_inner)?;
+        max = column.iter().map(NonNan::from).max().map(NonNan::into_inner)?;
     }
 
     let mut rng = rand::thread_rng();
-    let threshold: D = if min < max {
+    let threshold: F = if min < max {
         rng.gen_range(min, max)
     } else {
         return None;
@@ -65,7 +80,7 @@ fn find_split<'a, 'b>(
                                    let $var = k.0;
                                    $comp
                                })
-                              .map(|k| k.1)
+                              .map(|k| k.1.to_d())
                               .collect();
             } else {
                 $side = column.iter().cloned()
@@ -74,7 +89,7 @@ fn find_split<'a, 'b>(
                                    let $var = k.0;
                                    $comp
                                })
-                              .map(|k| k.1)
+                              .map(|k| k.1.to_d())
                               .collect();
             }
         };
@@ -83,16 +98,97 @@ fn find_split<'a, 'b>(
     calc_variance!(left, it, it <= threshold);
     calc_variance!(right, it, it > threshold);
 
+    Some(make_split(left, right, threshold, id))
+}
+
+/// Find random split of sparse `feature` of node samples by sorted `indices`.
+///
+/// Zeros are not stored, target values of samples with zero feature value are accumulated
+/// as a single block, which goes to the left or right side as a whole.
+fn find_sparse_split<F: Float>(
+        columns: &SparseColumns<F>,
+        target: &ArrayView1<'_, F>,
+        indices: Option<&Vec<usize>>,
+        feature: usize
+    ) -> Option<Split<F>> {
+    let (samples, values) = columns.feature(feature);
+    let mut explicit: Vec<(F, F)> = Vec::new();
+    let mut zeros = Variance::new();
+    let mut k = 0;
+    let mut visit = |sample: usize| {
+        while k < samples.len() && samples[k] < sample {
+            k += 1;
+        }
+        if k < samples.len() && samples[k] == sample {
+            explicit.push((values[k], target[sample]));
+        } else {
+            zeros.add(target[sample].to_d());
+        }
+    };
+    match indices {
+        Some(ind) => ind.iter().for_each(|sample| visit(*sample)),
+        None => (0..columns.n_samples()).for_each(visit),
+    }
+
+    let zero = if zeros.is_empty() { None } else { Some(F::default()) };
+    let min = explicit.iter().map(|e| e.0).chain(zero).map(NonNan::from).min().map(NonNan::into_inner)?;
+    let max = explicit.iter().map(|e| e.0).chain(zero).map(NonNan::from).max().map(NonNan::into_inner)?;
+    let threshold: F = if min < max {
+        rand::thread_rng().gen_range(min, max)
+    } else {
+        return None;
+    };
+
+    let mut left: Variance = explicit.iter().filter(|e| e.0 <= threshold).map(|e| e.1.to_d()).collect();
+    let mut right: Variance = explicit.iter().filter(|e| e.0 > threshold).map(|e| e.1.to_d()).collect();
+    if F::default() <= threshold {
+        left.merge(&zeros);
+    } else {
+        right.merge(&zeros);
+    }
+    Some(make_split(left, right, threshold, feature))
+}
+
+/// Split of `feature` by `threshold` with target variances of `left` and `right` sides.
+fn make_split<F: Float>(left: Variance, right: Variance, threshold: F, feature: usize) -> Split<F> {
     let impurity = left.population_variance() * (left.len() as D) + right.population_variance() * (right.len() as D);
-    
-    Some(Split {
-        feature: id,
-        threshold: threshold,
-        impurity: impurity,
-        values: [left.mean(), right.mean()]
-    })
+    let mut node = left.clone();
+    node.merge(&right);
+    let gain = node.population_variance() * (node.len() as D) - impurity;
+
+    Split {
+        feature,
+        threshold,
+        impurity: F::from_d(impurity),
+        gain: Some(F::from_d(gain)),
+        values: [F::from_d(left.mean()), F::from_d(right.mean())]
+    }
 }
 
+/// Find the best of random splits by `max_features` distinct features, selected randomly from `n_features` features.
+///
+/// Candidate features are evaluated in parallel if there is enough work.
+fn best_split<F, S>(n_features: usize, max_features: usize, n_samples: usize, feature_split: S) -> Option<Split<F>>
+    where F: Float, S: Fn(&usize) -> Option<Split<F>> + Send + Sync {
+    if n_features == 0 {
+        return None;
+    }
+    let need_features = max_features.max(1).min(n_features);
+    let mut rng = rand::thread_rng();
+    let mut active_features = sample(&mut rng, n_features, need_features).into_vec();
+    active_features.sort_unstable();
+
+    let by_impurity = |split: &Split<F>| NonNan::from(split.impurity);
+    if n_samples * active_features.len() >= PARALLEL_SPLIT_MIN_WORK {
+        active_features.par_iter()
+            .filter_map(feature_split)
+            .min_by_key(by_impurity)
+    } else {
+        active_features.iter()
+            .filter_map(feature_split)
+            .min_by_key(by_impurity)
+    }
+}
 
 type Indices = Vec<usize>;
 
@@ -105,67 +201,55 @@ pub struct SplitIndices {
 
 /// Split rule can be fit by indices and then split data indices into `SplitIndices`.
 pub trait SplitRule {
+    /// Floating point type of data and split values.
+    type F: Float;
     fn new() -> Self;
-    /// Fit using elements corresponding to `indices`.
+    /// Fit using elements corresponding to `indices`, the best split of `max_features` features is selected.
     /// If `indices` is `None`, all elements are used.
-    fn fit_by_indices(&mut self, columns: &ArrayView2<'_, D>, target: &ArrayView1<'_, D>,
-                      indices: Option<&Vec<usize>>) -> Option<()>;
-    fn split_indices(&self, columns: &ArrayView2<'_, D>, _target: &ArrayView1<'_, D>,
+    fn fit_by_indices(&mut self, columns: &ArrayView2<'_, Self::F>, target: &ArrayView1<'_, Self::F>,
+                      indices: Option<&Vec<usize>>, max_features: usize) -> Option<()>;
+    fn split_indices(&self, columns: &ArrayView2<'_, Self::F>, _target: &ArrayView1<'_, Self::F>,
                          indices: Option<&Vec<usize>>) -> SplitIndices;
+    /// Fit using sparse `columns` elements corresponding to sorted `indices`, see [`SplitRule::fit_by_indices`].
+    /// If `indices` is `None`, all elements are used.
+    fn fit_sparse_by_indices(&mut self, columns: &SparseColumns<Self::F>, target: &ArrayView1<'_, Self::F>,
+                             indices: Option<&Vec<usize>>, max_features: usize) -> Option<()>;
+    /// Split sample `indices` of sparse `columns`, the order of indices is preserved.
+    fn split_sparse_indices(&self, columns: &SparseColumns<Self::F>, indices: Option<&Vec<usize>>) -> SplitIndices;
     /// Get split information.
-    fn get_split(&self) -> Option<&Split>;
+    fn get_split(&self) -> Option<&Split<Self::F>>;
 }
 
-impl SplitRule for RandomSplitRule {
+impl<F: Float> SplitRule for RandomSplitRule<F> {
+    type F = F;
+
     fn new() -> Self {
         RandomSplitRule {
             split_info: None
         }
     }
 
-    fn fit_by_indices(&mut self, columns: &ArrayView2<'_, D>, target: &ArrayView1<'_, D>,
-                      indices: Option<&Vec<usize>>) -> Option<()> {
-        let n_features = columns.dim().0;
-        let need_features = 1;
-        let mut rng = rand::thread_rng();
-        let active_features: Vec<usize> = (0..need_features)
-                                            .map(|_| rng.gen_range(0, n_features))
-                                            .collect();
-
-        // self.split_info = columns.outer_iter().into_par_iter().enumerate().map(move |col| {
-        self.split_info = columns.outer_iter()
-            .enumerate()
-            .filter(|col| {
-                if need_features == 0 {
-                    false
-                } else {
-                    for i in &active_features {
-                        if &col.0 == i {
-                            return true;
-                        }
-                    }
-                    false
-                    // if selected_feature == col.0 {
-                    //     need_features -= 1;
-                    //     true
-                    // } else {
-                    //     false
-                    // }
-                }
-            })
-            .map(move |col| {
-                find_split(&col.1, &target, indices, col.0)
-            })
-            .filter(|opt| {
-                opt.is_some()
-            })
-            .min_by_key(|split| {
-                NonNan::from(split.as_ref().unwrap().impurity)
-            })?;
+    fn fit_by_indices(&mut self, columns: &ArrayView2<'_, F>, target: &ArrayView1<'_, F>,
+                      indices: Option<&Vec<usize>>, max_features: usize) -> Option<()> {
+        let n_samples = indices.map_or(target.dim(), |ind| ind.len());
+        self.split_info = best_split(columns.dim().0, max_features, n_samples, |feature: &usize| {
+            find_split(&columns.row(*feature), &target, indices, *feature)
+        });
+        self.split_info.as_ref()?;
         Some(())
     }
 
-    fn split_indices(&self, columns: &ArrayView2<'_, D>, _target: &ArrayView1<'_, D>,
+    fn fit_sparse_by_indices(&mut self, columns: &SparseColumns<F>, target: &ArrayView1<'_, F>,
+                             indices: Option<&Vec<usize>>, max_features: usize) -> Option<()> {
+        let n_samples = indices.map_or(target.dim(), |ind| ind.len());
+        self.split_info = best_split(columns.n_features(), max_features, n_samples, |feature: &usize| {
+            find_sparse_split(columns, target, indices, *feature)
+        });
+        self.split_info.as_ref()?;
+        Some(())
+    }
+
+    fn split_indices(&self, columns: &ArrayView2<'_, F>, _target: &ArrayView1<'_, F>,
                          indices: Option<&Vec<usize>>) -> SplitIndices {
         let mut result = SplitIndices::default();
         let split_info = self.split_info.as_ref().unwrap();
@@ -184,22 +268,56 @@ impl SplitRule for RandomSplitRule {
         result
     }
 
-    fn get_split(&self) -> Option<&Split> {
+    fn split_sparse_indices(&self, columns: &SparseColumns<F>, indices: Option<&Vec<usize>>) -> SplitIndices {
+        let mut result = SplitIndices::default();
+        let split_info = self.split_info.as_ref().unwrap();
+        let mut push = |id: usize| {
+            let cond = columns.get(split_info.feature, id) > split_info.threshold;
+            result.indices[cond as usize].push(id);
+        };
+        match indices {
+            Some(ind) => ind.iter().for_each(|id| push(*id)),
+            None => (0..columns.n_samples()).for_each(push),
+        }
+        result
+    }
+
+    fn get_split(&self) -> Option<&Split<F>> {
         self.split_info.as_ref()
     }
 }
 
+impl<F: Float> Model for RandomSplitRule<F> {
+    const KIND: &'static str = "DecisionRule";
+    const DTYPE: &'static str = F::NAME;
+
+    fn feature_names(&self) -> Option<Vec<String>> {
+        None
+    }
+}
+
 impl<T: SplitRule> Estimator for T {
-    fn fit(&mut self, columns: &ArrayView2<'_, D>, target: &ArrayView1<'_, D>) {
-        self.fit_by_indices(columns, target, None);
+    type F = T::F;
+
+    fn fit_unchecked(&mut self, columns: &ArrayView2<'_, T::F>, target: &ArrayView1<'_, T::F>) -> Result<()> {
+        // standalone split rule uses a single random feature
+        self.fit_by_indices(columns, target, None, 1);
+        Ok(())
     }
 
-    fn predict(&self, columns: &ArrayView2<'_, D>) -> Array1<D> {
-        let split_info = self.get_split().unwrap();
-        columns.row(split_info.feature).iter().map(|val| {
+    fn predict(&self, columns: &ArrayView2<'_, T::F>) -> Result<Array1<T::F>> {
+        let split_info = self.get_split().ok_or(WoodsError::NotFitted)?;
+        if split_info.feature >= columns.dim().0 {
+            return Err(WoodsError::ShapeMismatch {
+                what: "features",
+                expected: split_info.feature + 1,
+                found: columns.dim().0,
+            });
+        }
+        Ok(columns.row(split_info.feature).iter().map(|val| {
             let cond = *val > split_info.threshold;
             let index = cond as usize;
             split_info.values[index]
-        }).collect::<Array1<D>>()
+        }).collect::<Array1<T::F>>())
     }
-}
+}
\ No newline at end of file
@@ -0,0 +1,182 @@
+//! Exact TreeSHAP feature contributions for decision trees.
+//! 
+//! Implementation of the Algorithm 2 from *"Consistent Individualized Feature Attribution
+//! for Tree Ensembles"* (Lundberg et al., 2018). Node weights are numbers of training samples,
+//! recorded during tree building. Contributions are calculated in the default floating point type.
+//! 
+//! Trees saved without sample counts fall back to path-independent attribution: both children of each
+//! split have equal weights, so the bias is the mean of leaf values with each split halving the weight
+//! instead of the mean training prediction. Contributions still sum up to the prediction.
+
+use ndarray::{ArrayView1, ArrayView2, ArrayViewMut1, Array2, Axis};
+use crate::estimator::PredictContributions;
+use crate::utils::numerics::{D, Float};
+use crate::error::Result;
+use super::DecisionTreeImpl;
+use super::rule::SplitRule;
+
+/// Element of unique feature path from the root to the current node.
+#[derive(Clone, Copy)]
+struct PathElement {
+    /// Split feature, `None` for the root element.
+    feature: Option<usize>,
+    /// Fraction of zero paths (feature is not known) that flow through the branch.
+    zero_fraction: D,
+    /// Fraction of one paths (feature is known) that flow through the branch.
+    one_fraction: D,
+    /// Proportion of feature subsets of given cardinality.
+    pweight: D,
+}
+
+/// Tree node: internal node id or leaf value.
+#[derive(Clone, Copy)]
+enum Node {
+    Internal(usize),
+    Leaf(D),
+}
+
+/// Extend path with a new feature element.
+fn extend_path(path: &mut Vec<PathElement>, zero_fraction: D, one_fraction: D, feature: Option<usize>) {
+    let depth = path.len();
+    path.push(PathElement {
+        feature,
+        zero_fraction,
+        one_fraction,
+        pweight: if depth == 0 { 1.0 } else { 0.0 },
+    });
+    for i in (0..depth).rev() {
+        path[i + 1].pweight += one_fraction * path[i].pweight * ((i + 1) as D) / ((depth + 1) as D);
+        path[i].pweight = zero_fraction * path[i].pweight * ((depth - i) as D) / ((depth + 1) as D);
+    }
+}
+
+/// Undo extension of path by element at `index`.
+fn unwind_path(path: &mut Vec<PathElement>, index: usize) {
+    let depth = path.len() - 1;
+    let one_fraction = path[index].one_fraction;
+    let zero_fraction = path[index].zero_fraction;
+    let mut next_one_portion = path[depth].pweight;
+    for i in (0..depth).rev() {
+        if one_fraction != 0.0 {
+            let tmp = path[i].pweight;
+            path[i].pweight = next_one_portion * ((depth + 1) as D) / (((i + 1) as D) * one_fraction);
+            next_one_portion = tmp - path[i].pweight * zero_fraction * ((depth - i) as D) / ((depth + 1) as D);
+        } else {
+            path[i].pweight = path[i].pweight * ((depth + 1) as D) / (zero_fraction * ((depth - i) as D));
+        }
+    }
+    for i in index..depth {
+        path[i].feature = path[i + 1].feature;
+        path[i].zero_fraction = path[i + 1].zero_fraction;
+        path[i].one_fraction = path[i + 1].one_fraction;
+    }
+    path.pop();
+}
+
+/// Total permutation weight of path, if it was unwound by element at `index`.
+fn unwound_path_sum(path: &[PathElement], index: usize) -> D {
+    let depth = path.len() - 1;
+    let one_fraction = path[index].one_fraction;
+    let zero_fraction = path[index].zero_fraction;
+    let mut next_one_portion = path[depth].pweight;
+    let mut total = 0.0;
+    for i in (0..depth).rev() {
+        if one_fraction != 0.0 {
+            let tmp = next_one_portion * ((depth + 1) as D) / (((i + 1) as D) * one_fraction);
+            total += tmp;
+            next_one_portion = path[i].pweight - tmp * zero_fraction * ((depth - i) as D) / ((depth + 1) as D);
+        } else if zero_fraction != 0.0 {
+            total += path[i].pweight / zero_fraction / (((depth - i) as D) / ((depth + 1) as D));
+        }
+    }
+    total
+}
+
+impl<S: SplitRule> DecisionTreeImpl<S> {
+    /// Number of training samples in left and right children of node `id`, see [`DecisionTreeImpl::children_samples`].
+    fn shap_samples(&self, id: usize) -> [D; 2] {
+        let samples = self.children_samples(id);
+        [samples[0].to_d(), samples[1].to_d()]
+    }
+
+    fn child(&self, id: usize, side: usize) -> Node {
+        let route = self.routes[id][side];
+        if route >= 0 {
+            Node::Internal(route as usize)
+        } else {
+            Node::Leaf(self.splitters[id].get_split().unwrap().values[side].to_d())
+        }
+    }
+
+    /// Expected value of prediction, weighted by training samples.
+    fn expected_value(&self, node: Node) -> D {
+        match node {
+            Node::Leaf(value) => value,
+            Node::Internal(id) => {
+                let samples = self.shap_samples(id);
+                let total = samples[0] + samples[1];
+                (0..2).map(|side| {
+                    self.expected_value(self.child(id, side)) * samples[side] / total
+                }).sum()
+            }
+        }
+    }
+
+    /// Accumulate contributions `phi` of `node` subtree, `path` is extended by the `node` parent split.
+    fn shap_recurse(&self, features: &ArrayView1<'_, S::F>, phi: &mut ArrayViewMut1<'_, D>, node: Node,
+                    mut path: Vec<PathElement>) {
+        match node {
+            Node::Leaf(value) => {
+                for i in 1..path.len() {
+                    let weight = unwound_path_sum(&path, i);
+                    let element = &path[i];
+                    phi[element.feature.unwrap()] += weight * (element.one_fraction - element.zero_fraction) * value;
+                }
+            },
+            Node::Internal(id) => {
+                let split_info = self.splitters[id].get_split().unwrap();
+                let hot = (features[split_info.feature] > split_info.threshold) as usize;
+                let cold = 1 - hot;
+                let samples = self.shap_samples(id);
+                let total = samples[0] + samples[1];
+
+                let mut incoming_zero_fraction = 1.0;
+                let mut incoming_one_fraction = 1.0;
+                // undo previous split on the same feature to redo it for this node
+                if let Some(index) = path.iter().position(|el| el.feature == Some(split_info.feature)) {
+                    incoming_zero_fraction = path[index].zero_fraction;
+                    incoming_one_fraction = path[index].one_fraction;
+                    unwind_path(&mut path, index);
+                }
+
+                let mut hot_path = path.clone();
+                extend_path(&mut hot_path, samples[hot] / total * incoming_zero_fraction,
+                            incoming_one_fraction, Some(split_info.feature));
+                self.shap_recurse(features, phi, self.child(id, hot), hot_path);
+                extend_path(&mut path, samples[cold] / total * incoming_zero_fraction,
+                            0.0, Some(split_info.feature));
+                self.shap_recurse(features, phi, self.child(id, cold), path);
+            },
+        }
+    }
+}
+
+impl<S: SplitRule + Send + Sync> PredictContributions for DecisionTreeImpl<S> {
+    fn predict_contributions(&self, columns: &ArrayView2<'_, S::F>) -> Result<Array2<D>> {
+        self.features.check(columns)?;
+        let (n_features, n_samples) = columns.dim();
+        let mut contributions = Array2::zeros((n_samples, n_features + 1));
+        if self.splitters.is_empty() {
+            contributions.column_mut(n_features).fill(self.root_value.unwrap_or_default().to_d());
+            return Ok(contributions);
+        }
+        let bias = self.expected_value(Node::Internal(0));
+        for (features, mut phi) in columns.axis_iter(Axis(1)).zip(contributions.outer_iter_mut()) {
+            let mut path = vec![];
+            extend_path(&mut path, 1.0, 1.0, None);
+            self.shap_recurse(&features, &mut phi, Node::Internal(0), path);
+            phi[n_features] = bias;
+        }
+        Ok(contributions)
+    }
+}
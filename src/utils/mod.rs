@@ -1,5 +1,6 @@
-//! Utils: array iterators, numerical wrappers and serialization.
+//! Utils: array iterators, numerical wrappers, sparse matrices and serialization.
 
 pub mod array;
 pub mod numerics;
-pub mod serialization;
\ No newline at end of file
+pub mod serialization;
+pub mod sparse;
\ No newline at end of file
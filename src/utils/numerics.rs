@@ -1,11 +1,60 @@
-//! Numeric utils: default floating point type (`D`) and `NonNan` wrapper.
+//! Numeric utils: floating point types (`Float`, default `D`), `NonNan` wrapper and adjacent floats.
 //! 
 
 use std::cmp::Ordering;
+use std::fmt::{Debug, Display};
+use std::iter::Sum;
+use std::ops::{AddAssign, SubAssign, MulAssign, DivAssign};
+use ndarray::{LinalgScalar, ScalarOperand};
+use num_traits::{FromPrimitive, Signed};
+use rand::distributions::uniform::SampleUniform;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
 
 /// Default floating point type.
 pub type D = f64;
 
+/// Floating point type of model values and input data: `f32` or `f64`.
+/// 
+/// Aggregated statistics (scores, feature importances, hyperparameters) are always [`D`].
+pub trait Float: num_traits::Float + FromPrimitive + Signed + Sum + AddAssign + SubAssign + MulAssign + DivAssign
+                 + LinalgScalar + ScalarOperand + SampleUniform + Default + Debug + Display
+                 + Serialize + DeserializeOwned + Send + Sync + 'static {
+    /// Type name, the same as numpy `dtype` name.
+    const NAME: &'static str;
+    /// Convert from the default floating point type, `f32` values are rounded to nearest.
+    fn from_d(value: D) -> Self;
+    /// Convert to the default floating point type.
+    fn to_d(self) -> D;
+}
+
+impl Float for f32 {
+    const NAME: &'static str = "float32";
+
+    fn from_d(value: D) -> Self {
+        value as f32
+    }
+
+    fn to_d(self) -> D {
+        self as D
+    }
+}
+
+impl Float for f64 {
+    const NAME: &'static str = "float64";
+
+    fn from_d(value: D) -> Self {
+        value
+    }
+
+    fn to_d(self) -> D {
+        self
+    }
+}
+
+/// Names of available floating point types.
+pub const FLOAT_TYPES: &[&str; 2] = &[f32::NAME, f64::NAME];
+
 /// Numeric data type wrapper with ordering for non-`NaN` numbers.
 /// 
 /// **NaN-safeness should be checked manually before wrapping into `NonNan`**
@@ -15,36 +64,54 @@ pub type D = f64;
 /// `NaN` number will result it `panic`.
 /// 
 #[derive(PartialEq,PartialOrd)]
-pub struct NonNan(D);
+pub struct NonNan<F: Float = D>(F);
 
-impl Eq for NonNan {}
+impl<F: Float> Eq for NonNan<F> {}
 
-impl Clone for NonNan {
+impl<F: Float> Clone for NonNan<F> {
     fn clone(&self) -> Self {
         NonNan::from(self.0)
     }
 }
 
-impl Ord for NonNan {
-    fn cmp(&self, other: &NonNan) -> Ordering {
+impl<F: Float> Ord for NonNan<F> {
+    fn cmp(&self, other: &NonNan<F>) -> Ordering {
         self.partial_cmp(other).unwrap()
     }
 }
 
-impl From<D> for NonNan {
-    fn from(item: D) -> Self {
+impl<F: Float> From<F> for NonNan<F> {
+    fn from(item: F) -> Self {
         NonNan(item)
     }
 }
 
-impl From<&D> for NonNan {
-    fn from(item: &D) -> Self {
+impl<F: Float> From<&F> for NonNan<F> {
+    fn from(item: &F) -> Self {
         NonNan(*item)
     }
 }
 
-impl Into<D> for NonNan {
-    fn into(self) -> D {
+impl<F: Float> NonNan<F> {
+    /// Unwrap the number.
+    pub fn into_inner(self) -> F {
         self.0
     }
-}
\ No newline at end of file
+}
+
+/// Smallest number greater than `x`, `x` itself for `NaN` and positive infinity.
+pub fn next_up(x: D) -> D {
+    if x.is_nan() || x == D::INFINITY {
+        return x;
+    }
+    if x == 0.0 {
+        return D::from_bits(1);
+    }
+    let bits = x.to_bits();
+    D::from_bits(if x > 0.0 { bits + 1 } else { bits - 1 })
+}
+
+/// Largest number less than `x`, `x` itself for `NaN` and negative infinity.
+pub fn next_down(x: D) -> D {
+    -next_up(-x)
+}
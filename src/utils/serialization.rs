@@ -6,19 +6,76 @@
 //! List of currently implemented formats:
 //! 1. `json`
 //! 2. `bincode`
+//! 3. `msgpack` (MessagePack)
+//! 4. `cbor`
+//! 
+//! Any format can be transparently compressed with one of [`COMPRESSIONS`]
+//! by adding compression name to the format, e.g. `bincode+zstd` or `json+gzip`.
+//! 
+//! # Envelope
+//! Models are wrapped into versioned envelope: [`MAGIC`] marker, [`ModelHeader`]
+//! (format version, crate version, model kind, timestamp, feature names and user metadata)
+//! and the model itself.
+//! Files without [`MAGIC`], written by older versions, are loaded as raw models of version `0`:
+//! JSON models and bincode decision trees, gradient boosting and deep gradient boosting models
+//! (`float64`, the only type of older versions) are upgraded to the current model representation.
+//! 
+//! JSON envelope is `{"magic": "WOODS", "header": {...}, "model": {...}}` with keys in this order,
+//! so the header is checked before the model is decoded; binary envelope is [`MAGIC`] bytes followed by header and model.
+//! Compression is applied to the whole envelope.
+//! 
+//! # Format detection
+//! If format is not set, [`save`] infers it from the file extension (see [`Format::from_extension`]),
+//! e.g. `model.bin.zst` is saved as `bincode+zstd`, and falls back to [`DEFAULT_SERIALIZATION_FORMAT`].
+//! [`load`], [`from_bytes`] and [`read_header`] detect the format by content: compression by its magic bytes,
+//! JSON by the opening brace, binary envelopes by [`MAGIC`] and the first byte of the header;
+//! anything else is treated as raw bincode model of older versions.
+//! 
+//! Models can also be serialized in memory with [`to_bytes`] and [`from_bytes`].
 //! 
 //! # Example
-//! ```
-//! let params = TreeParameters::new(None, None);
-//! save(&params, "params.json", Some("json"));
+//! ```no_run
+//! # use std::collections::BTreeMap;
+//! # use ndarray::array;
+//! # use woods::estimator::Estimator;
+//! # use woods::tree::{DecisionTreeImpl, TreeParameters};
+//! # use woods::tree::rule::RandomSplitRule;
+//! # use woods::utils::serialization::{load, save, save_with_metadata};
+//! # fn main() -> woods::error::Result<()> {
+//! // features are stored by columns: `(n_features, n_samples)`
+//! let columns = array![[0.0, 1.0, 2.0, 3.0]];
+//! let target = array![0.0, 0.0, 1.0, 1.0];
+//! let mut tree = DecisionTreeImpl::<RandomSplitRule>::new(TreeParameters::new(None, None));
+//! tree.fit(&columns.view(), &target.view())?;
+//! save(&tree, "tree.bin.zst", Some("bincode+zstd"))?;
+//! let header = load(&mut tree, "tree.bin.zst", Some("bincode+zstd"))?;
+//! // user metadata is stored in the header
+//! let metadata: BTreeMap<_, _> = vec![("dataset".to_string(), "toy".to_string())].into_iter().collect();
+//! save_with_metadata(&tree, "tree.json", None, metadata)?;
+//! # Ok(())
+//! # }
 //! ```
 
-use serde::Serialize;
-use serde::de::DeserializeOwned;
+use serde::{Serialize, Deserialize};
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IgnoredAny, MapAccess, Visitor};
+use serde_json::Value;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::fs::File;
+use std::path::Path;
+use std::io::{self, BufReader, BufWriter, Read, Write};
 use std::fmt;
-use pyo3::prelude::{PyResult, PyErr};
+use std::marker::PhantomData;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+#[cfg(feature = "python")]
+use pyo3::prelude::PyErr;
+#[cfg(feature = "python")]
 use pyo3::exceptions;
+use crate::error::{Result, WoodsError};
+use crate::utils::numerics::{D, Float};
 
 /// Unknown serialization file format error.
 #[derive(Debug, Clone)]
@@ -37,60 +94,672 @@ impl UnknownFormatError {
 }
 
 /// Available serialization formats.
-pub const SERIALIZATION_FORMATS: &[&str; 2] = &["json", "bincode"];
+pub const SERIALIZATION_FORMATS: &[&str; 4] = &["json", "bincode", "msgpack", "cbor"];
+/// Available compressions, that can be added to serialization format after `+`.
+pub const COMPRESSIONS: &[&str; 2] = &["gzip", "zstd"];
 /// Default serialization format (`json`).
 pub const DEFAULT_SERIALIZATION_FORMAT: &str = "json";
+/// Default zstd compression level.
+const ZSTD_LEVEL: i32 = 3;
+/// Gzip stream magic bytes.
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+/// Zstd frame magic bytes.
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
 
 impl fmt::Display for UnknownFormatError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Incorrect format: `{}`. Please, use one of: {:?}",
+        write!(f, "Incorrect format: `{}`. Please, use one of: {:?}, optionally with compression `+{{{}}}`",
                self.format,
-               SERIALIZATION_FORMATS)
+               SERIALIZATION_FORMATS,
+               COMPRESSIONS.join(","))
     }
 }
 
-impl Into<PyErr> for UnknownFormatError {
+#[cfg(feature = "python")]
+impl From<UnknownFormatError> for PyErr {
     /// Cast unknown format error to Python's `ValueError`.
-    fn into(self) -> PyErr {
-        PyErr::new::<exceptions::ValueError, _>(self.to_string())
-    }
-}
-
-/// Save serializable object to file (`filename`), using
-/// `format` file format.
-pub fn save<T: Serialize>(what: &T, filename: &str, format: Option<&str>) -> PyResult<()> {
-    let file = File::create(filename)?;
-    let f = format.unwrap_or(DEFAULT_SERIALIZATION_FORMAT);
-    match f {
-        "json" => serde_json::to_writer(file, what).unwrap(),
-        "bincode" => bincode::serialize_into(file, what).unwrap(),
-        _ => {
-            if SERIALIZATION_FORMATS.contains(&f) {
-                unimplemented!("Format `{}` serialization", f);
-            }
-            return Err(UnknownFormatError::new(f).into());
+    fn from(err: UnknownFormatError) -> PyErr {
+        PyErr::new::<exceptions::ValueError, _>(err.to_string())
+    }
+}
+
+/// Serialization format of the model envelope.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Json,
+    Bincode,
+    MsgPack,
+    Cbor,
+}
+
+/// Compression of the serialized model.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+/// File format: serialization format with optional compression, e.g. `bincode+zstd`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Format {
+    pub encoding: Encoding,
+    pub compression: Option<Compression>,
+}
+
+impl FromStr for Format {
+    type Err = UnknownFormatError;
+
+    fn from_str(name: &str) -> std::result::Result<Self, Self::Err> {
+        let mut parts = name.splitn(2, '+');
+        let encoding = match parts.next() {
+            Some("json") => Encoding::Json,
+            Some("bincode") => Encoding::Bincode,
+            Some("msgpack") => Encoding::MsgPack,
+            Some("cbor") => Encoding::Cbor,
+            _ => return Err(UnknownFormatError::new(name)),
+        };
+        let compression = match parts.next() {
+            None => None,
+            Some("gzip") => Some(Compression::Gzip),
+            Some("zstd") => Some(Compression::Zstd),
+            Some(_) => return Err(UnknownFormatError::new(name)),
+        };
+        Ok(Format { encoding, compression })
+    }
+}
+
+impl Format {
+    /// Infer format from `filename` extension: `.json`, `.bin`/`.bincode`, `.msgpack`/`.mpk` or `.cbor`,
+    /// optionally followed by `.gz` or `.zst`/`.zstd` compression extension.
+    /// 
+    /// Compressed files with unknown inner extension are treated as [`DEFAULT_SERIALIZATION_FORMAT`].
+    pub fn from_extension(filename: &str) -> Option<Format> {
+        let path = Path::new(filename);
+        let extension = |path: &Path| path.extension().and_then(|e| e.to_str()).map(str::to_lowercase);
+        let (compression, inner) = match extension(path).as_deref() {
+            Some("gz") => (Some(Compression::Gzip), extension(&path.with_extension(""))),
+            Some("zst") | Some("zstd") => (Some(Compression::Zstd), extension(&path.with_extension(""))),
+            _ => (None, extension(path)),
+        };
+        let encoding = match inner.as_deref() {
+            Some("json") => Encoding::Json,
+            Some("bin") | Some("bincode") => Encoding::Bincode,
+            Some("msgpack") | Some("mpk") => Encoding::MsgPack,
+            Some("cbor") => Encoding::Cbor,
+            _ if compression.is_some() => DEFAULT_SERIALIZATION_FORMAT.parse::<Format>().ok()?.encoding,
+            _ => return None,
+        };
+        Some(Format { encoding, compression })
+    }
+}
+
+/// Marker of the model file envelope.
+pub const MAGIC: &str = "WOODS";
+/// Current version of the model file envelope.
+/// 
+/// Version `0` denotes raw models without envelope.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Model that can be saved to versioned file with [`save`] and loaded with [`load`].
+pub trait Model: Serialize + DeserializeOwned {
+    /// Model kind name, stored in [`ModelHeader`] and checked on load.
+    const KIND: &'static str;
+    /// Floating point type of model values, see [`Float::NAME`].
+    /// 
+    /// Kind of models with non-default type has type suffix, e.g. `DecisionTree[float32]`.
+    const DTYPE: &'static str = <D as Float>::NAME;
+    /// Names of input features, stored in [`ModelHeader`].
+    fn feature_names(&self) -> Option<Vec<String>>;
+}
+
+/// Model kind of `T`, stored in [`ModelHeader`].
+pub fn model_kind<T: Model>() -> String {
+    if T::DTYPE == <D as Float>::NAME {
+        T::KIND.into()
+    } else {
+        format!("{}[{}]", T::KIND, T::DTYPE)
+    }
+}
+
+/// Header of the model file.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ModelHeader {
+    /// Version of the file format.
+    pub format_version: u32,
+    /// Version of the crate that saved the model.
+    pub crate_version: String,
+    /// Model kind, see [`model_kind`].
+    pub model_kind: String,
+    /// Unix time (seconds) of saving.
+    pub timestamp: u64,
+    /// Names of input features.
+    pub feature_names: Option<Vec<String>>,
+    /// User-defined metadata.
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl ModelHeader {
+    /// Make header of `model` with current format version and time.
+    fn of<T: Model>(model: &T, metadata: BTreeMap<String, String>) -> Self {
+        ModelHeader {
+            format_version: FORMAT_VERSION,
+            crate_version: env!("CARGO_PKG_VERSION").into(),
+            model_kind: model_kind::<T>(),
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+            feature_names: model.feature_names(),
+            metadata,
         }
     }
+
+    /// Make header of raw model without envelope, saved by older versions.
+    fn legacy<T: Model>(model: &T) -> Self {
+        ModelHeader {
+            format_version: 0,
+            crate_version: String::new(),
+            model_kind: model_kind::<T>(),
+            timestamp: 0,
+            feature_names: model.feature_names(),
+            metadata: BTreeMap::new(),
+        }
+    }
+
+    /// Floating point type of model values, see [`Model::DTYPE`].
+    pub fn dtype(&self) -> &str {
+        match (self.model_kind.find('['), self.model_kind.strip_suffix(']')) {
+            (Some(start), Some(kind)) => &kind[start + 1..],
+            _ => <D as Float>::NAME,
+        }
+    }
+
+    /// Check that model of kind `T` can be loaded from the file with this header.
+    fn check<T: Model>(&self) -> Result<()> {
+        if self.format_version > FORMAT_VERSION {
+            return Err(WoodsError::UnsupportedVersion(self.format_version));
+        }
+        if self.model_kind != model_kind::<T>() {
+            return Err(WoodsError::WrongModelKind {
+                expected: model_kind::<T>(),
+                found: self.model_kind.clone(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Migration of JSON model representation from version `i` to `i + 1`.
+type Migration = fn(Value) -> Result<Value>;
+
+/// Migrations of JSON models, `JSON_MIGRATIONS[i]` upgrades model from version `i` to `i + 1`.
+/// 
+/// Binary envelopes are loaded only by the version that saved them, since bincode is not self-describing.
+/// Raw bincode models of version `0` are decoded by their original shapes (see [`legacy`])
+/// and upgraded like raw JSON models.
+const JSON_MIGRATIONS: &[Migration] = &[
+    // 0 -> 1: envelope is added, model is the same.
+    Ok,
+];
+
+/// Models of version `0` as they were serialized, before any field was added.
+mod legacy {
+    use serde::{Serialize, Deserialize};
+    use crate::utils::numerics::D;
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Split {
+        pub feature: usize,
+        pub threshold: D,
+        pub impurity: D,
+        pub values: [D; 2],
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct RandomSplitRule {
+        pub split_info: Option<Split>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct TreeParameters {
+        pub depth: u8,
+        pub min_samples_split: usize,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct DecisionTree {
+        pub params: TreeParameters,
+        pub splitters: Vec<RandomSplitRule>,
+        pub routes: Vec<[i64; 2]>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct GradientBoostingParameters {
+        pub est_params: TreeParameters,
+        pub n_estimators: u32,
+        pub learning_rate: D,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct GradientBoosting {
+        pub params: GradientBoostingParameters,
+        pub estimators: Vec<DecisionTree>,
+        pub mean: D,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct AverageEnsemble {
+        pub estimators: Vec<GradientBoosting>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct DeepBoostingParameters {
+        pub n_estimators: u32,
+        pub layer_width: u32,
+        pub learning_rate: D,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct DeepGradientBoosting {
+        pub params: DeepBoostingParameters,
+        pub estimators: Vec<AverageEnsemble>,
+    }
+}
+
+/// Make corrupted model error from deserialization error.
+fn corrupted<E: fmt::Display>(err: E) -> WoodsError {
+    WoodsError::CorruptedModel(err.to_string())
+}
+
+/// Make I/O error from serialization error.
+fn io_error<E: std::error::Error + Send + Sync + 'static>(err: E) -> io::Error {
+    io::Error::other(err)
+}
+
+/// Parse format name or use the default one.
+fn parse_format(format: Option<&str>) -> Result<Format> {
+    Ok(format.unwrap_or(DEFAULT_SERIALIZATION_FORMAT).parse()?)
+}
+
+/// Parse format name or infer it from `filename` extension, otherwise use the default one.
+fn saved_format(filename: &str, format: Option<&str>) -> Result<Format> {
+    match format {
+        None => Format::from_extension(filename).map_or_else(|| parse_format(None), Ok),
+        Some(_) => parse_format(format),
+    }
+}
+
+/// Write `value` to `writer` with `encoding`.
+fn write_value<W: Write, T: Serialize>(writer: &mut W, encoding: Encoding, value: &T) -> Result<()> {
+    match encoding {
+        Encoding::Json => serde_json::to_writer(writer, value).map_err(io::Error::from)?,
+        Encoding::Bincode => bincode::serialize_into(writer, value).map_err(io_error)?,
+        Encoding::MsgPack => rmp_serde::encode::write_named(writer, value).map_err(io_error)?,
+        Encoding::Cbor => serde_cbor::to_writer(writer, value).map_err(io_error)?,
+    }
     Ok(())
 }
 
-/// Load serializable object from file (`filename`) with `format` file format.
-pub fn load<T: DeserializeOwned>(what: &mut T, filename: &str, format: Option<&str>) -> PyResult<()> {
-    let file = File::open(filename)?;
-    let f = format.unwrap_or(DEFAULT_SERIALIZATION_FORMAT);
-     match f {
-        "json" => {
-            *what = serde_json::from_reader::<_, T>(file).unwrap();
+/// Self-describing envelope of model, see [`JsonEnvelope`] for reading.
+#[derive(Serialize)]
+struct Envelope<'a, T> {
+    magic: &'a str,
+    header: &'a ModelHeader,
+    model: &'a T,
+}
+
+/// Write envelope of `model` with `header` to `writer`.
+fn write_envelope<W: Write, T: Model>(mut writer: W, encoding: Encoding, header: &ModelHeader, model: &T)
+        -> Result<W> {
+    if encoding == Encoding::Json {
+        write_value(&mut writer, encoding, &Envelope { magic: MAGIC, header, model })?;
+    } else {
+        writer.write_all(MAGIC.as_bytes())?;
+        write_value(&mut writer, encoding, header)?;
+        write_value(&mut writer, encoding, model)?;
+    }
+    Ok(writer)
+}
+
+/// Write `model` with header and user `metadata` to `writer`, using `format` file format.
+/// 
+/// Returns the writer after compression is finished.
+fn write_model<W: Write, T: Model>(writer: W, model: &T, format: Format,
+                                   metadata: BTreeMap<String, String>) -> Result<W> {
+    let Format { encoding, compression } = format;
+    let header = ModelHeader::of(model, metadata);
+    Ok(match compression {
+        None => write_envelope(writer, encoding, &header, model)?,
+        Some(Compression::Gzip) => {
+            let encoder = GzEncoder::new(writer, flate2::Compression::default());
+            write_envelope(encoder, encoding, &header, model)?.finish()?
         },
-        "bincode" => {
-            *what = bincode::deserialize_from::<_, T>(file).unwrap();
+        Some(Compression::Zstd) => {
+            let encoder = zstd::Encoder::new(writer, ZSTD_LEVEL)?;
+            write_envelope(encoder, encoding, &header, model)?.finish()?
         },
-        _ => {
-            if SERIALIZATION_FORMATS.contains(&f) {
-                unimplemented!("Format `{}` deserialization", f);
+    })
+}
+
+/// Save `model` with header to file (`filename`), using `format` file format,
+/// inferred from the file extension by default.
+pub fn save<T: Model>(model: &T, filename: &str, format: Option<&str>) -> Result<()> {
+    save_with_metadata(model, filename, format, BTreeMap::new())
+}
+
+/// Save `model` with header and user `metadata` to file (`filename`), see [`save`].
+pub fn save_with_metadata<T: Model>(model: &T, filename: &str, format: Option<&str>,
+                                    metadata: BTreeMap<String, String>) -> Result<()> {
+    let format = saved_format(filename, format)?;
+    let file = BufWriter::new(File::create(filename)?);
+    write_model(file, model, format, metadata)?.flush()?;
+    Ok(())
+}
+
+/// Serialize `model` with header to bytes, using `format` file format.
+pub fn to_bytes<T: Model>(model: &T, format: Option<&str>) -> Result<Vec<u8>> {
+    to_bytes_with_metadata(model, format, BTreeMap::new())
+}
+
+/// Serialize `model` with header and user `metadata` to bytes, see [`to_bytes`].
+pub fn to_bytes_with_metadata<T: Model>(model: &T, format: Option<&str>,
+                                        metadata: BTreeMap<String, String>) -> Result<Vec<u8>> {
+    write_model(Vec::new(), model, parse_format(format)?, metadata)
+}
+
+/// JSON envelope with header only, the model is skipped.
+#[derive(Deserialize)]
+struct JsonHeader {
+    magic: String,
+    header: ModelHeader,
+    #[allow(dead_code)]
+    model: IgnoredAny,
+}
+
+/// Sequential reader of binary envelope parts.
+enum BinaryReader<R: Read> {
+    Bincode(R),
+    MsgPack(rmp_serde::Deserializer<rmp_serde::decode::ReadReader<R>>),
+    Cbor(serde_cbor::Deserializer<serde_cbor::de::IoRead<R>>),
+}
+
+impl<R: Read> BinaryReader<R> {
+    fn new(reader: R, encoding: Encoding) -> Self {
+        match encoding {
+            Encoding::Json | Encoding::Bincode => BinaryReader::Bincode(reader),
+            Encoding::MsgPack => BinaryReader::MsgPack(rmp_serde::Deserializer::new(reader)),
+            Encoding::Cbor => BinaryReader::Cbor(serde_cbor::Deserializer::from_reader(reader)),
+        }
+    }
+
+    /// Read the next value.
+    fn read<T: DeserializeOwned>(&mut self) -> Result<T> {
+        match self {
+            BinaryReader::Bincode(reader) => bincode::deserialize_from(reader).map_err(corrupted),
+            BinaryReader::MsgPack(de) => T::deserialize(de).map_err(corrupted),
+            BinaryReader::Cbor(de) => T::deserialize(de).map_err(corrupted),
+        }
+    }
+}
+
+/// Reader with already read bytes put back.
+type Rewound<R> = io::Chain<io::Cursor<Vec<u8>>, R>;
+
+/// Read up to `n` first bytes of `reader`.
+/// 
+/// Returns the bytes and the reader, that starts from the beginning.
+fn peek<R: Read>(mut reader: R, n: usize) -> Result<(Vec<u8>, Rewound<R>)> {
+    let mut prefix = Vec::with_capacity(n);
+    (&mut reader).take(n as u64).read_to_end(&mut prefix)?;
+    Ok((prefix.clone(), io::Cursor::new(prefix).chain(reader)))
+}
+
+/// Read [`MAGIC`] from the beginning of binary `reader`.
+/// 
+/// Returns whether it is found and the reader, that starts from the beginning if it is not found.
+fn read_magic<R: Read>(mut reader: R) -> Result<(bool, Rewound<R>)> {
+    let mut magic = Vec::with_capacity(MAGIC.len());
+    (&mut reader).take(MAGIC.len() as u64).read_to_end(&mut magic)?;
+    let has_magic = magic == MAGIC.as_bytes();
+    if has_magic {
+        magic.clear();
+    }
+    Ok((has_magic, io::Cursor::new(magic).chain(reader)))
+}
+
+/// Detect compression by magic bytes at the beginning of `prefix`.
+fn detect_compression(prefix: &[u8]) -> Option<Compression> {
+    if prefix.starts_with(GZIP_MAGIC) {
+        Some(Compression::Gzip)
+    } else if prefix.starts_with(ZSTD_MAGIC) {
+        Some(Compression::Zstd)
+    } else {
+        None
+    }
+}
+
+/// Detect encoding by the beginning of uncompressed `prefix`.
+/// 
+/// Header of binary envelope is a struct, so it starts with map marker in MessagePack and CBOR
+/// and with format version in bincode.
+fn detect_encoding(prefix: &[u8]) -> Encoding {
+    if prefix.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'{') {
+        return Encoding::Json;
+    }
+    match prefix.strip_prefix(MAGIC.as_bytes()).and_then(|rest| rest.first()) {
+        Some(0x80..=0x8f) | Some(0xde) | Some(0xdf) => Encoding::MsgPack,
+        Some(0xa0..=0xbf) => Encoding::Cbor,
+        _ => Encoding::Bincode,
+    }
+}
+
+/// Upgrade JSON `model` of `version` to [`FORMAT_VERSION`] with [`JSON_MIGRATIONS`].
+fn migrate_json(model: Value, version: u32) -> Result<Value> {
+    JSON_MIGRATIONS[version as usize..FORMAT_VERSION as usize]
+        .iter()
+        .try_fold(model, |model, migration| migration(model))
+}
+
+/// Reader of JSON envelope, that decodes the model directly after the header.
+/// 
+/// JSON values are built only for models of older versions, which are upgraded by [`migrate_json`]:
+/// raw models without envelope and envelopes of older format versions.
+/// Errors of header checks are kept in `error`, since deserialization errors are strings.
+struct JsonEnvelope<'e, T> {
+    error: &'e RefCell<Option<WoodsError>>,
+    model: PhantomData<T>,
+}
+
+impl<'e, T> JsonEnvelope<'e, T> {
+    /// Convert `err` to deserialization error and keep it.
+    fn fail<E: de::Error>(&self, err: WoodsError) -> E {
+        let message = err.to_string();
+        *self.error.borrow_mut() = Some(err);
+        E::custom(message)
+    }
+}
+
+impl<'de, 'e, T: Model> DeserializeSeed<'de> for JsonEnvelope<'e, T> {
+    type Value = (ModelHeader, T);
+
+    fn deserialize<De: de::Deserializer<'de>>(self, deserializer: De) -> std::result::Result<Self::Value, De::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, 'e, T: Model> Visitor<'de> for JsonEnvelope<'e, T> {
+    type Value = (ModelHeader, T);
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("model envelope")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<Self::Value, A::Error> {
+        let first_key: Option<String> = map.next_key()?;
+        if first_key.as_deref() != Some("magic") {
+            // raw model of version 0
+            let mut raw = serde_json::Map::new();
+            if let Some(key) = first_key {
+                raw.insert(key, map.next_value()?);
+            }
+            while let Some((key, value)) = map.next_entry()? {
+                raw.insert(key, value);
             }
-            return Err(UnknownFormatError::new(f).into());
+            let model: T = migrate_json(Value::Object(raw), 0)
+                .and_then(|model| serde_json::from_value(model).map_err(corrupted))
+                .map_err(|err| self.fail(err))?;
+            return Ok((ModelHeader::legacy(&model), model));
+        }
+        if map.next_value::<String>()? != MAGIC {
+            return Err(self.fail(WoodsError::CorruptedModel("wrong magic".into())));
         }
+        if map.next_key::<String>()?.as_deref() != Some("header") {
+            return Err(self.fail(WoodsError::CorruptedModel("model header is expected after magic".into())));
+        }
+        let header: ModelHeader = map.next_value()?;
+        header.check::<T>().map_err(|err| self.fail(err))?;
+        if map.next_key::<String>()?.as_deref() != Some("model") {
+            return Err(self.fail(WoodsError::CorruptedModel("model is expected after header".into())));
+        }
+        let model: T = if header.format_version == FORMAT_VERSION {
+            map.next_value()?
+        } else {
+            migrate_json(map.next_value()?, header.format_version)
+                .and_then(|model| serde_json::from_value(model).map_err(corrupted))
+                .map_err(|err| self.fail(err))?
+        };
+        while map.next_entry::<IgnoredAny, IgnoredAny>()?.is_some() {}
+        Ok((header, model))
+    }
+}
+
+/// Read JSON envelope or raw JSON model of version `0` from `reader`.
+fn read_json_envelope<R: Read, T: Model>(reader: R) -> Result<(ModelHeader, T)> {
+    let error = RefCell::new(None);
+    let mut deserializer = serde_json::Deserializer::from_reader(BufReader::new(reader));
+    let seed = JsonEnvelope { error: &error, model: PhantomData };
+    let envelope = seed.deserialize(&mut deserializer).and_then(|envelope| {
+        deserializer.end()?;
+        Ok(envelope)
+    });
+    envelope.map_err(|err| error.into_inner().unwrap_or_else(|| corrupted(err)))
+}
+
+/// Decode raw bincode model of version `0` of shape `L` as JSON value.
+fn legacy_value<R: Read, L: Serialize + DeserializeOwned>(reader: R) -> Result<Value> {
+    let model: L = bincode::deserialize_from(reader).map_err(corrupted)?;
+    serde_json::to_value(model).map_err(corrupted)
+}
+
+/// Read raw bincode model of version `0` and upgrade it with [`JSON_MIGRATIONS`].
+/// 
+/// Returns `UnsupportedVersion` error for model kinds, that older versions couldn't save.
+fn read_legacy_bincode<R: Read, T: Model>(reader: R) -> Result<T> {
+    let model = match model_kind::<T>().as_str() {
+        "DecisionTree" => legacy_value::<R, legacy::DecisionTree>(reader)?,
+        "GradientBoosting" => legacy_value::<R, legacy::GradientBoosting>(reader)?,
+        "DeepGradientBoosting" => legacy_value::<R, legacy::DeepGradientBoosting>(reader)?,
+        _ => return Err(WoodsError::UnsupportedVersion(0)),
     };
-    Ok(())
+    serde_json::from_value(migrate_json(model, 0)?).map_err(corrupted)
+}
+
+/// Read envelope from `reader` to `model`.
+fn read_envelope<R: Read, T: Model>(reader: R, encoding: Encoding, model: &mut T) -> Result<ModelHeader> {
+    if encoding == Encoding::Json {
+        let (header, json_model) = read_json_envelope(reader)?;
+        *model = json_model;
+        return Ok(header);
+    }
+    let (has_magic, reader) = read_magic(reader)?;
+    let mut reader = BinaryReader::new(reader, encoding);
+    if !has_magic {
+        *model = match reader {
+            BinaryReader::Bincode(reader) => read_legacy_bincode(reader)?,
+            mut reader => reader.read()?,
+        };
+        return Ok(ModelHeader::legacy(model));
+    }
+    let header: ModelHeader = reader.read()?;
+    header.check::<T>()?;
+    if header.format_version != FORMAT_VERSION {
+        return Err(WoodsError::UnsupportedVersion(header.format_version));
+    }
+    *model = reader.read()?;
+    Ok(header)
+}
+
+/// Read header from envelope in `reader`.
+fn read_envelope_header<R: Read>(reader: R, encoding: Encoding) -> Result<ModelHeader> {
+    let no_envelope = || WoodsError::CorruptedModel("file has no model header".into());
+    if encoding == Encoding::Json {
+        let envelope: JsonHeader = serde_json::from_reader(reader).map_err(|_| no_envelope())?;
+        if envelope.magic != MAGIC {
+            return Err(no_envelope());
+        }
+        return Ok(envelope.header);
+    }
+    let (has_magic, reader) = read_magic(reader)?;
+    if !has_magic {
+        return Err(no_envelope());
+    }
+    BinaryReader::new(reader, encoding).read()
+}
+
+/// Wrap `reader` with decompression.
+fn decompress<'a, R: Read + 'a>(reader: R, compression: Option<Compression>) -> Result<Box<dyn Read + 'a>> {
+    Ok(match compression {
+        None => Box::new(reader),
+        Some(Compression::Gzip) => Box::new(GzDecoder::new(reader)),
+        Some(Compression::Zstd) => Box::new(zstd::Decoder::new(reader)?),
+    })
+}
+
+/// Wrap `reader` with decompression of `format`, detecting the format by content if it is not set.
+/// 
+/// Returns encoding and decompressed reader.
+fn decode<'a, R: Read + 'a>(reader: R, format: Option<&str>) -> Result<(Encoding, Box<dyn Read + 'a>)> {
+    if let Some(format) = format {
+        let Format { encoding, compression } = format.parse()?;
+        return Ok((encoding, decompress(reader, compression)?));
+    }
+    let (prefix, reader) = peek(reader, ZSTD_MAGIC.len())?;
+    let reader = decompress(reader, detect_compression(&prefix))?;
+    let (prefix, reader) = peek(reader, MAGIC.len() + 1)?;
+    Ok((detect_encoding(&prefix), Box::new(reader)))
+}
+
+/// Open file (`filename`) for reading with decompression.
+fn open(filename: &str, format: Option<&str>) -> Result<(Encoding, Box<dyn Read>)> {
+    decode(BufReader::new(File::open(filename)?), format)
+}
+
+/// Load `model` from file (`filename`) with `format` file format, detected by content by default.
+/// 
+/// Returns header of the file. Model kind and format version are checked before decoding the model.
+pub fn load<T: Model>(model: &mut T, filename: &str, format: Option<&str>) -> Result<ModelHeader> {
+    let (encoding, reader) = open(filename, format)?;
+    read_envelope(reader, encoding, model)
+}
+
+/// Deserialize `model` from `bytes` with `format` file format, detected by content by default.
+/// 
+/// Returns header of the serialized model, see [`load`].
+pub fn from_bytes<T: Model>(model: &mut T, bytes: &[u8], format: Option<&str>) -> Result<ModelHeader> {
+    let (encoding, reader) = decode(bytes, format)?;
+    read_envelope(reader, encoding, model)
+}
+
+/// Read header of the model serialized to `bytes` with `format` file format without loading the model.
+/// 
+/// Format is detected by content by default, see [`read_header`].
+pub fn header_from_bytes(bytes: &[u8], format: Option<&str>) -> Result<ModelHeader> {
+    let (encoding, reader) = decode(bytes, format)?;
+    read_envelope_header(reader, encoding)
+}
+
+/// Read header of the model file (`filename`) with `format` file format without loading the model.
+/// 
+/// Format is detected by content by default.
+/// Returns `CorruptedModel` error for raw models without envelope.
+pub fn read_header(filename: &str, format: Option<&str>) -> Result<ModelHeader> {
+    let (encoding, reader) = open(filename, format)?;
+    read_envelope_header(reader, encoding)
 }
@@ -0,0 +1,156 @@
+//! Sparse feature-major matrix for high-dimensional sparse features, e.g. one-hot or TF-IDF.
+//!
+//! [`SparseColumns`] stores non-zero values of each feature (compressed rows of `(n_features, n_samples)` matrix),
+//! which is the same memory layout as CSC matrix of samples with shape `(n_samples, n_features)`.
+//! Zero values are implicit: split rules and predictions don't iterate over them.
+
+use ndarray::{ArrayView2, Array2};
+use crate::utils::numerics::{D, Float};
+use crate::error::{Result, WoodsError};
+
+/// Sparse matrix with shape `(n_features, n_samples)`, only non-zero values are stored.
+#[derive(Debug, Clone)]
+pub struct SparseColumns<F = D> {
+    n_samples: usize,
+    /// Values of feature `i` are `values[indptr[i]..indptr[i + 1]]`.
+    indptr: Vec<usize>,
+    /// Sample indices of values, strictly increasing for each feature.
+    indices: Vec<usize>,
+    values: Vec<F>,
+}
+
+impl<F: Float> SparseColumns<F> {
+    /// Make matrix from CSC representation of samples matrix with `n_samples` rows, e.g. `scipy.sparse.csc_matrix`:
+    /// `indptr` has `n_features + 1` elements, `indices` are sample indices of `values`.
+    ///
+    /// Values of each feature are sorted by sample index, duplicate indices are not allowed.
+    pub fn from_csc(n_samples: usize, indptr: Vec<usize>, indices: Vec<usize>, values: Vec<F>) -> Result<Self> {
+        check_compressed(&indptr, &indices, &values, n_samples)?;
+        let mut matrix = SparseColumns { n_samples, indptr, indices, values };
+        matrix.sort_indices()?;
+        Ok(matrix)
+    }
+
+    /// Make matrix from CSR representation of samples matrix with `n_features` columns, e.g. `scipy.sparse.csr_matrix`:
+    /// `indptr` has `n_samples + 1` elements, `indices` are feature indices of `values`.
+    pub fn from_csr(n_features: usize, indptr: Vec<usize>, indices: Vec<usize>, values: Vec<F>) -> Result<Self> {
+        check_compressed(&indptr, &indices, &values, n_features)?;
+        let n_samples = indptr.len() - 1;
+        let mut counts = vec![0usize; n_features + 1];
+        for feature in &indices {
+            counts[feature + 1] += 1;
+        }
+        for i in 0..n_features {
+            counts[i + 1] += counts[i];
+        }
+        let mut next = counts.clone();
+        let mut sample_indices = vec![0; indices.len()];
+        let mut feature_values = vec![F::default(); values.len()];
+        for sample in 0..n_samples {
+            for k in indptr[sample]..indptr[sample + 1] {
+                let position = &mut next[indices[k]];
+                sample_indices[*position] = sample;
+                feature_values[*position] = values[k];
+                *position += 1;
+            }
+        }
+        let mut matrix = SparseColumns { n_samples, indptr: counts, indices: sample_indices, values: feature_values };
+        matrix.sort_indices()?;
+        Ok(matrix)
+    }
+
+    /// Make matrix from dense `columns` with shape `(n_features, n_samples)`, zeros are dropped.
+    pub fn from_dense(columns: &ArrayView2<'_, F>) -> Self {
+        let mut matrix = SparseColumns { n_samples: columns.dim().1, indptr: vec![0], indices: vec![], values: vec![] };
+        for column in columns.outer_iter() {
+            for (sample, value) in column.iter().enumerate().filter(|(_, v)| **v != F::default()) {
+                matrix.indices.push(sample);
+                matrix.values.push(*value);
+            }
+            matrix.indptr.push(matrix.indices.len());
+        }
+        matrix
+    }
+
+    /// Dense matrix with shape `(n_features, n_samples)`.
+    pub fn to_dense(&self) -> Array2<F> {
+        let mut columns = Array2::default(self.dim());
+        for feature in 0..self.n_features() {
+            let (samples, values) = self.feature(feature);
+            for (sample, value) in samples.iter().zip(values) {
+                columns[[feature, *sample]] = *value;
+            }
+        }
+        columns
+    }
+
+    /// Shape `(n_features, n_samples)`.
+    pub fn dim(&self) -> (usize, usize) {
+        (self.n_features(), self.n_samples)
+    }
+
+    pub fn n_features(&self) -> usize {
+        self.indptr.len() - 1
+    }
+
+    pub fn n_samples(&self) -> usize {
+        self.n_samples
+    }
+
+    /// Number of stored values.
+    pub fn nnz(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Stored values of all features.
+    pub fn values(&self) -> &[F] {
+        &self.values
+    }
+
+    /// Sample indices (increasing) and values of non-zero elements of `feature`.
+    pub fn feature(&self, feature: usize) -> (&[usize], &[F]) {
+        let range = self.indptr[feature]..self.indptr[feature + 1];
+        (&self.indices[range.clone()], &self.values[range])
+    }
+
+    /// Value of `feature` of `sample`, it is found by binary search.
+    pub fn get(&self, feature: usize, sample: usize) -> F {
+        let (samples, values) = self.feature(feature);
+        samples.binary_search(&sample).map_or_else(|_| F::default(), |k| values[k])
+    }
+
+    /// Sort values of each feature by sample index and check that indices are unique.
+    fn sort_indices(&mut self) -> Result<()> {
+        for feature in 0..self.n_features() {
+            let range = self.indptr[feature]..self.indptr[feature + 1];
+            let indices = &self.indices[range.clone()];
+            if indices.windows(2).all(|w| w[0] < w[1]) {
+                continue;
+            }
+            let mut entries: Vec<(usize, F)> = indices.iter().copied().zip(self.values[range.clone()].iter().copied()).collect();
+            entries.sort_by_key(|e| e.0);
+            if entries.windows(2).any(|w| w[0].0 == w[1].0) {
+                return Err(WoodsError::InvalidData(format!("duplicate sample indices of feature {}", feature)));
+            }
+            for (k, (sample, value)) in range.zip(entries) {
+                self.indices[k] = sample;
+                self.values[k] = value;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Check compressed sparse structure with minor indices less than `n_minor`.
+fn check_compressed<F>(indptr: &[usize], indices: &[usize], values: &[F], n_minor: usize) -> Result<()> {
+    if indices.len() != values.len() {
+        return Err(WoodsError::ShapeMismatch { what: "sparse indices", expected: values.len(), found: indices.len() });
+    }
+    if indptr.first() != Some(&0) || indptr.windows(2).any(|w| w[0] > w[1]) || indptr.last() != Some(&indices.len()) {
+        return Err(WoodsError::InvalidData("sparse `indptr` must increase from 0 to the number of values".into()));
+    }
+    if let Some(index) = indices.iter().find(|i| **i >= n_minor) {
+        return Err(WoodsError::InvalidData(format!("sparse index {} is out of range", index)));
+    }
+    Ok(())
+}
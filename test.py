@@ -1,13 +1,19 @@
 import numpy as np
 import woods
 from sklearn.datasets import make_regression, make_friedman2
-from sklearn.metrics import mean_squared_error
+from sklearn.metrics import mean_absolute_error, mean_squared_error, r2_score, roc_auc_score
 from sklearn.ensemble import GradientBoostingRegressor
 from sklearn.tree import DecisionTreeRegressor
-from sklearn.model_selection import GridSearchCV, train_test_split
-from sklearn.base import BaseEstimator, RegressorMixin
+from sklearn.model_selection import GridSearchCV, cross_val_score, train_test_split
+from sklearn.base import clone
+from sklearn.pipeline import make_pipeline
+from sklearn.preprocessing import StandardScaler
+from scipy import sparse
 from time import time
 import os
+import pickle
+import shutil
+import subprocess
 
 
 print("Check DecisionRule:")
@@ -39,7 +45,8 @@ print(f"Make temporary models directory: {models_dir}")
 os.makedirs(models_dir, exist_ok=True)
 dt_filename = os.path.join(models_dir, "test_dt.json")
 print(f"Check DecisionTree.save('{dt_filename}')")
-dt.save(dt_filename)
+dt.save(dt_filename, metadata={"dataset": "test"})
+print("  header:", woods.read_header(dt_filename))
 
 print(f"Check DecisionTree.load('{dt_filename}')")
 loaded_dt = woods.DecisionTree()
@@ -47,6 +54,16 @@ loaded_dt.load(dt_filename)
 print("  woods loaded tree predictions:", loaded_dt.predict(X))
 print("    ", mean_squared_error(y, loaded_dt.predict(X)))
 
+print("Check pickle.loads(pickle.dumps(DecisionTree))")
+pickled_dt = pickle.loads(pickle.dumps(dt))
+print("    ", mean_squared_error(y, pickled_dt.predict(X)))
+
+print("Check DecisionTree.dump_text()")
+print(dt.dump_text(precision=2))
+dot_filename = os.path.join(models_dir, "test_dt.dot")
+print(f"Check DecisionTree.to_dot('{dot_filename}')")
+dt.to_dot(dot_filename)
+
 print("Check GradientBoosting")
 params = dict(depth=5,
               min_samples_split=2,
@@ -57,6 +74,59 @@ gbm.fit(X, y)
 print(y)
 print("  woods gbm predictions:", gbm.predict(X))
 print("  ", mean_squared_error(y, gbm.predict(X)))
+print("  the last tree:")
+print(gbm.dump_text(params["n_estimators"] - 1, precision=2))
+
+print("Check input layouts and dtypes")
+preds = gbm.predict(X)
+assert np.array_equal(gbm.predict(np.asfortranarray(X)), preds)
+assert np.array_equal(gbm.predict(X[::-1])[::-1], preds)
+assert np.array_equal(gbm.predict(X.astype(np.int64)), preds)
+assert np.array_equal(gbm.predict(X.tolist()), preds)
+dataset = woods.Dataset(X, y, feature_names=[f"f{i}" for i in range(X.shape[1])])
+print(f"  dataset: {dataset.n_samples} samples, {dataset.n_features} features")
+dataset_gbm = woods.GradientBoosting(**params).fit(dataset)
+print("  feature names of Dataset:", dataset_gbm.feature_names_in_)
+assert np.array_equal(dataset_gbm.predict(dataset), dataset_gbm.predict(X))
+try:
+    import pandas as pd
+    df = pd.DataFrame(X, columns=[f"f{i}" for i in range(X.shape[1])])
+    print("  feature names of DataFrame:", woods.DecisionTree(depth=3).fit(df, y).feature_names_in_)
+    assert np.array_equal(gbm.predict(df), preds)
+except ImportError:
+    print("  pandas is not installed, skip DataFrame check")
+
+print("Check dtype=\"float32\"")
+gbm32 = woods.GradientBoosting(**params, dtype="float32").fit(X, y)
+preds32 = gbm32.predict(X)
+print("  float32 gbm predictions:", preds32)
+assert preds32.dtype == np.float32 and gbm32.dtype == "float32"
+assert gbm32.get_params()["dtype"] == "float32"
+assert np.allclose(preds32, preds, rtol=1e-4, atol=1e-4)
+gbm32_filename = os.path.join(models_dir, "test_gbm32.bin")
+gbm32.save(gbm32_filename)
+print("  header:", woods.read_header(gbm32_filename))
+loaded_gbm32 = woods.GradientBoosting()
+loaded_gbm32.load(gbm32_filename)
+assert loaded_gbm32.dtype == "float32"
+assert np.array_equal(loaded_gbm32.predict(X), preds32)
+assert np.array_equal(pickle.loads(pickle.dumps(gbm32)).predict(X), preds32)
+dataset32 = woods.Dataset(X, y, dtype=np.float32)
+assert np.array_equal(gbm32.predict(dataset32), preds32)
+assert woods.DeepGradientBoosting(n_estimators=2, dtype="float32").fit(dataset).predict(X).dtype == np.float32
+
+print("Check scipy.sparse input")
+X_sparse = sparse.random(200, 1000, density=0.01, format="csr", random_state=0)
+y_sparse = X_sparse[:, 0].toarray().ravel() * 10 + X_sparse[:, 1].toarray().ravel()
+gbm_sparse = woods.GradientBoosting(**params).fit(X_sparse, y_sparse)
+preds_sparse = gbm_sparse.predict(X_sparse)
+print("  sparse gbm MSE:", mean_squared_error(y_sparse, preds_sparse))
+assert np.array_equal(gbm_sparse.predict(X_sparse.toarray()), preds_sparse)
+assert np.array_equal(gbm_sparse.predict(X_sparse.tocsc()), preds_sparse)
+assert gbm_sparse.n_features_in_ == 1000
+tree_sparse = woods.DecisionTree(dtype="float32").fit(X_sparse.tocoo(), y_sparse)
+assert tree_sparse.predict(X_sparse).dtype == np.float32
+print("  sparse tree score:", tree_sparse.score(X_sparse, y_sparse))
 
 n_experiments = 10
 n_repeats = 10
@@ -92,34 +162,34 @@ for j in range(n_experiments):
 print(f"    {np.mean(gbm_times)} +- {np.std(gbm_times)}")
 
 
-print("Check WoodsGBMRegressor")
-class WoodsGBMRegressor(BaseEstimator, RegressorMixin):
-    def __init__(self, n_estimators=100, max_depth=3, min_samples_split=2, learning_rate=0.1):
-        self.n_estimators = n_estimators
-        self.max_depth = max_depth
-        self.min_samples_split = min_samples_split
-        self.learning_rate = learning_rate
-
-    def fit(self, X, y):
-        params = dict(
-            depth=self.max_depth,
-            min_samples_split=self.min_samples_split,
-            n_estimators=self.n_estimators,
-            learning_rate=self.learning_rate,
-        )
-        self.est_ = woods.GradientBoosting(**params)
-        self.est_.fit(X, y)
+print("Check scikit-learn estimator protocol")
+print("  ", repr(gbm), gbm.get_params())
+assert clone(gbm).get_params() == gbm.get_params()
+pipeline = make_pipeline(StandardScaler(), woods.GradientBoosting(n_estimators=10))
+print("  pipeline r^2 score:", pipeline.fit(X_train, y_train).score(X_test, y_test))
+print("  cross-validation r^2 scores:", cross_val_score(woods.DecisionTree(depth=5), X_train, y_train, cv=3))
 
-    def predict(self, X):
-        return self.est_.predict(X)
+print("Check score metrics")
+gbm_test_preds = gbm.predict(X_test)
+assert np.isclose(gbm.score(X_test, y_test), r2_score(y_test, gbm_test_preds))
+assert np.isclose(gbm.score(X_test, y_test, metric="mse"), mean_squared_error(y_test, gbm_test_preds))
+assert np.isclose(gbm.score(X_test, y_test, metric="mae"), mean_absolute_error(y_test, gbm_test_preds))
+weights = np.linspace(0.5, 1.5, len(y_test))
+assert np.isclose(gbm.score(X_test, y_test, sample_weight=weights, metric="rmse"),
+                  np.sqrt(mean_squared_error(y_test, gbm_test_preds, sample_weight=weights)))
+for metric in ["mape", "quantile@0.9", "auc", "ndcg@10"]:
+    print(f"  {metric}:", gbm.score(X_test, y_test, metric=metric))
+y_binary = (y_test > np.median(y_test)).astype(np.double)
+assert np.isclose(gbm.score(X_test, y_binary, metric="auc"), roc_auc_score(y_binary, gbm_test_preds))
 
+print("Check GridSearchCV(GradientBoosting)")
 grid = {
     'n_estimators': [100, 1000],
-    'max_depth': [2, 3, 4, 5, 7, 8, 9],
+    'depth': [2, 3, 4, 5, 7, 8, 9],
     'learning_rate': [0.1, 0.01]
 }
 
-model = GridSearchCV(WoodsGBMRegressor(), grid,
+model = GridSearchCV(woods.GradientBoosting(), grid,
                         scoring='neg_mean_squared_error',
                         n_jobs=-1)
 model.fit(X_train, y_train)
@@ -128,8 +198,8 @@ print("    ", mean_squared_error(y_test, model.predict(X_test)))
 print(f"  best parameters: {model.best_params_}")
 
 gbm_filename = os.path.join(models_dir, "test_gbm.json")
-print(f"Check WoodsGBMRegressor.best_estimator_.est_.save('{gbm_filename}')")
-model.best_estimator_.est_.save(gbm_filename)
+print(f"Check GridSearchCV.best_estimator_.save('{gbm_filename}')")
+model.best_estimator_.save(gbm_filename)
 
 print(f"Check GradientBoosting.load('{gbm_filename}')")
 loaded_gbm = woods.GradientBoosting()
@@ -137,6 +207,60 @@ loaded_gbm.load(gbm_filename)
 print("  best loaded gbm predictions:")
 print("    ", mean_squared_error(y_test, loaded_gbm.predict(X_test)))
 
+onnx_filename = os.path.join(models_dir, "test_gbm.onnx")
+print(f"Check GradientBoosting.to_onnx('{onnx_filename}')")
+loaded_gbm.to_onnx(onnx_filename)
+try:
+    import onnxruntime
+except ImportError:
+    # onnxruntime is in requirements-dev.txt, set `CI` to make the check required
+    if os.environ.get("CI"):
+        raise
+    onnxruntime = None
+    print("  onnxruntime is not installed, skip predictions check")
+if onnxruntime is not None:
+    session = onnxruntime.InferenceSession(onnx_filename)
+    onnx_preds = session.run(None, {"X": X_test.astype(np.float32)})[0].ravel()
+    print("  max abs difference of onnxruntime predictions:",
+          np.abs(onnx_preds - loaded_gbm.predict(X_test)).max())
+    assert np.allclose(onnx_preds, loaded_gbm.predict(X_test), rtol=1e-4, atol=1e-4)
+
+xgboost_filename = os.path.join(models_dir, "test_gbm_xgboost.json")
+print(f"Check GradientBoosting.to_xgboost_json('{xgboost_filename}')")
+loaded_gbm.to_xgboost_json(xgboost_filename)
+imported = woods.TreeEnsemble.from_xgboost(xgboost_filename)
+print("  max abs difference of imported model predictions:",
+      np.abs(imported.predict(X_test) - loaded_gbm.predict(X_test)).max())
+assert np.allclose(imported.predict(X_test), loaded_gbm.predict(X_test))
+
+code_dir = os.path.join(models_dir, "code")
+print(f"Check GradientBoosting.export_code() compiled in '{code_dir}'")
+os.makedirs(code_dir, exist_ok=True)
+loaded_gbm.export_code("rust", os.path.join(code_dir, "model.rs"))
+harness = """include!("model.rs");
+
+fn main() {
+    use std::io::BufRead;
+    for line in std::io::stdin().lock().lines() {
+        let x: Vec<f64> = line.unwrap().split(',').map(|v| v.parse().unwrap()).collect();
+        println!("{:?}", predict(&x));
+    }
+}
+"""
+with open(os.path.join(code_dir, "main.rs"), "w") as f:
+    f.write(harness)
+if shutil.which("rustc"):
+    binary = os.path.join(code_dir, "model")
+    subprocess.run(["rustc", "-O", "-o", binary, os.path.join(code_dir, "main.rs")], check=True)
+    samples = "\n".join(",".join(repr(v) for v in row) for row in X_test.tolist())
+    output = subprocess.run([binary], input=samples, capture_output=True, text=True, check=True).stdout
+    code_preds = np.array([float(v) for v in output.split()])
+    print("  number of different predictions of compiled code:",
+          np.sum(code_preds != loaded_gbm.predict(X_test)))
+    assert np.array_equal(code_preds, loaded_gbm.predict(X_test))
+else:
+    print("  rustc is not installed, skip compiled code check")
+
 print("Check DeepGradientBoosting")
 cb = woods.DeepGradientBoosting(n_estimators=5)
 
@@ -163,16 +287,28 @@ for j in range(n_experiments):
     cb_times.append(end - start)
 print(f"    {np.mean(cb_times)} +- {np.std(cb_times)}")
 
-cb_filename = os.path.join(models_dir, "test_cb.bin")
+cb_filename = os.path.join(models_dir, "test_cb.bin.zst")
 print(f"Check DeepGradientBoosting.save({cb_filename})")
-cb.save(cb_filename, format='bincode')
+cb.save(cb_filename, format="bincode+zstd")
 
 print(f"Check DeepGradientBoosting.load('{cb_filename}')")
 loaded_cb = woods.DeepGradientBoosting()
-loaded_cb.load(cb_filename, format='bincode')
+loaded_cb.load(cb_filename)
 print("  best loaded deep gbm predictions:")
 print("    ", mean_squared_error(y_test, loaded_cb.predict(X_test)))
 
+print("Check DeepGradientBoosting layer parameters search")
+searched_cb = woods.DeepGradientBoosting(n_estimators=2, search_depth=[2, 3], search_max_features=[1, 3],
+                                         search_n_estimators=[50], search_learning_rate=[0.1], cv=3,
+                                         search_metric="mae")
+print("  searched layers MSE:", mean_squared_error(y_test, searched_cb.fit(X_train, y_train).predict(X_test)))
+assert clone(searched_cb).get_params() == searched_cb.get_params()
+fixed_cb = woods.DeepGradientBoosting(n_estimators=2, layer_params={"depth": 3, "max_features": 3, "n_estimators": 50})
+print("  fixed layers MSE:", mean_squared_error(y_test, fixed_cb.fit(X_train, y_train).predict(X_test)))
+assert fixed_cb.get_params()["layer_params"]["learning_rate"] == 0.1
+assert fixed_cb.get_params()["layer_params"]["max_features"] == 3
+assert fixed_cb.set_params(layer_params=None).get_params()["layer_params"] is None
+
 
 
 print("Scikit-learn GradientBoostingRegressor:")
@@ -0,0 +1,81 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// Make empty directory for files of test `name`.
+fn work_dir(name: &str) -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("cli").join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Write CSV with features `a`, `b` and target `y`, that depends on `a` only.
+fn write_csv(path: &Path, n_samples: usize) {
+    let mut csv = String::from("a,b,y\n");
+    for i in 0..n_samples {
+        let a = (i % 10) as f64;
+        csv.push_str(&format!("{},{},{}\n", a, (i * 7 % 13) as f64, if a < 5.0 { 0.0 } else { 10.0 }));
+    }
+    fs::write(path, csv).unwrap();
+}
+
+fn woods(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_woods")).current_dir(dir).args(args).output().unwrap()
+}
+
+fn success(output: Output) -> String {
+    assert!(output.status.success(), "woods failed: {}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn failure(output: Output) -> String {
+    assert!(!output.status.success(), "woods succeeded: {}", String::from_utf8_lossy(&output.stdout));
+    String::from_utf8(output.stderr).unwrap()
+}
+
+#[test]
+fn train_predict_cv() {
+    let dir = work_dir("train_predict_cv");
+    write_csv(&dir.join("train.csv"), 100);
+
+    success(woods(&dir, &["train", "--data", "train.csv", "--target", "y", "--model", "gbm",
+                          "--depth", "2", "--max-features", "2", "--n-estimators", "100", "--output", "model.json"]));
+    assert!(dir.join("model.json").exists());
+
+    success(woods(&dir, &["predict", "--model", "model.json", "--data", "train.csv", "--target", "y",
+                          "--output", "predictions.csv"]));
+    let predictions: Vec<f64> = fs::read_to_string(dir.join("predictions.csv")).unwrap()
+        .lines()
+        .skip(1)
+        .map(|line| line.parse().unwrap())
+        .collect();
+    assert_eq!(predictions.len(), 100);
+    for (i, prediction) in predictions.iter().enumerate() {
+        let expected = if i % 10 < 5 { 0.0 } else { 10.0 };
+        assert!((prediction - expected).abs() < 2.5, "prediction of sample {} is {}", i, prediction);
+    }
+
+    // features are selected by names: extra columns are ignored, columns may be reordered
+    fs::write(dir.join("reordered.csv"), "id,b,a\n0,1,2\n1,3,8\n").unwrap();
+    let stdout = success(woods(&dir, &["predict", "--model", "model.json", "--data", "reordered.csv"]));
+    let predictions: Vec<f64> = stdout.lines().skip(1).map(|line| line.parse().unwrap()).collect();
+    assert!(predictions[0] < 5.0 && predictions[1] > 5.0, "{:?}", predictions);
+
+    let stdout = success(woods(&dir, &["cv", "--data", "train.csv", "--target", "y", "--model", "tree",
+                                       "--depth", "2", "--folds", "3", "--split", "shuffle", "--seed", "0",
+                                       "--metric", "mae"]));
+    assert_eq!(stdout.lines().filter(|line| line.starts_with("Fold ")).count(), 3);
+    assert!(stdout.contains("Cross-validation mae (3 folds): "), "{}", stdout);
+}
+
+#[test]
+fn predict_without_model_features() {
+    let dir = work_dir("predict_without_model_features");
+    write_csv(&dir.join("train.csv"), 50);
+    success(woods(&dir, &["train", "--data", "train.csv", "--target", "y", "--model", "tree",
+                          "--output", "model.bin"]));
+    fs::write(dir.join("test.csv"), "a,c\n1,2\n").unwrap();
+    let stderr = failure(woods(&dir, &["predict", "--model", "model.bin", "--data", "test.csv"]));
+    assert!(stderr.contains("Model features are missing in data: b"), "{}", stderr);
+}
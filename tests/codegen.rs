@@ -0,0 +1,115 @@
+mod common;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use ndarray::{Array2, Axis};
+use woods::ensemble::boosting::GradientBoostingParameters;
+use woods::estimator::{ConstructibleWithCopyArg, Estimator};
+use woods::export::codegen::{to_code, AsTreeSum, Language};
+use woods::tree::TreeParameters;
+use common::{samples, Gbm, Tree};
+
+/// Rust program, that predicts samples from stdin (one per line) with the generated `model.rs`.
+const RUST_MAIN: &str = r#"
+mod model { include!("model.rs"); }
+
+fn main() {
+    let input = std::io::read_to_string(std::io::stdin()).unwrap();
+    for line in input.lines() {
+        let x: Vec<f64> = line.split_whitespace().map(|v| v.parse().unwrap()).collect();
+        assert_eq!(x.len(), model::N_FEATURES);
+        println!("{:?}", model::predict(&x));
+    }
+}
+"#;
+
+/// C program, that predicts samples from stdin (one per line) with the generated `model.c`.
+const C_MAIN: &str = r#"
+#include <stdio.h>
+#include "model.c"
+
+int main(void) {
+    double x[N_FEATURES];
+    for (;;) {
+        for (int i = 0; i < N_FEATURES; i++) {
+            if (scanf("%lf", &x[i]) != 1) {
+                return 0;
+            }
+        }
+        printf("%.17g\n", predict(x));
+    }
+}
+"#;
+
+/// Make empty directory for the generated program `name`.
+fn work_dir(name: &str) -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("codegen").join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn run(command: &mut Command) {
+    let output = command.output().unwrap_or_else(|err| panic!("failed to run {:?}: {}", command, err));
+    assert!(output.status.success(), "{:?} failed: {}", command, String::from_utf8_lossy(&output.stderr));
+}
+
+/// Compile generated `language` code of `model` and predict `columns` with it.
+fn predict_compiled<M: AsTreeSum>(model: &M, language: Language, name: &str, columns: &Array2<f64>) -> Vec<f64> {
+    let dir = work_dir(name);
+    let binary = dir.join("predict");
+    match language {
+        Language::Rust => {
+            fs::write(dir.join("model.rs"), to_code(model, language).unwrap()).unwrap();
+            fs::write(dir.join("main.rs"), RUST_MAIN).unwrap();
+            let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".into());
+            run(Command::new(rustc).args(["--edition", "2018", "-O", "-o"]).arg(&binary).arg(dir.join("main.rs")));
+        },
+        Language::C => {
+            fs::write(dir.join("model.c"), to_code(model, language).unwrap()).unwrap();
+            fs::write(dir.join("main.c"), C_MAIN).unwrap();
+            let cc = env::var("CC").unwrap_or_else(|_| "cc".into());
+            run(Command::new(cc).args(["-O2", "-ffp-contract=off", "-o"]).arg(&binary).arg(dir.join("main.c")).arg("-lm"));
+        },
+    }
+
+    let input: String = columns.axis_iter(Axis(1))
+        .map(|x| x.iter().map(|v| format!("{:?}", v)).collect::<Vec<_>>().join(" ") + "\n")
+        .collect();
+    fs::write(dir.join("input.txt"), input).unwrap();
+    let output = Command::new(&binary).stdin(fs::File::open(dir.join("input.txt")).unwrap()).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap().lines().map(|line| line.parse().unwrap()).collect()
+}
+
+/// Check that generated code of both languages predicts exactly like `model`.
+fn check_compiled<M: AsTreeSum + Estimator<F = f64>>(model: &M, name: &str, columns: &Array2<f64>) {
+    let predictions = model.predict(&columns.view()).unwrap().to_vec();
+    for &(language, suffix) in &[(Language::Rust, "rust"), (Language::C, "c")] {
+        let compiled = predict_compiled(model, language, &format!("{}_{}", name, suffix), columns);
+        assert_eq!(compiled.len(), predictions.len());
+        for (i, (c, p)) in compiled.iter().zip(&predictions).enumerate() {
+            assert_eq!(c.to_bits(), p.to_bits(), "{:?} prediction of sample {}: {} != {}", language, i, c, p);
+        }
+    }
+}
+
+#[test]
+fn compiled_tree_predictions_are_equal() {
+    let (columns, target) = samples(4, 200);
+    let mut tree = Tree::new(TreeParameters::new(Some(6), None));
+    tree.fit(&columns.view(), &target.view()).unwrap();
+    check_compiled(&tree, "tree", &columns);
+}
+
+#[test]
+fn compiled_gbm_predictions_are_equal() {
+    let (columns, target) = samples(4, 200);
+    // enough trees for pairwise and sequential sums to differ
+    let mut gbm = Gbm::new(GradientBoostingParameters::new(TreeParameters::new(Some(3), None), Some(100), Some(0.1)));
+    gbm.fit(&columns.view(), &target.view()).unwrap();
+    let (test_columns, _) = samples(4, 300);
+    check_compiled(&gbm, "gbm", &test_columns);
+}
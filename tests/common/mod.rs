@@ -0,0 +1,23 @@
+//! Model aliases and samples shared by integration tests.
+// every test crate compiles this module, but uses only a part of it
+#![allow(dead_code)]
+
+use ndarray::{Array1, Array2, Axis};
+use woods::ensemble::AverageEnsemble;
+use woods::ensemble::boosting::TreeGBM;
+use woods::ensemble::deep_boosting::DeepBoostingImpl;
+use woods::tree::DecisionTreeImpl;
+use woods::tree::rule::RandomSplitRule;
+
+pub type Tree = DecisionTreeImpl<RandomSplitRule>;
+pub type Gbm = TreeGBM<f64>;
+pub type DeepGbm = DeepBoostingImpl<AverageEnsemble<Gbm>>;
+
+/// Samples with `n_features` deterministic features, target is a nonlinear function of all of them.
+pub fn samples(n_features: usize, n_samples: usize) -> (Array2<f64>, Array1<f64>) {
+    let columns = Array2::from_shape_fn((n_features, n_samples), |(i, j)| ((j * (3 + 5 * i)) % 23) as f64 / 4.0);
+    let target = columns.map_axis(Axis(0), |x| {
+        x.iter().enumerate().map(|(i, v)| if i % 2 == 0 { v * v } else { -v.sin() }).sum()
+    });
+    (columns, target)
+}
@@ -0,0 +1,107 @@
+use ndarray::{arr1, arr2};
+use woods::data::{read_csv, read_libsvm, Column, ColumnRoles, CsvOptions, LibSvmOptions};
+use woods::error::WoodsError;
+
+fn csv_options(has_header: bool, target: Option<Column>, group: Option<Column>) -> CsvOptions {
+    CsvOptions {
+        has_header,
+        roles: ColumnRoles { target, group, ..ColumnRoles::default() },
+        ..CsvOptions::default()
+    }
+}
+
+#[test]
+fn csv_with_header_target_and_group_by_name() {
+    let csv = "a,q,y,b\n1,x,10,2\n3,z,20,4\n5,x,30,6\n";
+    let dataset = read_csv(csv.as_bytes(), &csv_options(true, Some("y".into()), Some("q".into()))).unwrap();
+    assert_eq!(dataset.columns, arr2(&[[1.0, 3.0, 5.0], [2.0, 4.0, 6.0]]));
+    assert_eq!(dataset.target, Some(arr1(&[10.0, 20.0, 30.0])));
+    assert_eq!(dataset.groups, Some(arr1(&[0, 1, 0])));
+    assert_eq!(dataset.feature_names, Some(vec!["a".to_string(), "b".to_string()]));
+    assert!(dataset.weights.is_none());
+}
+
+#[test]
+fn csv_without_header_target_and_group_by_index() {
+    let csv = "1,x,10,2\n3,z,20,4\n5,x,30,6\n";
+    let dataset = read_csv(csv.as_bytes(), &csv_options(false, Some(2.into()), Some(1.into()))).unwrap();
+    assert_eq!(dataset.columns, arr2(&[[1.0, 3.0, 5.0], [2.0, 4.0, 6.0]]));
+    assert_eq!(dataset.target, Some(arr1(&[10.0, 20.0, 30.0])));
+    assert_eq!(dataset.groups, Some(arr1(&[0, 1, 0])));
+    assert!(dataset.feature_names.is_none());
+
+    // the header line is data without `has_header`
+    let csv = "a,y\n1,2\n";
+    assert!(matches!(read_csv(csv.as_bytes(), &csv_options(false, None, None)), Err(WoodsError::InvalidData(_))));
+    // names can't be resolved without header
+    let csv = "1,2\n";
+    assert!(matches!(read_csv(csv.as_bytes(), &csv_options(false, Some("y".into()), None)),
+                     Err(WoodsError::InvalidData(_))));
+}
+
+#[test]
+fn csv_target_and_group_by_index_with_header() {
+    let csv = "a,y\n1,2\n3,4\n";
+    let dataset = read_csv(csv.as_bytes(), &csv_options(true, Some(1.into()), None)).unwrap();
+    assert_eq!(dataset.target, Some(arr1(&[2.0, 4.0])));
+    assert_eq!(dataset.feature_names, Some(vec!["a".to_string()]));
+    assert!(matches!(read_csv(csv.as_bytes(), &csv_options(true, Some(2.into()), None)),
+                     Err(WoodsError::InvalidData(_))));
+    assert!(matches!(read_csv(csv.as_bytes(), &csv_options(true, Some("z".into()), None)),
+                     Err(WoodsError::InvalidData(_))));
+}
+
+#[test]
+fn csv_missing_values_are_nan() {
+    let csv = "a,b,c,d\n,NA,NaN, 1.5 \n";
+    let dataset = read_csv(csv.as_bytes(), &CsvOptions::default()).unwrap();
+    let values = dataset.columns.column(0).to_vec();
+    assert!(values[..3].iter().all(|v| v.is_nan()), "{:?}", values);
+    assert_eq!(values[3], 1.5);
+
+    let csv = "a\nmissing\n";
+    assert!(matches!(read_csv(csv.as_bytes(), &CsvOptions::default()), Err(WoodsError::InvalidData(_))));
+}
+
+#[test]
+fn csv_ragged_row_is_error() {
+    let csv = "a,b\n1,2\n3\n";
+    assert!(matches!(read_csv(csv.as_bytes(), &CsvOptions::default()), Err(WoodsError::InvalidData(_))));
+    let csv = "1,2\n3,4,5\n";
+    assert!(matches!(read_csv(csv.as_bytes(), &csv_options(false, None, None)), Err(WoodsError::InvalidData(_))));
+}
+
+#[test]
+fn libsvm_one_based_indices() {
+    let libsvm = "1.5 1:1 3:3 # comment\n-2 2:2\n";
+    let dataset = read_libsvm(libsvm.as_bytes(), &LibSvmOptions::default()).unwrap();
+    assert_eq!(dataset.columns, arr2(&[[1.0, 0.0], [0.0, 2.0], [3.0, 0.0]]));
+    assert_eq!(dataset.target, Some(arr1(&[1.5, -2.0])));
+    assert!(dataset.groups.is_none());
+}
+
+#[test]
+fn libsvm_zero_based_indices() {
+    let libsvm = "1 0:1 2:3\n0 1:2\n";
+    // detected by index `0`
+    let dataset = read_libsvm(libsvm.as_bytes(), &LibSvmOptions::default()).unwrap();
+    assert_eq!(dataset.columns, arr2(&[[1.0, 0.0], [0.0, 2.0], [3.0, 0.0]]));
+
+    let options = LibSvmOptions { zero_based: Some(true), n_features: Some(4) };
+    let dataset = read_libsvm("1 1:1\n".as_bytes(), &options).unwrap();
+    assert_eq!(dataset.columns, arr2(&[[0.0], [1.0], [0.0], [0.0]]));
+
+    let options = LibSvmOptions { zero_based: Some(false), ..LibSvmOptions::default() };
+    assert!(matches!(read_libsvm(libsvm.as_bytes(), &options), Err(WoodsError::InvalidData(_))));
+}
+
+#[test]
+fn libsvm_qid_groups() {
+    let libsvm = "2 qid:7 1:1\n1 qid:7 1:2\n0 qid:3 1:3\n1 qid:7 1:4\n";
+    let dataset = read_libsvm(libsvm.as_bytes(), &LibSvmOptions::default()).unwrap();
+    assert_eq!(dataset.groups, Some(arr1(&[0, 0, 1, 0])));
+    assert_eq!(dataset.columns, arr2(&[[1.0, 2.0, 3.0, 4.0]]));
+
+    let libsvm = "2 qid:7 1:1\n1 1:2\n";
+    assert!(matches!(read_libsvm(libsvm.as_bytes(), &LibSvmOptions::default()), Err(WoodsError::InvalidData(_))));
+}
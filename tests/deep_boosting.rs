@@ -0,0 +1,102 @@
+mod common;
+
+use ndarray::{Array1, Array2};
+use woods::ensemble::boosting::GradientBoostingParameters;
+use woods::ensemble::deep_boosting::{DeepBoostingParameters, LayerSearch};
+use woods::error::WoodsError;
+use woods::estimator::{ConstructibleWithArg, Estimator, FeatureImportance, ImportanceType};
+use woods::tree::TreeParameters;
+use common::DeepGbm;
+
+const N_FEATURES: usize = 5;
+
+/// Samples where only feature 0 is not constant, splits of constant features are never made.
+fn one_informative_feature(n_samples: usize) -> (Array2<f64>, Array1<f64>) {
+    let columns = Array2::from_shape_fn((N_FEATURES, n_samples), |(i, j)| if i == 0 { j as f64 } else { 1.0 });
+    let target = columns.row(0).mapv(|v| if v < n_samples as f64 / 2.0 { 0.0 } else { 1.0 });
+    (columns, target)
+}
+
+/// Parameters of one layer of `layer_width` gradient boostings of 3 stumps.
+fn one_layer_params(layer_width: u32, max_features: usize) -> DeepBoostingParameters {
+    let mut tree_params = TreeParameters::new(Some(1), None);
+    tree_params.max_features = max_features;
+    let mut params = DeepBoostingParameters::new(Some(1), Some(layer_width), None);
+    params.layer_params = Some(GradientBoostingParameters::new(tree_params, Some(3), Some(1.0)));
+    params
+}
+
+/// Fit deep boosting and count splits of all its trees.
+fn fit_n_splits(params: &DeepBoostingParameters) -> f64 {
+    let (columns, target) = one_informative_feature(200);
+    let mut deep = DeepGbm::new(params.clone());
+    deep.fit(&columns.view(), &target.view()).unwrap();
+    let importances = deep.feature_importances(N_FEATURES, ImportanceType::Split).unwrap();
+    assert!(importances.iter().skip(1).all(|v| *v == 0.0), "{}", importances);
+    importances.sum()
+}
+
+#[test]
+fn layer_params_max_features() {
+    // all features are candidates: each stump splits feature 0
+    let params = one_layer_params(3, N_FEATURES);
+    for _ in 0..5 {
+        assert_eq!(fit_n_splits(&params), 9.0);
+    }
+    // one random candidate: a stump is not split if constant feature is selected
+    let params = one_layer_params(3, 1);
+    assert!((0..5).any(|_| fit_n_splits(&params) < 9.0));
+}
+
+/// Search space of one point: stumps with all candidate features.
+fn one_point_search(n_estimators: u32) -> LayerSearch {
+    LayerSearch {
+        depth: vec![1],
+        max_features: vec![N_FEATURES],
+        n_estimators: vec![n_estimators],
+        learning_rate: vec![1.0],
+        n_folds: 2,
+        metric: "mse".into(),
+    }
+}
+
+fn fit_error(params: DeepBoostingParameters) -> WoodsError {
+    let (columns, target) = one_informative_feature(20);
+    DeepGbm::new(params).fit(&columns.view(), &target.view()).unwrap_err()
+}
+
+#[test]
+fn layer_search_one_point() {
+    for &n_estimators in &[2, 4] {
+        let mut params = DeepBoostingParameters::new(Some(1), Some(3), None);
+        params.layer_search = one_point_search(n_estimators);
+        for _ in 0..5 {
+            assert_eq!(fit_n_splits(&params), 3.0 * n_estimators as f64);
+        }
+    }
+}
+
+#[test]
+fn layer_search_folds_and_metric() {
+    let mut params = DeepBoostingParameters::new(Some(1), Some(3), None);
+    params.layer_search = LayerSearch { metric: "mae".into(), n_folds: 5, ..one_point_search(3) };
+    assert_eq!(fit_n_splits(&params), 9.0);
+
+    for &n_folds in &[1, 21] {
+        params.layer_search = LayerSearch { n_folds, ..one_point_search(3) };
+        assert!(matches!(fit_error(params.clone()), WoodsError::InvalidParameter(_)), "{} folds", n_folds);
+    }
+    params.layer_search = LayerSearch { metric: "quantile@1.5".into(), ..one_point_search(3) };
+    assert!(matches!(fit_error(params.clone()), WoodsError::InvalidParameter(_)));
+    params.layer_search = LayerSearch { depth: vec![], ..one_point_search(3) };
+    assert!(matches!(fit_error(params), WoodsError::InvalidParameter(_)));
+}
+
+#[test]
+fn layer_params_skip_search() {
+    // the search would fail
+    let invalid_search = LayerSearch { depth: vec![], n_folds: 100, metric: "unknown".into(), ..one_point_search(3) };
+    let mut params = one_layer_params(3, N_FEATURES);
+    params.layer_search = invalid_search;
+    assert_eq!(fit_n_splits(&params), 9.0);
+}
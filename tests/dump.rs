@@ -0,0 +1,96 @@
+mod common;
+
+use ndarray::{arr1, Array2};
+use woods::error::WoodsError;
+use woods::estimator::{Estimator, WithInputFeatures};
+use woods::tree::TreeParameters;
+use woods::utils::numerics::next_up;
+use woods::utils::serialization::load;
+use common::Tree;
+
+/// Tree of depth 2, fitted on 4 samples with 2 binary features.
+///
+/// Values of feature `i` are `t` and the next float after `t`, so the random threshold between them is `t`.
+fn fitted_tree() -> Tree {
+    let thresholds = [0.5, 1.25];
+    let columns = Array2::from_shape_fn((2, 4), |(i, j)| {
+        if (j >> i) & 1 == 0 { thresholds[i] } else { next_up(thresholds[i]) }
+    });
+    let mut params = TreeParameters::new(Some(2), None);
+    params.max_features = 2;
+    let mut tree = Tree::new(params);
+    tree.fit(&columns.view(), &arr1(&[1.0, 3.0, 2.0, 8.0]).view()).unwrap();
+    tree
+}
+
+#[test]
+fn dump_text_of_fitted_tree() {
+    let expected = "\
+if x[0] > 0.5000:  # value = 3.5000, samples = 4, squared_error = 7.2500
+    if x[1] > 1.2500:  # value = 5.5000, samples = 2, squared_error = 6.2500
+        value = 8.0000  # samples = 1
+    else:
+        value = 3.0000  # samples = 1
+else:
+    if x[1] > 1.2500:  # value = 1.5000, samples = 2, squared_error = 0.2500
+        value = 2.0000  # samples = 1
+    else:
+        value = 1.0000  # samples = 1
+";
+    assert_eq!(fitted_tree().dump_text(4).unwrap(), expected);
+}
+
+#[test]
+fn dot_of_fitted_tree() {
+    let expected = r#"digraph Tree {
+node [shape=box, style="rounded", fontname="helvetica"] ;
+edge [fontname="helvetica"] ;
+0 [label="x[0] <= 0.50\nsquared_error = 7.25\nsamples = 4\nvalue = 3.50"] ;
+0 -> 1 [labeldistance=2.5, labelangle=45, headlabel="True"] ;
+0 -> 4 [labeldistance=2.5, labelangle=-45, headlabel="False"] ;
+1 [label="x[1] <= 1.25\nsquared_error = 0.25\nsamples = 2\nvalue = 1.50"] ;
+1 -> 2 [labeldistance=2.5, labelangle=45, headlabel="True"] ;
+1 -> 3 [labeldistance=2.5, labelangle=-45, headlabel="False"] ;
+2 [label="samples = 1\nvalue = 1.00"] ;
+3 [label="samples = 1\nvalue = 2.00"] ;
+4 [label="x[1] <= 1.25\nsquared_error = 6.25\nsamples = 2\nvalue = 5.50"] ;
+4 -> 5 [labeldistance=2.5, labelangle=45, headlabel="True"] ;
+4 -> 6 [labeldistance=2.5, labelangle=-45, headlabel="False"] ;
+5 [label="samples = 1\nvalue = 3.00"] ;
+6 [label="samples = 1\nvalue = 8.00"] ;
+}
+"#;
+    assert_eq!(fitted_tree().to_dot(2).unwrap(), expected);
+}
+
+#[test]
+fn dump_of_legacy_tree_with_feature_names() {
+    // legacy models have no sample counts and split gains
+    let mut tree = Tree::new(TreeParameters::default());
+    load(&mut tree, "tests/data/legacy_tree.json", None).unwrap();
+    tree.input_features_mut().names = Some(vec!["a".into(), "b \"c\"".into()]);
+    let expected = "\
+if a > 0.5:  # value = 2.0
+    if b \"c\" > 0.5:  # value = 3.0
+        value = 3.5
+    else:
+        value = 2.5
+else:
+    if b \"c\" > 0.5:  # value = 1.0
+        value = 2.0
+    else:
+        value = 0.0
+";
+    assert_eq!(tree.dump_text(1).unwrap(), expected);
+    let dot = tree.to_dot(1).unwrap();
+    assert!(dot.contains(r#"0 [label="a <= 0.5\nvalue = 2.0"] ;"#), "{}", dot);
+    assert!(dot.contains(r#"4 [label="b \"c\" <= 0.5\nvalue = 3.0"] ;"#), "{}", dot);
+    assert!(dot.contains(r#"6 [label="value = 3.5"] ;"#), "{}", dot);
+}
+
+#[test]
+fn dump_of_not_fitted_tree() {
+    let tree = Tree::new(TreeParameters::default());
+    assert!(matches!(tree.dump_text(4), Err(WoodsError::NotFitted)));
+    assert!(matches!(tree.to_dot(4), Err(WoodsError::NotFitted)));
+}
@@ -0,0 +1,73 @@
+mod common;
+
+use woods::ensemble::boosting::GradientBoostingParameters;
+use woods::ensemble::deep_boosting::DeepBoostingParameters;
+use woods::error::WoodsError;
+use woods::estimator::{ConstructibleWithArg, ConstructibleWithCopyArg, Estimator};
+use woods::tree::TreeParameters;
+use common::{samples, DeepGbm, Gbm, Tree};
+
+fn tree() -> Tree {
+    Tree::new(TreeParameters::new(Some(2), None))
+}
+
+fn gbm() -> Gbm {
+    Gbm::new(GradientBoostingParameters::new(TreeParameters::new(Some(2), None), Some(5), None))
+}
+
+fn deep_gbm() -> DeepGbm {
+    let mut params = DeepBoostingParameters::new(Some(2), Some(2), None);
+    params.layer_params = Some(GradientBoostingParameters::new(TreeParameters::new(Some(2), None), Some(5), None));
+    DeepGbm::new(params)
+}
+
+/// Check training and prediction errors of `est`, which is not fitted yet.
+fn check_errors<E: Estimator<F = f64>>(mut est: E) {
+    let (columns, mut target) = samples(3, 20);
+
+    let (_, short_target) = samples(3, 19);
+    match est.fit(&columns.view(), &short_target.view()) {
+        Err(WoodsError::ShapeMismatch { what: "target samples", expected: 20, found: 19 }) => {},
+        result => panic!("unexpected result of fit with short target: {:?}", result.err()),
+    }
+
+    let (no_samples, no_target) = samples(3, 0);
+    assert!(matches!(est.fit(&no_samples.view(), &no_target.view()), Err(WoodsError::EmptyData)));
+    let (no_features, _) = samples(0, 20);
+    assert!(matches!(est.fit(&no_features.view(), &target.view()), Err(WoodsError::EmptyData)));
+
+    target[5] = f64::NAN;
+    assert!(matches!(est.fit(&columns.view(), &target.view()), Err(WoodsError::NanInput)));
+    target[5] = 0.0;
+
+    est.fit(&columns.view(), &target.view()).unwrap();
+    let (wrong_columns, _) = samples(4, 20);
+    match est.predict(&wrong_columns.view()) {
+        Err(WoodsError::ShapeMismatch { what: "features", expected: 3, found: 4 }) => {},
+        result => panic!("unexpected result of predict with wrong features: {:?}", result.err()),
+    }
+    assert_eq!(est.predict(&columns.view()).unwrap().dim(), 20);
+}
+
+#[test]
+fn tree_errors() {
+    check_errors(tree());
+}
+
+#[test]
+fn gbm_errors() {
+    check_errors(gbm());
+}
+
+#[test]
+fn deep_boosting_errors() {
+    let (columns, _) = samples(3, 20);
+    assert!(matches!(deep_gbm().predict(&columns.view()), Err(WoodsError::NotFitted)));
+    check_errors(deep_gbm());
+}
+
+#[test]
+fn error_messages() {
+    let err = WoodsError::ShapeMismatch { what: "features", expected: 3, found: 4 };
+    assert_eq!(err.to_string(), "Shape mismatch: expected 3 features, found 4");
+}
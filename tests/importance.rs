@@ -0,0 +1,83 @@
+mod common;
+
+use ndarray::{Array1, Array2};
+use woods::ensemble::boosting::GradientBoostingParameters;
+use woods::ensemble::deep_boosting::DeepBoostingParameters;
+use woods::error::WoodsError;
+use woods::estimator::{ConstructibleWithArg, ConstructibleWithCopyArg, Estimator, FeatureImportance, ImportanceType};
+use woods::tree::TreeParameters;
+use woods::utils::serialization::load;
+use common::{DeepGbm, Gbm, Tree};
+
+const N_FEATURES: usize = 5;
+
+/// Samples where target depends only on feature 0, other features are deterministic noise.
+fn feature_0_target(n_samples: usize) -> (Array2<f64>, Array1<f64>) {
+    let columns = Array2::from_shape_fn((N_FEATURES, n_samples), |(i, j)| {
+        if i == 0 { j as f64 } else { ((j * (7 + 13 * i)) % 101) as f64 }
+    });
+    let target = columns.row(0).mapv(|v| (v / 10.0).floor());
+    (columns, target)
+}
+
+fn tree_params() -> TreeParameters {
+    let mut params = TreeParameters::new(Some(3), None);
+    params.max_features = N_FEATURES;
+    params
+}
+
+fn assert_feature_0_is_most_important<M: FeatureImportance>(model: &M) {
+    for &importance_type in &[ImportanceType::Split, ImportanceType::Gain] {
+        let importances = model.feature_importances(N_FEATURES, importance_type).unwrap();
+        assert!(importances[0] > 0.0, "{:?}: {}", importance_type, importances);
+        for i in 1..N_FEATURES {
+            assert!(importances[0] > importances[i], "{:?}: {}", importance_type, importances);
+        }
+        for &n_features in &[1, N_FEATURES - 1, N_FEATURES + 1] {
+            assert!(matches!(model.feature_importances(n_features, importance_type),
+                             Err(WoodsError::ShapeMismatch { what: "features", .. })));
+        }
+    }
+}
+
+#[test]
+fn tree_importances_of_informative_feature() {
+    let (columns, target) = feature_0_target(200);
+    let mut tree = Tree::new(tree_params());
+    tree.fit(&columns.view(), &target.view()).unwrap();
+    assert_feature_0_is_most_important(&tree);
+}
+
+#[test]
+fn gbm_importances_of_informative_feature() {
+    let (columns, target) = feature_0_target(200);
+    let mut gbm = Gbm::new(GradientBoostingParameters::new(tree_params(), Some(20), Some(0.1)));
+    gbm.fit(&columns.view(), &target.view()).unwrap();
+    assert_feature_0_is_most_important(&gbm);
+}
+
+#[test]
+fn deep_boosting_importances_of_informative_feature() {
+    let (columns, target) = feature_0_target(200);
+    let mut params = DeepBoostingParameters::new(Some(2), Some(2), None);
+    params.layer_params = Some(GradientBoostingParameters::new(tree_params(), Some(10), Some(0.1)));
+    let mut deep = DeepGbm::new(params);
+    deep.fit(&columns.view(), &target.view()).unwrap();
+    assert_feature_0_is_most_important(&deep);
+}
+
+#[test]
+fn gain_importances_of_model_without_gains() {
+    let mut tree = Tree::new(TreeParameters::new(None, None));
+    load(&mut tree, "tests/data/legacy_tree.json", None).unwrap();
+    assert_eq!(tree.feature_importances(3, ImportanceType::Split).unwrap().to_vec(), vec![1.0, 2.0, 0.0]);
+    assert!(matches!(tree.feature_importances(3, ImportanceType::Gain), Err(WoodsError::UnsupportedModel(_))));
+    // number of features is not stored, but split features are checked
+    assert!(matches!(tree.feature_importances(1, ImportanceType::Split),
+                     Err(WoodsError::ShapeMismatch { what: "features", expected: 2, found: 1 })));
+
+    let mut gbm = Gbm::new(GradientBoostingParameters::new(TreeParameters::new(None, None), None, None));
+    load(&mut gbm, "tests/data/legacy_gbm.json", None).unwrap();
+    assert_eq!(gbm.feature_importances(2, ImportanceType::Split).unwrap().to_vec(), vec![1.0, 2.0]);
+    assert!(matches!(gbm.feature_importances(2, ImportanceType::Gain), Err(WoodsError::UnsupportedModel(_))));
+}
@@ -0,0 +1,84 @@
+mod common;
+
+use ndarray::{arr2, Array1, Array2, ArrayView1};
+use woods::error::WoodsError;
+use woods::estimator::{permutation_importance, Estimator};
+use woods::inspection::{cartesian_grid, partial_dependence, PartialDependenceRecursion};
+use woods::metrics::MeanSquaredError;
+use woods::tree::{ExplicitNode, TreeParameters};
+use common::Tree;
+
+/// Samples with 3 features, only feature 0 is not constant, target is a step at half of samples.
+fn step_samples(n_samples: usize) -> (Array2<f64>, Array1<f64>) {
+    let columns = Array2::from_shape_fn((3, n_samples), |(i, j)| if i == 0 { j as f64 } else { 1.0 });
+    let target = columns.row(0).mapv(|v| if v < n_samples as f64 / 2.0 { 0.0 } else { 1.0 });
+    (columns, target)
+}
+
+fn assert_close(values: &ArrayView1<'_, f64>, expected: &Array1<f64>) {
+    assert!(values.iter().zip(expected).all(|(v, e)| (v - e).abs() < 1e-12), "{} != {}", values, expected);
+}
+
+fn fit_stump(columns: &Array2<f64>, target: &Array1<f64>) -> Tree {
+    let mut params = TreeParameters::new(Some(1), None);
+    params.max_features = 3;
+    let mut tree = Tree::new(params);
+    tree.fit(&columns.view(), &target.view()).unwrap();
+    tree
+}
+
+#[test]
+fn partial_dependence_of_stump_is_step_function() {
+    let (columns, target) = step_samples(100);
+    let tree = fit_stump(&columns, &target);
+    let grid = cartesian_grid(&[Array1::linspace(0.0, 99.0, 12)]);
+    let dependence = partial_dependence(&tree, &columns.view(), &[0], &grid.view()).unwrap();
+    assert_eq!(dependence.individual.dim(), (100, 12));
+    let (threshold, values) = match tree.explicit_nodes().unwrap().as_slice() {
+        [ExplicitNode::Split { feature: 0, threshold, .. },
+         ExplicitNode::Leaf { value: left, .. }, ExplicitNode::Leaf { value: right, .. }] => (*threshold, [*left, *right]),
+        nodes => panic!("tree is not a stump on feature 0: {:?}", nodes),
+    };
+    let expected = grid.column(0).mapv(|v| values[(v > threshold) as usize]);
+    assert!(expected[0] < 0.5 && expected[11] > 0.5, "{}", expected);
+    assert_close(&dependence.average.view(), &expected);
+    for curve in dependence.individual.outer_iter() {
+        assert_close(&curve, &expected);
+    }
+    assert_close(&tree.partial_dependence_recursion(&[0], &grid.view()).view(), &expected);
+
+    // prediction does not depend on constant features
+    let grid = cartesian_grid(&[Array1::linspace(0.0, 2.0, 3)]);
+    let dependence = partial_dependence(&tree, &columns.view(), &[1], &grid.view()).unwrap();
+    let mean_prediction = tree.predict(&columns.view()).unwrap().mean().unwrap();
+    assert_close(&dependence.average.view(), &Array1::from_elem(3, mean_prediction));
+}
+
+#[test]
+fn partial_dependence_checks_grid_and_features() {
+    let (columns, target) = step_samples(10);
+    let tree = fit_stump(&columns, &target);
+    let grid = arr2(&[[0.0, 1.0], [1.0, 0.0]]);
+    assert!(matches!(
+        partial_dependence(&tree, &columns.view(), &[0], &grid.view()),
+        Err(WoodsError::ShapeMismatch { what: "grid columns", expected: 1, found: 2 })
+    ));
+    assert!(matches!(
+        partial_dependence(&tree, &columns.view(), &[0, 3], &grid.view()),
+        Err(WoodsError::InvalidParameter(_))
+    ));
+}
+
+#[test]
+fn permutation_importance_of_informative_feature() {
+    let (columns, target) = step_samples(100);
+    let tree = fit_stump(&columns, &target);
+    let importances = permutation_importance(&tree, &columns.view(), &target.view(), 5, &MeanSquaredError).unwrap();
+    assert_eq!(importances.mean.dim(), 3);
+    assert_eq!(importances.std.dim(), 3);
+    assert!(importances.mean[0] > 0.0, "{}", importances.mean);
+    // permutation of constant features does not change predictions
+    assert_eq!(importances.mean[1], 0.0);
+    assert_eq!(importances.mean[2], 0.0);
+    assert_eq!(importances.std[1], 0.0);
+}
@@ -0,0 +1,64 @@
+use ndarray::{arr2, Array2};
+use woods::error::WoodsError;
+use woods::export::lightgbm::from_lightgbm_text;
+use woods::tree::ExplicitNode;
+
+/// Samples of the fixture model with features `a` and `b`, splits are `x <= threshold` on the boundaries.
+fn fixture_columns() -> Array2<f64> {
+    arr2(&[[0.0, 0.5, 0.5, 1.0, f64::NAN, f64::NAN],
+           [0.0, 1.0, 1.5, -1.0, f64::NAN, -5.0]])
+}
+
+/// Predictions of the fixture model, `NaN` of missing type `None` is zero, `NaN` of missing type `NaN` goes right.
+const FIXTURE_PREDICTIONS: [f64; 6] = [2.6, 2.6, 2.8, 1.3, 2.8, 1.6];
+
+fn fixture() -> String {
+    std::fs::read_to_string("tests/data/lightgbm_model.txt").unwrap()
+}
+
+fn assert_close(a: &[f64], b: &[f64]) {
+    assert!(a.len() == b.len() && a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-12), "{:?} != {:?}", a, b);
+}
+
+#[test]
+fn import_text_model() {
+    let ensemble = from_lightgbm_text(&fixture()).unwrap();
+    assert_eq!(ensemble.features.n_features, 2);
+    assert_eq!(ensemble.features.names, Some(vec!["a".to_string(), "b".to_string()]));
+    assert_eq!(ensemble.trees.len(), 3);
+    assert!(ensemble.trees.iter().all(|(weight, _)| *weight == 1.0));
+    assert_close(&ensemble.predict(&fixture_columns().view()).unwrap().to_vec(), &FIXTURE_PREDICTIONS);
+
+    // thresholds are not converted, missing type `None` goes to the side of zero
+    let splits: Vec<(usize, f64, bool)> = ensemble.trees.iter().flat_map(|(_, nodes)| nodes).filter_map(|node| match node {
+        ExplicitNode::Split { feature, threshold, default_left, .. } => Some((*feature, *threshold, *default_left)),
+        ExplicitNode::Leaf { .. } => None,
+    }).collect();
+    assert_eq!(splits, vec![(0, 0.5, true), (1, 1.0000000000000002, false), (1, -1.0, false)]);
+}
+
+#[test]
+fn import_average_output() {
+    let text = fixture().replacen("tree\n", "tree\naverage_output\n", 1);
+    let ensemble = from_lightgbm_text(&text).unwrap();
+    assert!(ensemble.trees.iter().all(|(weight, _)| *weight == 1.0 / 3.0));
+    let expected: Vec<f64> = FIXTURE_PREDICTIONS.iter().map(|p| p / 3.0).collect();
+    assert_close(&ensemble.predict(&fixture_columns().view()).unwrap().to_vec(), &expected);
+}
+
+#[test]
+fn import_errors() {
+    let text = fixture();
+    let unsupported = |text: &str| matches!(from_lightgbm_text(text), Err(WoodsError::UnsupportedModel(_)));
+    let corrupted = |text: &str| matches!(from_lightgbm_text(text), Err(WoodsError::CorruptedModel(_)));
+    // zero as missing value
+    assert!(unsupported(&text.replace("decision_type=0 8", "decision_type=4 8")));
+    // categorical split
+    assert!(unsupported(&text.replace("decision_type=0 8", "decision_type=1 8")));
+    assert!(unsupported(&text.replace("num_tree_per_iteration=1", "num_tree_per_iteration=3")));
+    assert!(unsupported(&text.replacen("is_linear=0", "is_linear=1", 1)));
+    assert!(corrupted(""));
+    assert!(corrupted(&text.replace("leaf_value=1 2", "leaf_value=1")));
+    assert!(corrupted(&text.replace("left_child=1 -1", "left_child=1")));
+    assert!(corrupted(&text.replace("split_feature=0 1", "split_feature=0 2")));
+}
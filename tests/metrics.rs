@@ -0,0 +1,148 @@
+mod common;
+
+use ndarray::{arr1, Array1, Array2};
+use woods::ensemble::boosting::GradientBoostingParameters;
+use woods::error::WoodsError;
+use woods::estimator::{ConstructibleWithCopyArg, Estimator};
+use woods::metrics::{metric_by_name, METRICS};
+use woods::tree::TreeParameters;
+use common::Gbm;
+
+fn evaluate(name: &str, preds: &[f64], target: &[f64], weights: Option<&[f64]>) -> f64 {
+    let metric = metric_by_name::<f64>(name).unwrap();
+    let weights = weights.map(arr1);
+    metric.evaluate(&arr1(preds).view(), &arr1(target).view(), weights.as_ref().map(|w| w.view()).as_ref())
+}
+
+fn assert_close(a: f64, b: f64) {
+    assert!((a - b).abs() < 1e-12, "{} != {}", a, b);
+}
+
+#[test]
+fn regression_metrics() {
+    let (preds, target) = ([1.0, 2.0, 3.0], [1.0, 3.0, 5.0]);
+    assert_close(evaluate("mse", &preds, &target, None), 5.0 / 3.0);
+    assert_close(evaluate("rmse", &preds, &target, None), (5.0f64 / 3.0).sqrt());
+    assert_close(evaluate("mae", &preds, &target, None), 1.0);
+    assert_close(evaluate("r2", &preds, &target, None), 0.375);
+    assert_close(evaluate("mape", &preds, &target, None), (1.0 / 3.0 + 2.0 / 5.0) / 3.0);
+    assert_close(evaluate("quantile@0.9", &[1.0, 1.0], &[0.0, 2.0], None), 0.5);
+    assert_close(evaluate("quantile", &[1.0, 1.0], &[0.0, 2.0], None), 0.5);
+    // perfect and constant predictions of constant target
+    assert_close(evaluate("r2", &[2.0, 2.0], &[2.0, 2.0], None), 1.0);
+    assert_close(evaluate("r2", &[1.0, 2.0], &[2.0, 2.0], None), 0.0);
+}
+
+#[test]
+fn weighted_regression_metrics() {
+    let (preds, target, weights) = ([1.0, 2.0, 3.0], [1.0, 3.0, 5.0], [1.0, 1.0, 2.0]);
+    assert_close(evaluate("mse", &preds, &target, Some(&weights)), 2.25);
+    assert_close(evaluate("mae", &preds, &target, Some(&weights)), 1.25);
+    assert_close(evaluate("r2", &preds, &target, Some(&weights)), 2.0 / 11.0);
+    // unit weights are equal to unweighted metrics
+    for name in &["mse", "rmse", "mae", "r2", "mape", "quantile@0.3"] {
+        assert_close(evaluate(name, &preds, &target, Some(&[1.0; 3])), evaluate(name, &preds, &target, None));
+    }
+}
+
+#[test]
+fn classification_metrics() {
+    let (preds, target) = ([0.1, 0.4, 0.35, 0.8], [0.0, 0.0, 1.0, 1.0]);
+    assert_close(evaluate("auc", &preds, &target, None), 0.75);
+    assert_close(evaluate("auc", &preds, &target, Some(&[1.0, 2.0, 3.0, 1.0])), 0.5);
+    // tied predictions
+    assert_close(evaluate("auc", &[0.5, 0.5, 0.9], &[0.0, 1.0, 1.0], None), 0.75);
+    assert!(evaluate("auc", &preds, &[1.0; 4], None).is_nan());
+    assert_close(evaluate("accuracy", &preds, &target, None), 0.75);
+    assert_close(evaluate("accuracy", &preds, &target, Some(&[1.0, 2.0, 3.0, 1.0])), 4.0 / 7.0);
+    let logloss = -(0.9f64.ln() + 0.6f64.ln() + 0.35f64.ln() + 0.8f64.ln()) / 4.0;
+    assert_close(evaluate("logloss", &preds, &target, None), logloss);
+    assert!(evaluate("logloss", &[0.0, 1.0], &[1.0, 0.0], None).is_finite());
+}
+
+#[test]
+fn ranking_metrics() {
+    let (preds, relevance) = ([3.0, 2.0, 1.0], [0.0, 1.0, 2.0]);
+    let log3 = 3.0f64.log2();
+    assert_close(evaluate("ndcg@1", &preds, &relevance, None), 0.0);
+    assert_close(evaluate("ndcg@2", &preds, &relevance, None), (1.0 / log3) / (3.0 + 1.0 / log3));
+    assert_close(evaluate("ndcg", &preds, &relevance, None), (1.0 / log3 + 3.0 / 2.0) / (3.0 + 1.0 / log3));
+    assert_close(evaluate("ndcg@2", &[1.0, 2.0, 3.0], &relevance, None), 1.0);
+    // weights are ignored
+    assert_close(evaluate("ndcg", &preds, &relevance, Some(&[5.0, 1.0, 1.0])), evaluate("ndcg", &preds, &relevance, None));
+
+    // mean of queries, query without relevant samples has NDCG 1
+    let metric = metric_by_name::<f64>("ndcg@1").unwrap();
+    let (preds, relevance) = (arr1(&[1.0, 2.0, 3.0, 4.0, 5.0]), arr1(&[1.0, 0.0, 0.0, 0.0, 0.0]));
+    assert_close(metric.evaluate_groups(&preds.view(), &relevance.view(), None, &[7, 7, 3, 3, 3]), 0.5);
+}
+
+#[test]
+fn metric_names() {
+    for name in METRICS.iter().filter(|name| **name != "quantile") {
+        assert_eq!(metric_by_name::<f64>(name).unwrap().name(), *name);
+    }
+    assert_eq!(metric_by_name::<f64>("quantile").unwrap().name(), "quantile@0.5");
+    assert_eq!(metric_by_name::<f32>("quantile@0.9").unwrap().name(), "quantile@0.9");
+    assert_eq!(metric_by_name::<f64>("ndcg@10").unwrap().name(), "ndcg@10");
+    for name in &["quantile@1.5", "quantile@0", "quantile@x", "ndcg@x", "ndcg@-1", "mse@2", "auc@", "msle", ""] {
+        assert!(matches!(metric_by_name::<f64>(name), Err(WoodsError::InvalidParameter(_))), "{}", name);
+    }
+}
+
+#[test]
+fn loss_is_negated_score() {
+    let (preds, target) = (arr1(&[1.0, 2.0, 3.0]), arr1(&[1.0, 3.0, 5.0]));
+    let loss = |name| metric_by_name::<f64>(name).unwrap().loss(&preds.view(), &target.view(), None);
+    assert_close(loss("r2"), -0.375);
+    assert_close(loss("mse"), 5.0 / 3.0);
+}
+
+/// Samples with one binary feature and target equal to the feature.
+fn binary_samples() -> (Array2<f64>, Array1<f64>) {
+    let columns = Array2::from_shape_fn((1, 16), |(_, j)| (j % 2) as f64);
+    let target = columns.row(0).to_owned();
+    (columns, target)
+}
+
+fn n_estimators(gbm: &Gbm) -> usize {
+    (0..).take_while(|i| gbm.estimator(*i).is_some()).count()
+}
+
+#[test]
+fn early_stopping_truncates_estimators() {
+    let (columns, target) = binary_samples();
+    let mut gbm = Gbm::new(GradientBoostingParameters::new(TreeParameters::new(Some(1), None), Some(10), Some(0.5)));
+    let mse = metric_by_name::<f64>("mse").unwrap();
+
+    // validation loss improves at each iteration
+    let scores = gbm.fit_early_stopping(&columns.view(), &target.view(), &columns.view(), &target.view(), &*mse, 2).unwrap();
+    assert_eq!(scores.len(), 10);
+    assert_eq!(n_estimators(&gbm), 10);
+
+    // validation target is the mean of target, first estimator is the best
+    let val_target = Array1::from_elem(16, 0.5);
+    let scores = gbm.fit_early_stopping(&columns.view(), &target.view(), &columns.view(), &val_target.view(), &*mse, 2).unwrap();
+    assert_eq!(scores.len(), 3);
+    assert!(scores[0] < scores[1] && scores[1] < scores[2], "{}", scores);
+    assert_eq!(n_estimators(&gbm), 1);
+    assert_eq!(gbm.predict(&columns.view()).unwrap(), columns.row(0).mapv(|v| 0.25 + 0.5 * v));
+
+    // the best iteration is found by the loss of metric where higher values are better
+    let r2 = metric_by_name::<f64>("r2").unwrap();
+    let scores = gbm.fit_early_stopping(&columns.view(), &target.view(), &columns.view(), &target.view(), &*r2, 1).unwrap();
+    assert_eq!(scores.len(), 10);
+    assert_eq!(n_estimators(&gbm), 10);
+}
+
+#[test]
+fn early_stopping_errors() {
+    let (columns, target) = binary_samples();
+    let mut gbm = Gbm::new(GradientBoostingParameters::new(TreeParameters::new(Some(1), None), Some(10), Some(0.5)));
+    let mse = metric_by_name::<f64>("mse").unwrap();
+    assert!(matches!(gbm.fit_early_stopping(&columns.view(), &target.view(), &columns.view(), &target.view(), &*mse, 0),
+                     Err(WoodsError::InvalidParameter(_))));
+    let two_features = Array2::zeros((2, 16));
+    assert!(matches!(gbm.fit_early_stopping(&columns.view(), &target.view(), &two_features.view(), &target.view(), &*mse, 1),
+                     Err(WoodsError::ShapeMismatch { what: "features", .. })));
+}
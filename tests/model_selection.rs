@@ -0,0 +1,106 @@
+use std::collections::HashSet;
+use woods::error::WoodsError;
+use woods::model_selection::{Fold, GroupKFold, KFold, ShuffleKFold, Splitter, TimeSeriesSplit};
+
+/// Constructor of splitter with given number of splits.
+type SplitterFn = Box<dyn Fn(usize) -> Box<dyn Splitter>>;
+
+/// Check that test folds partition all samples and each train fold is the complement of its test fold.
+fn assert_partition(folds: &[Fold], n_samples: usize) {
+    let mut tested = vec![0; n_samples];
+    for fold in folds {
+        for &i in &fold.test {
+            tested[i] += 1;
+        }
+        let test: HashSet<usize> = fold.test.iter().copied().collect();
+        let train: HashSet<usize> = fold.train.iter().copied().collect();
+        assert!(test.is_disjoint(&train), "{:?}", fold);
+        assert_eq!(test.len() + train.len(), n_samples);
+        assert_eq!(fold.test.len() + fold.train.len(), n_samples, "duplicated indices in {:?}", fold);
+    }
+    assert!(tested.iter().all(|n| *n == 1), "samples are tested {:?} times", tested);
+}
+
+fn assert_balanced(folds: &[Fold]) {
+    let sizes: Vec<usize> = folds.iter().map(|fold| fold.test.len()).collect();
+    assert!(sizes.iter().max().unwrap() - sizes.iter().min().unwrap() <= 1, "fold sizes {:?}", sizes);
+}
+
+#[test]
+fn kfold_partitions_samples() {
+    for &(n_splits, n_samples) in &[(2, 2), (3, 10), (5, 23), (7, 7)] {
+        let folds = KFold::new(n_splits).split(n_samples, None).unwrap();
+        assert_eq!(folds.len(), n_splits);
+        assert_partition(&folds, n_samples);
+        assert_balanced(&folds);
+    }
+}
+
+#[test]
+fn shuffle_kfold_partitions_samples() {
+    for &(n_splits, n_samples) in &[(2, 3), (4, 10), (5, 23)] {
+        let folds = ShuffleKFold::new(n_splits, None).split(n_samples, None).unwrap();
+        assert_eq!(folds.len(), n_splits);
+        assert_partition(&folds, n_samples);
+        assert_balanced(&folds);
+    }
+}
+
+#[test]
+fn shuffle_kfold_is_deterministic_for_seed() {
+    let split = |seed| ShuffleKFold::new(5, Some(seed)).split(100, None).unwrap();
+    assert_eq!(split(42), split(42));
+    assert_ne!(split(42), split(43));
+    assert_ne!(split(42), KFold::new(5).split(100, None).unwrap());
+}
+
+#[test]
+fn group_kfold_keeps_groups_together() {
+    let groups: Vec<usize> = (0..40).map(|i| (i * 7 + i / 3) % 9).collect();
+    let folds = GroupKFold::new(4).split(groups.len(), Some(&groups)).unwrap();
+    assert_eq!(folds.len(), 4);
+    assert_partition(&folds, groups.len());
+    for fold in &folds {
+        let test_groups: HashSet<usize> = fold.test.iter().map(|i| groups[*i]).collect();
+        assert!(fold.train.iter().all(|i| !test_groups.contains(&groups[*i])), "{:?}", fold);
+    }
+}
+
+#[test]
+fn group_kfold_errors() {
+    assert!(matches!(GroupKFold::new(2).split(4, None), Err(WoodsError::InvalidParameter(_))));
+    assert!(matches!(GroupKFold::new(2).split(4, Some(&[0, 1, 0])), Err(WoodsError::ShapeMismatch { .. })));
+    // more splits than groups
+    assert!(matches!(GroupKFold::new(3).split(4, Some(&[0, 1, 0, 1])), Err(WoodsError::InvalidParameter(_))));
+}
+
+#[test]
+fn time_series_split_trains_on_past() {
+    let folds = TimeSeriesSplit::new(4).split(22, None).unwrap();
+    assert_eq!(folds.len(), 4);
+    let mut tested = HashSet::new();
+    for fold in &folds {
+        assert!(!fold.train.is_empty() && !fold.test.is_empty());
+        assert!(fold.train.iter().max() < fold.test.iter().min(), "{:?}", fold);
+        assert_eq!(fold.test.len(), 22 / 5);
+        assert!(fold.test.iter().all(|i| tested.insert(*i)), "overlapping test folds");
+    }
+    assert_eq!(folds.last().unwrap().test.last(), Some(&21));
+}
+
+#[test]
+fn number_of_splits_errors() {
+    let splitters: Vec<SplitterFn> = vec![
+        Box::new(|n| Box::new(KFold::new(n))),
+        Box::new(|n| Box::new(ShuffleKFold::new(n, Some(0)))),
+        Box::new(|n| Box::new(TimeSeriesSplit::new(n))),
+    ];
+    for splitter in &splitters {
+        assert!(matches!(splitter(1).split(10, None), Err(WoodsError::InvalidParameter(_))));
+        assert!(matches!(splitter(0).split(10, None), Err(WoodsError::InvalidParameter(_))));
+        assert!(matches!(splitter(11).split(10, None), Err(WoodsError::InvalidParameter(_))));
+    }
+    let groups = [0, 1, 2, 3, 4];
+    assert!(matches!(GroupKFold::new(1).split(5, Some(&groups)), Err(WoodsError::InvalidParameter(_))));
+    assert!(matches!(GroupKFold::new(6).split(5, Some(&groups)), Err(WoodsError::InvalidParameter(_))));
+}
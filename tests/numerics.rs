@@ -0,0 +1,80 @@
+mod common;
+
+use ndarray::{Array1, Array2, Axis};
+use woods::ensemble::boosting::{GradientBoostingParameters, TreeGBM};
+use woods::error::WoodsError;
+use woods::estimator::{ConstructibleWithCopyArg, Estimator};
+use woods::tree::{DecisionTreeImpl, TreeParameters};
+use woods::tree::rule::RandomSplitRule;
+use woods::utils::serialization::{from_bytes, header_from_bytes, to_bytes};
+use common::{Gbm, Tree};
+
+type Tree32 = DecisionTreeImpl<RandomSplitRule<f32>>;
+type Gbm32 = TreeGBM<f32>;
+
+const N_FEATURES: usize = 5;
+
+/// Binary features (bits of sample index) and target with distinct weights of features, exact in `f32`.
+/// Weights of residuals don't tie at learning rate `0.3`, so `f32` and `f64` models choose the same splits.
+fn binary_samples() -> (Array2<f64>, Array1<f64>) {
+    let weights = [5.5, 3.25, 1.75, 0.875, 0.5];
+    let columns = Array2::from_shape_fn((N_FEATURES, 32), |(i, j)| ((j >> i) & 1) as f64);
+    let target = columns.map_axis(Axis(0), |x| x.iter().zip(&weights).map(|(v, w)| v * w).sum());
+    (columns, target)
+}
+
+fn tree_params(depth: u8) -> TreeParameters {
+    let mut params = TreeParameters::new(Some(depth), None);
+    params.max_features = N_FEATURES;
+    params
+}
+
+fn gbm_params() -> GradientBoostingParameters<TreeParameters> {
+    GradientBoostingParameters::new(tree_params(2), Some(10), Some(0.3))
+}
+
+/// Fit `f32` and `f64` models on the same samples and compare their predictions.
+fn check_f32_model<E32, E64>(mut est32: E32, mut est64: E64)
+    where E32: Estimator<F = f32>, E64: Estimator<F = f64> {
+    let (columns, target) = binary_samples();
+    let (columns32, target32) = (columns.mapv(|v| v as f32), target.mapv(|v| v as f32));
+    est32.fit(&columns32.view(), &target32.view()).unwrap();
+    est64.fit(&columns.view(), &target.view()).unwrap();
+
+    let predictions = est64.predict(&columns.view()).unwrap();
+    let predictions32 = est32.predict(&columns32.view()).unwrap();
+    assert!(predictions.iter().zip(&predictions32).all(|(p, p32)| (p - *p32 as f64).abs() < 1e-5),
+            "{} != {}", predictions, predictions32);
+    // predictions are not constant
+    assert!(predictions.iter().any(|p| (p - predictions[0]).abs() > 1.0));
+}
+
+#[test]
+fn f32_tree_is_equal_to_f64_tree() {
+    check_f32_model(Tree32::new(tree_params(3)), Tree::new(tree_params(3)));
+}
+
+#[test]
+fn f32_gbm_is_equal_to_f64_gbm() {
+    check_f32_model(Gbm32::new(gbm_params()), Gbm::new(gbm_params()));
+}
+
+#[test]
+fn f32_round_trip() {
+    let (columns, target) = binary_samples();
+    let (columns, target) = (columns.mapv(|v| v as f32), target.mapv(|v| v as f32));
+    let mut gbm = Gbm32::new(gbm_params());
+    gbm.fit(&columns.view(), &target.view()).unwrap();
+    let predictions = gbm.predict(&columns.view()).unwrap();
+    for format in &["json", "bincode", "msgpack", "cbor", "bincode+zstd"] {
+        let bytes = to_bytes(&gbm, Some(format)).unwrap();
+        let header = header_from_bytes(&bytes, None).unwrap();
+        assert_eq!((header.model_kind.as_str(), header.dtype()), ("GradientBoosting[float32]", "float32"));
+
+        let mut loaded = Gbm32::new(gbm_params());
+        from_bytes(&mut loaded, &bytes, None).unwrap();
+        assert_eq!(loaded.predict(&columns.view()).unwrap(), predictions, "format {}", format);
+        // `f32` model is not loaded as `f64` one
+        assert!(matches!(from_bytes(&mut Gbm::new(gbm_params()), &bytes, None), Err(WoodsError::WrongModelKind { .. })));
+    }
+}
@@ -0,0 +1,248 @@
+mod common;
+
+use std::collections::HashMap;
+use ndarray::Array2;
+use woods::ensemble::boosting::GradientBoostingParameters;
+use woods::estimator::{ConstructibleWithCopyArg, Estimator};
+use woods::export::AsWeightedTrees;
+use woods::export::onnx::to_onnx;
+use woods::tree::{ExplicitNode, TreeParameters};
+use common::{samples, Gbm};
+
+/// Decoded protobuf field value.
+#[derive(Debug, Clone)]
+enum Field {
+    Varint(u64),
+    Bytes(Vec<u8>),
+}
+
+fn read_varint(buf: &[u8], pos: &mut usize) -> u64 {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = buf[*pos];
+        *pos += 1;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte < 0x80 {
+            return value;
+        }
+        shift += 7;
+    }
+}
+
+/// Decode protobuf message to fields, repeated fields keep their order.
+fn decode(buf: &[u8]) -> Vec<(u64, Field)> {
+    let mut fields = vec![];
+    let mut pos = 0;
+    while pos < buf.len() {
+        let key = read_varint(buf, &mut pos);
+        let field = match key & 7 {
+            0 => Field::Varint(read_varint(buf, &mut pos)),
+            2 => {
+                let len = read_varint(buf, &mut pos) as usize;
+                pos += len;
+                Field::Bytes(buf[pos - len..pos].to_vec())
+            },
+            wire_type => panic!("unexpected wire type {}", wire_type),
+        };
+        fields.push((key >> 3, field));
+    }
+    fields
+}
+
+fn messages(fields: &[(u64, Field)], number: u64) -> Vec<Vec<(u64, Field)>> {
+    fields.iter().filter(|(n, _)| *n == number).map(|(_, field)| match field {
+        Field::Bytes(bytes) => decode(bytes),
+        field => panic!("field {} is not a message: {:?}", number, field),
+    }).collect()
+}
+
+fn bytes(fields: &[(u64, Field)], number: u64) -> Vec<u8> {
+    match fields.iter().find(|(n, _)| *n == number) {
+        Some((_, Field::Bytes(bytes))) => bytes.clone(),
+        field => panic!("field {} is not bytes: {:?}", number, field),
+    }
+}
+
+fn string(fields: &[(u64, Field)], number: u64) -> String {
+    String::from_utf8(bytes(fields, number)).unwrap()
+}
+
+fn int(fields: &[(u64, Field)], number: u64) -> i64 {
+    match fields.iter().find(|(n, _)| *n == number) {
+        Some((_, Field::Varint(value))) => *value as i64,
+        field => panic!("field {} is not varint: {:?}", number, field),
+    }
+}
+
+/// Attribute of `TreeEnsembleRegressor` node.
+#[derive(Debug)]
+enum Attribute {
+    Int(i64),
+    String(String),
+    Floats(Vec<f32>),
+    Ints(Vec<i64>),
+    Strings(Vec<String>),
+}
+
+fn attribute(fields: &[(u64, Field)]) -> Attribute {
+    match int(fields, 20) {
+        2 => Attribute::Int(int(fields, 3)),
+        3 => Attribute::String(string(fields, 4)),
+        6 => Attribute::Floats(bytes(fields, 7).chunks(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect()),
+        7 => {
+            let packed = bytes(fields, 8);
+            let mut pos = 0;
+            let mut ints = vec![];
+            while pos < packed.len() {
+                ints.push(read_varint(&packed, &mut pos) as i64);
+            }
+            Attribute::Ints(ints)
+        },
+        8 => Attribute::Strings(fields.iter().filter(|(n, _)| *n == 9).map(|(_, field)| match field {
+            Field::Bytes(bytes) => String::from_utf8(bytes.clone()).unwrap(),
+            field => panic!("string is not bytes: {:?}", field),
+        }).collect()),
+        attr_type => panic!("unexpected attribute type {}", attr_type),
+    }
+}
+
+/// Decoded ONNX model: opsets by domain, input shape and attributes of the only graph node.
+struct OnnxModel {
+    ir_version: i64,
+    opsets: HashMap<String, i64>,
+    op_type: String,
+    domain: String,
+    input_width: i64,
+    attributes: HashMap<String, Attribute>,
+}
+
+fn decode_model(buf: &[u8]) -> OnnxModel {
+    let model = decode(buf);
+    let opsets = messages(&model, 8).iter().map(|opset| (string(opset, 1), int(opset, 2))).collect();
+    let graph = messages(&model, 7).pop().unwrap();
+    let nodes = messages(&graph, 1);
+    assert_eq!(nodes.len(), 1);
+    let node = &nodes[0];
+    let attributes = messages(node, 5).iter().map(|attr| (string(attr, 1), attribute(attr))).collect();
+    // ValueInfoProto -> TypeProto -> Tensor -> TensorShapeProto -> dims
+    let input = &messages(&graph, 11)[0];
+    let tensor = &messages(&messages(input, 2)[0], 1)[0];
+    let dims = messages(&messages(tensor, 2)[0], 1);
+    assert_eq!(dims.len(), 2);
+    assert_eq!(string(&dims[0], 2), "N");
+    OnnxModel {
+        ir_version: int(&model, 1),
+        opsets,
+        op_type: string(node, 4),
+        domain: string(node, 7),
+        input_width: int(&dims[1], 1),
+        attributes,
+    }
+}
+
+impl OnnxModel {
+    fn ints(&self, name: &str) -> &[i64] {
+        match &self.attributes[name] {
+            Attribute::Ints(values) => values,
+            attr => panic!("{} is not ints: {:?}", name, attr),
+        }
+    }
+
+    fn floats(&self, name: &str) -> &[f32] {
+        match &self.attributes[name] {
+            Attribute::Floats(values) => values,
+            attr => panic!("{} is not floats: {:?}", name, attr),
+        }
+    }
+
+    fn strings(&self, name: &str) -> &[String] {
+        match &self.attributes[name] {
+            Attribute::Strings(values) => values,
+            attr => panic!("{} is not strings: {:?}", name, attr),
+        }
+    }
+
+    /// Predict `features` of one sample like ONNX Runtime `TreeEnsembleRegressor` with `SUM` aggregation.
+    fn predict(&self, features: &[f32]) -> f32 {
+        let index: HashMap<(i64, i64), usize> = self.ints("nodes_treeids").iter()
+            .zip(self.ints("nodes_nodeids"))
+            .enumerate()
+            .map(|(i, (tree, node))| ((*tree, *node), i))
+            .collect();
+        let leaves: HashMap<(i64, i64), f32> = self.ints("target_treeids").iter()
+            .zip(self.ints("target_nodeids"))
+            .zip(self.floats("target_weights"))
+            .map(|((tree, node), weight)| ((*tree, *node), *weight))
+            .collect();
+        let n_trees = self.ints("nodes_treeids").iter().max().map_or(0, |id| id + 1);
+        (0..n_trees).map(|tree| {
+            let mut i = index[&(tree, 0)];
+            while self.strings("nodes_modes")[i] == "BRANCH_LEQ" {
+                let feature = self.ints("nodes_featureids")[i] as usize;
+                let child = if features[feature] <= self.floats("nodes_values")[i] {
+                    self.ints("nodes_truenodeids")[i]
+                } else {
+                    self.ints("nodes_falsenodeids")[i]
+                };
+                i = index[&(tree, child)];
+            }
+            assert_eq!(self.strings("nodes_modes")[i], "LEAF");
+            leaves[&(tree, self.ints("nodes_nodeids")[i])]
+        }).sum::<f32>() + self.floats("base_values")[0]
+    }
+}
+
+fn fitted_gbm() -> (Gbm, Array2<f64>) {
+    let (columns, target) = samples(4, 100);
+    let mut gbm = Gbm::new(GradientBoostingParameters::new(TreeParameters::new(Some(3), None), Some(15), Some(0.2)));
+    gbm.fit(&columns.view(), &target.view()).unwrap();
+    (gbm, columns)
+}
+
+#[test]
+fn onnx_model_structure() {
+    let (gbm, _) = fitted_gbm();
+    let onnx = decode_model(&to_onnx(&gbm).unwrap());
+    assert_eq!(onnx.ir_version, 7);
+    assert_eq!(onnx.opsets.len(), 2);
+    assert_eq!(onnx.opsets[""], 13);
+    assert_eq!(onnx.opsets["ai.onnx.ml"], 1);
+    assert_eq!((onnx.op_type.as_str(), onnx.domain.as_str()), ("TreeEnsembleRegressor", "ai.onnx.ml"));
+    assert_eq!(onnx.input_width, 4);
+
+    let ensemble = gbm.weighted_trees().unwrap();
+    let n_nodes: usize = ensemble.trees.iter().map(|(_, nodes)| nodes.len()).sum();
+    let n_leaves = ensemble.trees.iter()
+        .flat_map(|(_, nodes)| nodes)
+        .filter(|node| matches!(node, ExplicitNode::Leaf { .. }))
+        .count();
+    assert!(n_leaves > ensemble.trees.len());
+    for name in &["nodes_treeids", "nodes_nodeids", "nodes_featureids", "nodes_truenodeids",
+                  "nodes_falsenodeids", "nodes_missing_value_tracks_true"] {
+        assert_eq!(onnx.ints(name).len(), n_nodes, "{}", name);
+    }
+    assert_eq!(onnx.floats("nodes_values").len(), n_nodes);
+    assert_eq!(onnx.strings("nodes_modes").len(), n_nodes);
+    for name in &["target_treeids", "target_nodeids", "target_ids"] {
+        assert_eq!(onnx.ints(name).len(), n_leaves, "{}", name);
+    }
+    assert_eq!(onnx.floats("target_weights").len(), n_leaves);
+    assert_eq!(onnx.floats("base_values"), &[ensemble.base_value as f32]);
+    assert!(matches!(onnx.attributes["n_targets"], Attribute::Int(1)));
+    assert!(matches!(&onnx.attributes["aggregate_function"], Attribute::String(s) if s == "SUM"));
+    assert!(matches!(&onnx.attributes["post_transform"], Attribute::String(s) if s == "NONE"));
+}
+
+#[test]
+fn onnx_predictions_are_equal_to_model_predictions() {
+    let (gbm, columns) = fitted_gbm();
+    let onnx = decode_model(&to_onnx(&gbm).unwrap());
+    let predictions = gbm.predict(&columns.view()).unwrap();
+    for (features, prediction) in columns.t().outer_iter().zip(predictions.iter()) {
+        let features: Vec<f32> = features.iter().map(|v| *v as f32).collect();
+        let onnx_prediction = onnx.predict(&features);
+        assert!((f64::from(onnx_prediction) - prediction).abs() < 1e-4 * (1.0 + prediction.abs()),
+                "ONNX prediction {} != {}", onnx_prediction, prediction);
+    }
+}
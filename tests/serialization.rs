@@ -0,0 +1,155 @@
+mod common;
+
+use std::collections::BTreeMap;
+use ndarray::Array2;
+use woods::ensemble::boosting::{GradientBoostingParameters, TreeGBM};
+use woods::ensemble::deep_boosting::DeepBoostingParameters;
+use woods::error::WoodsError;
+use woods::estimator::{ConstructibleWithArg, ConstructibleWithCopyArg, Estimator};
+use woods::tree::TreeParameters;
+use woods::utils::serialization::{from_bytes, header_from_bytes, load, to_bytes, to_bytes_with_metadata};
+use common::{samples, DeepGbm, Gbm, Tree};
+
+fn fitted_gbm() -> (Gbm, Array2<f64>) {
+    let (columns, target) = samples(3, 50);
+    let mut gbm = Gbm::new(GradientBoostingParameters::new(TreeParameters::new(Some(3), None), Some(10), None));
+    gbm.fit(&columns.view(), &target.view()).unwrap();
+    (gbm, columns)
+}
+
+fn empty_gbm() -> Gbm {
+    Gbm::new(GradientBoostingParameters::new(TreeParameters::new(None, None), None, None))
+}
+
+#[test]
+fn round_trip_all_formats() {
+    let (gbm, columns) = fitted_gbm();
+    let predictions = gbm.predict(&columns.view()).unwrap();
+    for format in &["json", "bincode", "msgpack", "cbor", "json+gzip", "bincode+zstd", "msgpack+zstd", "cbor+gzip"] {
+        let bytes = to_bytes(&gbm, Some(format)).unwrap();
+        let header = header_from_bytes(&bytes, None).unwrap();
+        assert_eq!(header.model_kind, "GradientBoosting");
+        assert!(header.metadata.is_empty());
+
+        let mut loaded = empty_gbm();
+        from_bytes(&mut loaded, &bytes, None).unwrap();
+        assert_eq!(loaded.predict(&columns.view()).unwrap(), predictions, "format {}", format);
+    }
+}
+
+#[test]
+fn metadata_is_stored_in_header() {
+    let (gbm, _) = fitted_gbm();
+    let metadata: BTreeMap<String, String> = vec![("dataset".to_string(), "test".to_string())].into_iter().collect();
+    let bytes = to_bytes_with_metadata(&gbm, Some("json"), metadata.clone()).unwrap();
+    assert_eq!(header_from_bytes(&bytes, None).unwrap().metadata, metadata);
+    let mut loaded = empty_gbm();
+    assert_eq!(from_bytes(&mut loaded, &bytes, None).unwrap().metadata, metadata);
+}
+
+#[test]
+fn json_envelope_checks_header() {
+    let (gbm, columns) = fitted_gbm();
+    let json = String::from_utf8(to_bytes(&gbm, Some("json")).unwrap()).unwrap();
+    assert!(json.starts_with(r#"{"magic":"#));
+
+    let mut tree = Tree::new(TreeParameters::new(None, None));
+    match from_bytes(&mut tree, json.as_bytes(), None) {
+        Err(WoodsError::WrongModelKind { expected, found }) => {
+            assert_eq!((expected.as_str(), found.as_str()), ("DecisionTree", "GradientBoosting"));
+        },
+        result => panic!("unexpected result of loading wrong model kind: {:?}", result.err()),
+    }
+
+    let newer = json.replacen(r#""format_version":1"#, r#""format_version":1000"#, 1);
+    assert!(matches!(from_bytes(&mut empty_gbm(), newer.as_bytes(), None), Err(WoodsError::UnsupportedVersion(1000))));
+
+    // envelope of older version is upgraded through JSON migrations
+    let older = json.replacen(r#""format_version":1"#, r#""format_version":0"#, 1);
+    let mut loaded = empty_gbm();
+    assert_eq!(from_bytes(&mut loaded, older.as_bytes(), None).unwrap().format_version, 0);
+    assert_eq!(loaded.predict(&columns.view()).unwrap(), gbm.predict(&columns.view()).unwrap());
+
+    let truncated = &json[..json.len() - 10];
+    assert!(matches!(from_bytes(&mut empty_gbm(), truncated.as_bytes(), None), Err(WoodsError::CorruptedModel(_))));
+}
+
+#[test]
+fn raw_json_model_of_older_versions() {
+    let mut gbm = empty_gbm();
+    let header = load(&mut gbm, "tests/data/legacy_gbm.json", None).unwrap();
+    assert_eq!((header.format_version, header.model_kind.as_str()), (0, "GradientBoosting"));
+    let columns = Array2::from_shape_vec((2, 3), vec![0.0, 1.0, 0.0, 0.0, 0.0, 1.0]).unwrap();
+    // mean + learning rate * (first tree + second tree)
+    assert_eq!(gbm.predict(&columns.view()).unwrap().to_vec(), vec![1.0, 2.25, 2.0]);
+}
+
+#[test]
+fn truncated_or_wrong_magic_bytes_are_rejected() {
+    let (gbm, _) = fitted_gbm();
+    for &format in &["bincode", "msgpack", "cbor", "json", "bincode+zstd", "json+gzip"] {
+        let bytes = to_bytes(&gbm, Some(format)).unwrap();
+        for &len in &[0, 3, 8, bytes.len() / 2, bytes.len() - 1] {
+            for &read_format in &[None, Some(format)] {
+                assert!(from_bytes(&mut empty_gbm(), &bytes[..len], read_format).is_err(),
+                        "{} bytes of {} read as {:?}", len, format, read_format);
+            }
+        }
+    }
+    // pickle state is bincode envelope
+    for &format in &["bincode", "msgpack", "cbor"] {
+        let mut bytes = to_bytes(&gbm, Some(format)).unwrap();
+        bytes[4] = b'X';
+        for &read_format in &[None, Some(format)] {
+            assert!(from_bytes(&mut empty_gbm(), &bytes, read_format).is_err(),
+                    "wrong magic of {} read as {:?}", format, read_format);
+        }
+    }
+    let json = String::from_utf8(to_bytes(&gbm, Some("json")).unwrap()).unwrap().replacen("WOODS", "WOODX", 1);
+    assert!(matches!(from_bytes(&mut empty_gbm(), json.as_bytes(), None), Err(WoodsError::CorruptedModel(_))));
+}
+
+/// Samples of the legacy models: two binary features and a constant one.
+fn legacy_columns() -> Array2<f64> {
+    Array2::from_shape_vec((3, 4), vec![0.0, 0.0, 1.0, 1.0,
+                                        0.0, 1.0, 0.0, 1.0,
+                                        0.0, 0.0, 0.0, 0.0]).unwrap()
+}
+
+#[test]
+fn raw_bincode_models_of_older_versions() {
+    let columns = legacy_columns();
+    for &format in &[None, Some("bincode")] {
+        let mut tree = Tree::new(TreeParameters::new(None, None));
+        let header = load(&mut tree, "tests/data/legacy_tree.bin", format).unwrap();
+        assert_eq!((header.format_version, header.model_kind.as_str()), (0, "DecisionTree"));
+        assert_eq!(tree.predict(&columns.view()).unwrap().to_vec(), vec![0.0, 2.0, 2.5, 3.5]);
+
+        let mut json_tree = Tree::new(TreeParameters::new(None, None));
+        load(&mut json_tree, "tests/data/legacy_tree.json", None).unwrap();
+        assert_eq!(tree.explicit_nodes().unwrap(), json_tree.explicit_nodes().unwrap());
+
+        let columns = columns.slice(ndarray::s![..2, ..]).to_owned();
+        let mut gbm = empty_gbm();
+        assert_eq!(load(&mut gbm, "tests/data/legacy_gbm.bin", format).unwrap().format_version, 0);
+        assert_eq!(gbm.predict(&columns.view()).unwrap().to_vec(), vec![1.0, 2.0, 2.25, 2.75]);
+        let mut json_gbm = empty_gbm();
+        load(&mut json_gbm, "tests/data/legacy_gbm.json", None).unwrap();
+        assert_eq!(gbm.predict(&columns.view()).unwrap(), json_gbm.predict(&columns.view()).unwrap());
+
+        // both layers are the legacy boosting of the original features, the second one has learning rate 0.5
+        let mut deep = DeepGbm::new(DeepBoostingParameters::default());
+        assert_eq!(load(&mut deep, "tests/data/legacy_deep_gbm.bin", format).unwrap().format_version, 0);
+        assert_eq!(deep.predict(&columns.view()).unwrap().to_vec(), vec![1.5, 3.0, 3.375, 4.125]);
+    }
+}
+
+#[test]
+fn raw_bincode_models_of_unsupported_kinds() {
+    // older versions had no `float32` models
+    let mut gbm = TreeGBM::<f32>::new(GradientBoostingParameters::new(TreeParameters::new(None, None), None, None));
+    assert!(matches!(load(&mut gbm, "tests/data/legacy_gbm.bin", None), Err(WoodsError::UnsupportedVersion(0))));
+    // truncated model
+    let bytes = std::fs::read("tests/data/legacy_gbm.bin").unwrap();
+    assert!(matches!(from_bytes(&mut empty_gbm(), &bytes[..bytes.len() - 4], None), Err(WoodsError::CorruptedModel(_))));
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use ndarray::Array2;
+use woods::ensemble::boosting::GradientBoostingParameters;
+use woods::ensemble::deep_boosting::DeepBoostingParameters;
+use woods::estimator::{ConstructibleWithArg, ConstructibleWithCopyArg, Estimator, PredictContributions};
+use woods::tree::TreeParameters;
+use woods::utils::serialization::load;
+use common::{samples, DeepGbm, Gbm, Tree};
+
+/// Check that contributions rows, the bias included, sum up to predictions.
+fn assert_additive<M: PredictContributions<F = f64>>(model: &M, columns: &Array2<f64>) {
+    let predictions = model.predict(&columns.view()).unwrap();
+    let contributions = model.predict_contributions(&columns.view()).unwrap();
+    assert_eq!(contributions.dim(), (columns.dim().1, columns.dim().0 + 1));
+    for (row, prediction) in contributions.outer_iter().zip(predictions.iter()) {
+        assert!((row.sum() - prediction).abs() < 1e-6 * (1.0 + prediction.abs()),
+                "contributions {} sum to {}, prediction is {}", row, row.sum(), prediction);
+    }
+}
+
+#[test]
+fn tree_contributions_are_additive() {
+    let (columns, target) = samples(3, 100);
+    let mut tree = Tree::new(TreeParameters::new(Some(4), None));
+    tree.fit(&columns.view(), &target.view()).unwrap();
+    assert_additive(&tree, &columns);
+}
+
+#[test]
+fn gbm_contributions_are_additive() {
+    let (columns, target) = samples(3, 100);
+    let mut gbm = Gbm::new(GradientBoostingParameters::new(TreeParameters::new(Some(3), None), Some(20), Some(0.1)));
+    gbm.fit(&columns.view(), &target.view()).unwrap();
+    assert_additive(&gbm, &columns);
+}
+
+#[test]
+fn deep_boosting_contributions_are_additive() {
+    let (columns, target) = samples(3, 100);
+    let mut params = DeepBoostingParameters::new(Some(3), Some(2), None);
+    let layer_tree_params = TreeParameters::new(Some(3), None);
+    params.layer_params = Some(GradientBoostingParameters::new(layer_tree_params, Some(10), Some(0.1)));
+    let mut deep = DeepGbm::new(params);
+    deep.fit(&columns.view(), &target.view()).unwrap();
+    assert_additive(&deep, &columns);
+}
+
+#[test]
+fn contributions_of_models_without_node_samples_are_additive() {
+    let columns = Array2::from_shape_vec((3, 4), vec![0.0, 0.0, 1.0, 1.0,
+                                                      0.0, 1.0, 0.0, 1.0,
+                                                      0.0, 0.0, 0.0, 0.0]).unwrap();
+    let mut tree = Tree::new(TreeParameters::new(None, None));
+    load(&mut tree, "tests/data/legacy_tree.json", None).unwrap();
+    assert_additive(&tree, &columns);
+    // equal weights of children: the bias is the mean of leaves
+    let contributions = tree.predict_contributions(&columns.view()).unwrap();
+    assert!(contributions.column(3).iter().all(|bias| (bias - 2.0).abs() < 1e-12));
+
+    let mut gbm = Gbm::new(GradientBoostingParameters::new(TreeParameters::new(None, None), None, None));
+    load(&mut gbm, "tests/data/legacy_gbm.json", None).unwrap();
+    assert_additive(&gbm, &columns.slice(ndarray::s![..2, ..]).to_owned());
+}
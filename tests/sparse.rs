@@ -0,0 +1,107 @@
+mod common;
+
+use ndarray::{arr2, s, Array1, Array2, Axis};
+use woods::ensemble::boosting::GradientBoostingParameters;
+use woods::error::WoodsError;
+use woods::estimator::{ConstructibleWithCopyArg, SparseEstimator};
+use woods::tree::TreeParameters;
+use woods::utils::sparse::SparseColumns;
+use common::{Gbm, Tree};
+
+const N_FEATURES: usize = 6;
+
+/// Binary features (bits of sample index), any split threshold of a feature makes the same partition,
+/// so sparse and dense models are equal. Target weights of features are distinct, splits have no ties.
+fn binary_samples(n_samples: usize) -> (Array2<f64>, Array1<f64>) {
+    let weights = [5.3, 3.1, 1.7, 0.9, 0.45, 0.2];
+    let columns = Array2::from_shape_fn((N_FEATURES, n_samples), |(i, j)| ((j >> i) & 1) as f64);
+    let target = columns.map_axis(Axis(0), |x| x.iter().zip(&weights).map(|(v, w)| v * w).sum());
+    (columns, target)
+}
+
+fn tree_params(depth: u8) -> TreeParameters {
+    let mut params = TreeParameters::new(Some(depth), None);
+    params.max_features = N_FEATURES;
+    params
+}
+
+fn assert_close(a: &Array1<f64>, b: &Array1<f64>) {
+    assert!(a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-9), "{} != {}", a, b);
+}
+
+/// Fit `est` on dense and `sparse_est` on sparse samples and compare their predictions.
+fn check_sparse_fit<E: SparseEstimator<F = f64>>(mut est: E, mut sparse_est: E) {
+    let (columns, target) = binary_samples(64);
+    let sparse = SparseColumns::from_dense(&columns.view());
+    assert_eq!(sparse.nnz(), N_FEATURES * 32);
+
+    est.fit(&columns.view(), &target.view()).unwrap();
+    sparse_est.fit_sparse(&sparse, &target.view()).unwrap();
+    let predictions = est.predict(&columns.view()).unwrap();
+    assert_close(&sparse_est.predict(&columns.view()).unwrap(), &predictions);
+    assert_close(&sparse_est.predict_sparse(&sparse).unwrap(), &predictions);
+    assert_eq!(est.predict_sparse(&sparse).unwrap(), predictions);
+}
+
+#[test]
+fn csr_and_csc_round_trip() {
+    // samples matrix with shape (3, 4)
+    let samples = arr2(&[[1.0, 0.0, 2.0, 0.0],
+                         [0.0, 0.0, 0.0, 0.0],
+                         [0.0, 3.0, 4.0, -5.0]]);
+    let columns = samples.t().to_owned();
+
+    let csr = SparseColumns::from_csr(4, vec![0, 2, 2, 5], vec![0, 2, 1, 2, 3], vec![1.0, 2.0, 3.0, 4.0, -5.0]).unwrap();
+    assert_eq!(csr.dim(), (4, 3));
+    assert_eq!(csr.to_dense(), columns);
+    // indices of CSC are sorted
+    let csc = SparseColumns::from_csc(3, vec![0, 1, 2, 4, 5], vec![0, 2, 2, 0, 2], vec![1.0, 3.0, 4.0, 2.0, -5.0]).unwrap();
+    assert_eq!(csc.to_dense(), columns);
+    assert_eq!(csc.feature(2), (&[0, 2][..], &[2.0, 4.0][..]));
+    assert_eq!((csc.get(3, 2), csc.get(3, 1)), (-5.0, 0.0));
+
+    let dense = SparseColumns::from_dense(&columns.view());
+    assert_eq!(dense.nnz(), 5);
+    assert_eq!(dense.to_dense(), columns);
+}
+
+#[test]
+fn invalid_compressed_matrices() {
+    let invalid_data = |result: Result<SparseColumns<f64>, WoodsError>| matches!(result, Err(WoodsError::InvalidData(_)));
+    // duplicate index
+    assert!(invalid_data(SparseColumns::from_csc(3, vec![0, 2], vec![1, 1], vec![1.0, 2.0])));
+    assert!(invalid_data(SparseColumns::from_csr(3, vec![0, 2], vec![1, 1], vec![1.0, 2.0])));
+    // index out of range
+    assert!(invalid_data(SparseColumns::from_csc(3, vec![0, 1], vec![3], vec![1.0])));
+    assert!(invalid_data(SparseColumns::from_csr(2, vec![0, 1], vec![2], vec![1.0])));
+    // `indptr` doesn't end with number of values or decreases
+    assert!(invalid_data(SparseColumns::from_csc(3, vec![0, 1], vec![0, 1], vec![1.0, 2.0])));
+    assert!(invalid_data(SparseColumns::from_csc(3, vec![0, 2, 1, 2], vec![0, 1], vec![1.0, 2.0])));
+    assert!(invalid_data(SparseColumns::from_csr(3, vec![], vec![], vec![])));
+    assert!(matches!(SparseColumns::from_csc(3, vec![0, 2], vec![0, 1], vec![1.0]),
+                     Err(WoodsError::ShapeMismatch { what: "sparse indices", .. })));
+}
+
+#[test]
+fn sparse_tree_is_equal_to_dense_tree() {
+    check_sparse_fit(Tree::new(tree_params(3)), Tree::new(tree_params(3)));
+}
+
+#[test]
+fn sparse_gbm_is_equal_to_dense_gbm() {
+    let params = GradientBoostingParameters::new(tree_params(2), Some(10), Some(0.3));
+    check_sparse_fit(Gbm::new(params), Gbm::new(params));
+}
+
+#[test]
+fn sparse_input_errors() {
+    let (columns, target) = binary_samples(8);
+    let sparse = SparseColumns::from_dense(&columns.view());
+    let mut tree = Tree::new(tree_params(2));
+    assert!(matches!(tree.predict_sparse(&sparse), Err(WoodsError::NotFitted)));
+    assert!(matches!(tree.fit_sparse(&sparse, &target.slice(s![..7])),
+                     Err(WoodsError::ShapeMismatch { what: "target samples", .. })));
+    tree.fit_sparse(&sparse, &target.view()).unwrap();
+    let fewer_features = SparseColumns::from_dense(&columns.slice(s![..3, ..]));
+    assert!(matches!(tree.predict_sparse(&fewer_features), Err(WoodsError::ShapeMismatch { what: "features", .. })));
+}
@@ -0,0 +1,39 @@
+mod common;
+
+use ndarray::{Array1, Array2};
+use woods::estimator::Estimator;
+use woods::tree::{ExplicitNode, TreeParameters};
+use common::Tree;
+
+/// Samples with 5 features, only feature 0 is not constant, target depends on feature 0.
+fn one_informative_feature(n_samples: usize) -> (Array2<f64>, Array1<f64>) {
+    let columns = Array2::from_shape_fn((5, n_samples), |(i, j)| if i == 0 { j as f64 } else { 1.0 });
+    let target = columns.row(0).mapv(|v| if v < n_samples as f64 / 2.0 { 0.0 } else { 1.0 });
+    (columns, target)
+}
+
+#[test]
+fn all_max_features_split_informative_feature() {
+    // large enough to evaluate candidate features in parallel
+    let (columns, target) = one_informative_feature(5000);
+    let mut params = TreeParameters::new(Some(2), None);
+    params.max_features = 5;
+    for _ in 0..10 {
+        let mut tree = Tree::new(params);
+        tree.fit(&columns.view(), &target.view()).unwrap();
+        match &tree.explicit_nodes().unwrap()[0] {
+            ExplicitNode::Split { feature, .. } => assert_eq!(*feature, 0),
+            node => panic!("root is not split: {:?}", node),
+        }
+    }
+}
+
+#[test]
+fn max_features_greater_than_n_features_uses_all_features() {
+    let (columns, target) = one_informative_feature(100);
+    let mut params = TreeParameters::new(Some(1), None);
+    params.max_features = 100;
+    let mut tree = Tree::new(params);
+    tree.fit(&columns.view(), &target.view()).unwrap();
+    assert!(matches!(tree.explicit_nodes().unwrap()[0], ExplicitNode::Split { feature: 0, .. }));
+}
@@ -0,0 +1,87 @@
+mod common;
+
+use ndarray::{arr2, Array2};
+use woods::ensemble::boosting::GradientBoostingParameters;
+use woods::error::WoodsError;
+use woods::estimator::{ConstructibleWithCopyArg, Estimator};
+use woods::export::xgboost::{from_xgboost_json, to_xgboost_json};
+use woods::tree::{ExplicitNode, TreeParameters};
+use woods::utils::numerics::next_down;
+use common::{samples, Gbm};
+
+/// Samples of fixture models with features `a` and `b`, `x < 0.5` and `x < 2` splits are on the boundaries.
+fn fixture_columns() -> Array2<f64> {
+    arr2(&[[0.0, 0.5, 0.5, 2.0, f64::NAN, 0.49],
+           [0.0, 0.0, 1.0, 5.0, f64::NAN, 0.99]])
+}
+
+/// Sums of leaf values of fixture trees, without base margin.
+const FIXTURE_TREE_SUMS: [f64; 6] = [0.4, 0.1, -0.15, -0.6, 0.15, 0.4];
+
+fn assert_close(a: &[f64], b: &[f64]) {
+    assert!(a.len() == b.len() && a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-12), "{:?} != {:?}", a, b);
+}
+
+#[test]
+fn import_json_model() {
+    let ensemble = from_xgboost_json(&std::fs::read_to_string("tests/data/xgboost_model.json").unwrap(), None).unwrap();
+    assert_eq!(ensemble.features.n_features, 2);
+    assert_eq!(ensemble.features.names, Some(vec!["a".to_string(), "b".to_string()]));
+    // base score `0.8` of `binary:logistic` is folded to margin `ln(0.8 / 0.2)`
+    assert!((ensemble.base_value - 4.0f64.ln()).abs() < 1e-12);
+    let expected: Vec<f64> = FIXTURE_TREE_SUMS.iter().map(|sum| sum + 4.0f64.ln()).collect();
+    assert_close(&ensemble.predict(&fixture_columns().view()).unwrap().to_vec(), &expected);
+
+    // `x < split_condition` is `x <= next_down(split_condition)`
+    match &ensemble.trees[0].1[0] {
+        ExplicitNode::Split { feature, threshold, default_left, gain, samples, .. } => {
+            assert_eq!((*feature, *threshold, *default_left, *gain, *samples), (0, next_down(0.5), true, 4.5, 20));
+        },
+        node => panic!("split is expected, found {:?}", node),
+    }
+}
+
+#[test]
+fn import_json_dump() {
+    let text = std::fs::read_to_string("tests/data/xgboost_dump.json").unwrap();
+    let ensemble = from_xgboost_json(&text, None).unwrap();
+    assert_eq!((ensemble.features.n_features, ensemble.base_value), (0, 0.0));
+    assert_close(&ensemble.predict(&fixture_columns().view()).unwrap().to_vec(), &FIXTURE_TREE_SUMS);
+
+    // dump is equal to the model with given base score
+    let model = from_xgboost_json(&std::fs::read_to_string("tests/data/xgboost_model.json").unwrap(), Some(0.0)).unwrap();
+    assert_eq!(model.base_value, 0.0);
+    assert_eq!(model.trees, ensemble.trees);
+}
+
+#[test]
+fn export_json_dump() {
+    let ensemble = from_xgboost_json(&std::fs::read_to_string("tests/data/xgboost_model.json").unwrap(), None).unwrap();
+    let dump: serde_json::Value = serde_json::from_str(&to_xgboost_json(&ensemble).unwrap()).unwrap();
+    // thresholds are converted back to split conditions, base margin is added to the first tree
+    assert_eq!(dump[0]["split_condition"], 0.5);
+    assert_eq!(dump[1]["children"][1]["split_condition"], 2.0);
+    assert_eq!(dump[0]["missing"], 1);
+    assert_eq!(dump[1]["missing"], 2);
+    assert_eq!(dump[0]["children"][0]["leaf"], 0.1 + ensemble.base_value);
+
+    let (columns, target) = samples(3, 50);
+    let mut gbm = Gbm::new(GradientBoostingParameters::new(TreeParameters::new(Some(3), None), Some(5), None));
+    gbm.fit(&columns.view(), &target.view()).unwrap();
+    let imported = from_xgboost_json(&to_xgboost_json(&gbm).unwrap(), None).unwrap();
+    let (predictions, imported_predictions) = (gbm.predict(&columns.view()).unwrap(), imported.predict(&columns.view()).unwrap());
+    assert!(predictions.iter().zip(&imported_predictions).all(|(a, b)| (a - b).abs() < 1e-9));
+}
+
+#[test]
+fn import_errors() {
+    let model = std::fs::read_to_string("tests/data/xgboost_model.json").unwrap();
+    let corrupted = |text: &str| matches!(from_xgboost_json(text, None), Err(WoodsError::CorruptedModel(_)));
+    let unsupported = |text: &str| matches!(from_xgboost_json(text, None), Err(WoodsError::UnsupportedModel(_)));
+    assert!(corrupted("{"));
+    assert!(corrupted(&model.replace("\"left_children\": [1, -1, -1]", "\"left_children\": [1, -1]")));
+    assert!(corrupted(&model.replace("\"split_indices\": [1, 0, 0, 0, 0]", "\"split_indices\": [2, 0, 0, 0, 0]")));
+    assert!(unsupported(&model.replace("\"name\": \"gbtree\"", "\"name\": \"gblinear\"")));
+    assert!(unsupported(&model.replace("\"num_class\": \"0\"", "\"num_class\": \"3\"")));
+    assert!(unsupported(&model.replace("\"split_type\": [0, 0, 0]", "\"split_type\": [1, 0, 0]")));
+}